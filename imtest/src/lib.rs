@@ -4,33 +4,49 @@
 #![cfg_attr(not(test), allow(dead_code))]
 
 mod imtest {
-    use im::{HashMap, Vector};
+    use std::{
+        fmt::{self, Debug},
+        hash::{Hash, Hasher},
+    };
+
+    use persistent::backend::backend::{Backend, PersistentMap, PersistentVector};
     use serde::{Deserialize, Serialize};
-    #[derive(Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
-    pub struct State<T>
+
+    persistent::selected_backend!();
+
+    type Data<T, B> = <B as Backend>::Map<String, T>;
+    type History<T, B> = <B as Backend>::Vector<Data<T, B>>;
+
+    #[derive(Deserialize, Serialize)]
+    #[serde(bound(
+        serialize = "History<T, B>: Serialize, Data<T, B>: Serialize",
+        deserialize = "History<T, B>: Deserialize<'de>, Data<T, B>: Deserialize<'de>"
+    ))]
+    pub struct State<T, B: Backend = Selected>
     where
         T: Clone + Send + Sync,
     {
-        history: Vector<HashMap<String, T>>,
-        data: HashMap<String, T>,
+        history: History<T, B>,
+        data: Data<T, B>,
     }
-    impl<T> State<T>
+    impl<T, B> State<T, B>
     where
         T: Clone + Send + Sync + PartialEq + Eq,
+        B: Backend,
     {
         pub fn new() -> Self {
             Self {
-                history: Vector::new(),
-                data: HashMap::new(),
+                history: PersistentVector::new(),
+                data: PersistentMap::new(),
             }
         }
         pub fn insert(&mut self, key: String, value: T) {
             self.history.push_back(self.data.clone());
-            self.data.clone().insert(key, value);
+            self.data.insert(key, value);
         }
         pub fn remove(&mut self, key: &str) {
             self.history.push_back(self.data.clone());
-            self.data.remove(key);
+            self.data.remove(&key.to_string());
         }
         pub fn len(&self) -> usize {
             self.data.len()
@@ -39,16 +55,17 @@ mod imtest {
             self.history.len()
         }
         pub fn get(&self, key: &str) -> Option<T> {
-            self.data.get(key).cloned()
+            self.data.get(&key.to_string()).cloned()
         }
         // return an immutable copy of the data
-        pub fn reader(&self) -> &HashMap<String, T> {
+        pub fn reader(&self) -> &Data<T, B> {
             &self.data
         }
     }
-    impl<T> Clone for State<T>
+    impl<T, B> Clone for State<T, B>
     where
         T: Clone + Send + Sync + PartialEq + Eq,
+        B: Backend,
     {
         fn clone(&self) -> Self {
             Self {
@@ -57,23 +74,74 @@ mod imtest {
             }
         }
     }
-    impl<T> Default for State<T>
+    impl<T, B> Default for State<T, B>
     where
         T: Clone + Send + Sync + PartialEq + Eq,
+        B: Backend,
     {
         fn default() -> Self {
             Self::new()
         }
     }
+    // written out rather than derived, derives would ask the backend itself
+    // to be Debug, PartialEq and Hash
+    impl<T, B> Debug for State<T, B>
+    where
+        T: Clone + Send + Sync,
+        B: Backend,
+        History<T, B>: Debug,
+        Data<T, B>: Debug,
+    {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("State")
+                .field("history", &self.history)
+                .field("data", &self.data)
+                .finish()
+        }
+    }
+    impl<T, B> PartialEq for State<T, B>
+    where
+        T: Clone + Send + Sync,
+        B: Backend,
+        History<T, B>: PartialEq,
+        Data<T, B>: PartialEq,
+    {
+        fn eq(&self, other: &Self) -> bool {
+            self.history == other.history && self.data == other.data
+        }
+    }
+    impl<T, B> Eq for State<T, B>
+    where
+        T: Clone + Send + Sync,
+        B: Backend,
+        History<T, B>: Eq,
+        Data<T, B>: Eq,
+    {
+    }
+    impl<T, B> Hash for State<T, B>
+    where
+        T: Clone + Send + Sync,
+        B: Backend,
+        History<T, B>: Hash,
+        Data<T, B>: Hash,
+    {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            self.history.hash(state);
+            self.data.hash(state);
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
 
-    use im::HashMap;
+    use persistent::backend::backend::Backend;
+
+    use super::imtest::{self, Selected};
 
-    use super::imtest::State;
+    type State<T> = imtest::State<T, Selected>;
+    type HashMap<K, V> = <Selected as Backend>::Map<K, V>;
 
     #[test]
     fn ser_de() {
@@ -93,6 +161,7 @@ mod tests {
         assert_eq!(state.hist_len(), 2);
         state.insert("a".to_string(), 3);
         assert_eq!(state.hist_len(), 3);
+        assert_eq!(state.get("a"), Some(3));
     }
     #[test]
     fn remove_makes_history() {
@@ -121,7 +190,7 @@ mod tests {
             let ref_state = &mut state;
             let (tx1, rx1) = std::sync::mpsc::channel::<Arc<&HashMap<String, i32>>>();
 
-            let _t1 = scope
+            scope
                 .spawn(move || {
                     // mutate the state
                     ref_state.insert("t1a".to_string(), 5);
@@ -133,26 +202,13 @@ mod tests {
                 })
                 .join()
                 .unwrap();
-            let _t2 = scope
-                .spawn(move || loop {
-                    match rx1.recv() {
-                        Ok(data) => {
-                            if let Some(v) = data.get("t1a") {
-                                assert_eq!(*v, 5);
-                            }
-                            if let Some(v) = data.get("t2a") {
-                                assert_eq!(*v, 6);
-                            }
-                            if let Some(v) = data.get("t3a") {
-                                assert_eq!(*v, 7);
-                            }
-                            if let Some(v) = data.get("t4a") {
-                                assert_eq!(*v, 8);
-                            }
-                            break;
-                        }
-                        Err(_) => break,
-                    }
+            scope
+                .spawn(move || {
+                    let data = rx1.recv().unwrap();
+                    assert_eq!(data.get(&"t1a".to_string()), Some(&5));
+                    assert_eq!(data.get(&"t2a".to_string()), Some(&6));
+                    assert_eq!(data.get(&"t3a".to_string()), Some(&7));
+                    assert_eq!(data.get(&"t4a".to_string()), Some(&8));
                 })
                 .join()
                 .unwrap();
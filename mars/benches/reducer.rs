@@ -0,0 +1,70 @@
+// compares the by value Reducer with an InPlace MutReducer the way Store::update
+// applies them, on a state that is expensive to clone.
+// run with `cargo bench -p mars --bench reducer`
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use mars::simple::simple::{InPlace, Reducer};
+
+#[derive(Clone, Debug)]
+enum Action {
+    Set(usize, u64),
+}
+
+#[derive(Clone, Debug)]
+struct State {
+    samples: Vec<u64>,
+}
+
+const STATE_LEN: usize = 100_000;
+const ACTIONS: usize = 1_000;
+
+fn run<R: Reducer<State, Action>>(reducer: &R) -> Duration {
+    let mut state = State {
+        samples: vec![0; STATE_LEN],
+    };
+    let actions = (0..ACTIONS)
+        .map(|i| Action::Set(i * 7 % STATE_LEN, i as u64))
+        .collect::<Vec<Action>>();
+    let start = Instant::now();
+    for action in actions.iter() {
+        reducer.reduce_in_place(&mut state, black_box(action));
+    }
+    let elapsed = start.elapsed();
+    black_box(state);
+    elapsed
+}
+
+fn main() {
+    let by_value = |mut state: State, action: Action| match action {
+        Action::Set(i, value) => {
+            state.samples[i] = value;
+            state
+        }
+    };
+    let in_place = InPlace(|state: &mut State, action: &Action| match action {
+        Action::Set(i, value) => state.samples[*i] = *value,
+    });
+    // warm up allocator and caches before measuring
+    run(&by_value);
+    run(&in_place);
+    let by_value = run(&by_value);
+    let in_place = run(&in_place);
+    println!("{} actions on a state of {} samples", ACTIONS, STATE_LEN);
+    println!(
+        "by value: {:>12?} ({:?} per action)",
+        by_value,
+        by_value / ACTIONS as u32
+    );
+    println!(
+        "in place: {:>12?} ({:?} per action)",
+        in_place,
+        in_place / ACTIONS as u32
+    );
+    println!(
+        "in place is {:.1}x faster",
+        by_value.as_secs_f64() / in_place.as_secs_f64()
+    );
+}
@@ -0,0 +1,92 @@
+// measures what Store::update costs on top of the reducer: dispatch, tick and
+// update for each store option that keeps copies of the state or extra
+// bookkeeping per action. the store logs every action, results go to stderr.
+// run with `cargo bench -p mars --bench store > /dev/null`
+use std::{
+    fmt,
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use mars::simple::simple::{InPlace, Middleware, Store};
+
+#[derive(Clone, Debug)]
+enum Action {
+    Set(usize, u64),
+}
+
+#[derive(Clone)]
+struct State {
+    samples: Vec<u64>,
+}
+
+// the store prints the state after every action, keep that out of the numbers
+impl fmt::Debug for State {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "State({} samples)", self.samples.len())
+    }
+}
+
+// does nothing, but makes update() keep the state from before each action
+struct Noop;
+
+impl Middleware<State, Action> for Noop {}
+
+const STATE_LEN: usize = 100_000;
+const ACTIONS: usize = 1_000;
+// actions dispatched before each tick and update
+const BATCH: usize = 10;
+
+type Reducer = InPlace<fn(&mut State, &Action)>;
+type Config = fn() -> Store<Action, State, Reducer>;
+
+fn store() -> Store<Action, State, Reducer> {
+    let reducer: fn(&mut State, &Action) = |state, action| match action {
+        Action::Set(i, value) => state.samples[*i] = *value,
+    };
+    let state = State {
+        samples: vec![0; STATE_LEN],
+    };
+    Store::new(InPlace(reducer), state)
+}
+
+fn run(mut store: Store<Action, State, Reducer>) -> Duration {
+    let start = Instant::now();
+    for batch in 0..ACTIONS / BATCH {
+        for i in batch * BATCH..(batch + 1) * BATCH {
+            store
+                .dispatch(black_box(Action::Set(i * 7 % STATE_LEN, i as u64)))
+                .unwrap();
+        }
+        store.tick();
+        store.update();
+    }
+    let elapsed = start.elapsed();
+    black_box(store.state);
+    elapsed
+}
+
+fn main() {
+    let configs: Vec<(&str, Config)> = vec![
+        ("plain", store),
+        ("middleware", || store().with_middleware(Noop)),
+        ("time travel", || store().with_time_travel()),
+        ("stats", || store().with_stats(|_: &Action| "set")),
+        ("optimistic", || store().with_optimistic(|_| {}, |_| {})),
+    ];
+    eprintln!(
+        "{} actions in ticks of {} on a state of {} samples",
+        ACTIONS, BATCH, STATE_LEN
+    );
+    for (name, config) in configs {
+        // warm up allocator and caches before measuring
+        run(config());
+        let elapsed = run(config());
+        eprintln!(
+            "{:>12}: {:>12?} ({:?} per action)",
+            name,
+            elapsed,
+            elapsed / ACTIONS as u32
+        );
+    }
+}
@@ -0,0 +1,594 @@
+// allow dead_code when checking the lib without tests
+// cargo-analyzer runs `cargo check` for lib the lib with and without tests.
+// dead_code warnings in the ide won't show if the code is used in tests.
+#![cfg_attr(not(test), allow(dead_code))]
+pub mod ack {
+    use std::{
+        collections::HashMap,
+        fmt::Debug,
+        ops::Deref,
+        sync::{Arc, Condvar, Mutex, MutexGuard},
+        task::{Context, Poll, Waker},
+        time::{Duration, Instant},
+    };
+
+    use crate::{
+        queue::queue::{DispatchError, Dispatcher},
+        simple::simple::{Reducer, Store},
+    };
+
+    // (dispatcher id, sequence) of an envelope
+    pub(crate) type Stamp = (u64, u64);
+
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub enum Acked<State> {
+        // the action is part of the state committed as version, the state is
+        // included if the action was dispatched with a snapshot
+        Applied { version: u64, state: Option<State> },
+        // the coalescer merged the action into another one, which is part of
+        // the state committed as version unless a middleware dropped it
+        Coalesced { version: u64 },
+        // the action was never reduced: a middleware dropped it, a full bounded
+        // queue overwrote it or merged it into a newer action, or the store was
+        // dropped first
+        Dropped,
+    }
+
+    // what became of an envelope, the store hands these to settle
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub(crate) enum Outcome {
+        Applied,
+        Coalesced,
+        Dropped,
+    }
+
+    pub(crate) struct Inner<State> {
+        pub(crate) version: u64,
+        // the latest committed state, only kept while someone watches
+        pub(crate) state: Option<State>,
+        // the watchers and state streams alive
+        watchers: usize,
+        // the store was dropped, nothing will be committed anymore
+        pub(crate) closed: bool,
+        // the actions waited on, and whether they want a snapshot
+        wanted: HashMap<Stamp, bool>,
+        pub(crate) resolved: HashMap<Stamp, Acked<State>>,
+        // tasks polling for the next settle
+        wakers: Vec<Waker>,
+    }
+
+    /*
+     what the store shares with threads and tasks waiting on it. update() settles
+     the actions it processed and wakes every waiter, each one checks whether what
+     it waits for happened. dispatch_with_ack holds the lock while it sends, so an
+     action is always registered before update() can settle it
+    */
+    pub(crate) struct Progress<State> {
+        inner: Mutex<Inner<State>>,
+        changed: Condvar,
+    }
+
+    impl<State> Progress<State> {
+        // called when the store is dropped: the actions still waited on resolve
+        // as dropped, waiting watchers give up and streams end
+        pub(crate) fn close(&self) {
+            let mut inner = self.inner.lock().unwrap();
+            inner.closed = true;
+            let wanted = std::mem::take(&mut inner.wanted);
+            inner
+                .resolved
+                .extend(wanted.into_keys().map(|stamp| (stamp, Acked::Dropped)));
+            let wakers = std::mem::take(&mut inner.wakers);
+            drop(inner);
+            self.changed.notify_all();
+            wakers.into_iter().for_each(Waker::wake);
+        }
+        // settle keeps a clone of the committed state while anyone watches
+        pub(crate) fn watch(&self) {
+            self.inner.lock().unwrap().watchers += 1;
+        }
+        pub(crate) fn unwatch(&self) {
+            let mut inner = self.inner.lock().unwrap();
+            inner.watchers -= 1;
+            if inner.watchers == 0 {
+                inner.state = None;
+            }
+        }
+    }
+
+    // the store's handle on its progress, closes it when the store is dropped
+    pub(crate) struct Closing<State>(pub(crate) Arc<Progress<State>>);
+
+    impl<State> Deref for Closing<State> {
+        type Target = Progress<State>;
+        fn deref(&self) -> &Progress<State> {
+            &self.0
+        }
+    }
+
+    impl<State> Drop for Closing<State> {
+        fn drop(&mut self) {
+            self.0.close();
+        }
+    }
+
+    impl<State: Clone> Progress<State> {
+        pub(crate) fn new(version: u64) -> Self {
+            Progress {
+                inner: Mutex::new(Inner {
+                    version,
+                    state: None,
+                    watchers: 0,
+                    closed: false,
+                    wanted: HashMap::new(),
+                    resolved: HashMap::new(),
+                    wakers: Vec::new(),
+                }),
+                changed: Condvar::new(),
+            }
+        }
+        // called by the store after every update() and commit
+        pub(crate) fn settle(&self, version: u64, state: &State, processed: Vec<(Stamp, Outcome)>) {
+            let mut inner = self.inner.lock().unwrap();
+            inner.version = version;
+            if inner.watchers > 0 {
+                inner.state = Some(state.clone());
+            }
+            for (stamp, outcome) in processed {
+                if let Some(snapshot) = inner.wanted.remove(&stamp) {
+                    let acked = match outcome {
+                        Outcome::Applied => Acked::Applied {
+                            version,
+                            state: snapshot.then(|| state.clone()),
+                        },
+                        Outcome::Coalesced => Acked::Coalesced { version },
+                        Outcome::Dropped => Acked::Dropped,
+                    };
+                    inner.resolved.insert(stamp, acked);
+                }
+            }
+            let wakers = std::mem::take(&mut inner.wakers);
+            drop(inner);
+            self.changed.notify_all();
+            wakers.into_iter().for_each(Waker::wake);
+        }
+        // the actions waited on right now
+        pub(crate) fn waiting(&self) -> Vec<Stamp> {
+            self.inner.lock().unwrap().wanted.keys().copied().collect()
+        }
+        // waits until done returns Some, the timeout passed or the store was dropped
+        fn wait<T, F>(&self, timeout: Duration, mut done: F) -> Option<T>
+        where
+            F: FnMut(&mut Inner<State>) -> Option<T>,
+        {
+            let deadline = Instant::now() + timeout;
+            let mut inner: MutexGuard<Inner<State>> = self.inner.lock().unwrap();
+            loop {
+                if let Some(result) = done(&mut inner) {
+                    return Some(result);
+                }
+                if inner.closed {
+                    return None;
+                }
+                let left = deadline.checked_duration_since(Instant::now())?;
+                inner = self.changed.wait_timeout(inner, left).unwrap().0;
+            }
+        }
+        // the async wait: Ready once done returns Some, otherwise the task is
+        // woken by the next settle
+        pub(crate) fn poll<T, F>(&self, cx: &mut Context<'_>, done: F) -> Poll<T>
+        where
+            F: FnOnce(&mut Inner<State>) -> Option<T>,
+        {
+            let mut inner = self.inner.lock().unwrap();
+            match done(&mut inner) {
+                Some(result) => Poll::Ready(result),
+                None => {
+                    if !inner.wakers.iter().any(|w| w.will_wake(cx.waker())) {
+                        inner.wakers.push(cx.waker().clone());
+                    }
+                    Poll::Pending
+                }
+            }
+        }
+        pub(crate) fn send_with_ack<Action>(
+            self: &Arc<Self>,
+            dispatcher: &Dispatcher<Action>,
+            action: Action,
+            snapshot: bool,
+        ) -> Result<Ack<State>, DispatchError<Action>> {
+            let mut inner = self.inner.lock().unwrap();
+            let sequence = dispatcher.send_stamped(action)?;
+            let stamp = (dispatcher.id(), sequence);
+            inner.wanted.insert(stamp, snapshot);
+            Ok(Ack {
+                stamp: Some(stamp),
+                progress: self.clone(),
+            })
+        }
+    }
+
+    /*
+     resolves once update() processed the action it was returned for. actions a
+     bounded queue overwrote or merged into another action are never processed on
+     their own, they resolve as dropped with the first tick() that empties every
+     lane. all acks left resolve as dropped when the store is dropped
+    */
+    pub struct Ack<State: Clone> {
+        // None if the on_dispatch middleware dropped the action
+        pub(crate) stamp: Option<Stamp>,
+        pub(crate) progress: Arc<Progress<State>>,
+    }
+
+    impl<State: Clone> Ack<State> {
+        pub fn wait_timeout(&self, timeout: Duration) -> Option<Acked<State>> {
+            match &self.stamp {
+                Some(stamp) => self
+                    .progress
+                    .wait(timeout, |inner| inner.resolved.remove(stamp)),
+                None => Some(Acked::Dropped),
+            }
+        }
+        pub fn try_acked(&self) -> Option<Acked<State>> {
+            self.wait_timeout(Duration::ZERO)
+        }
+    }
+
+    impl<State: Clone> Drop for Ack<State> {
+        fn drop(&mut self) {
+            if let Some(stamp) = &self.stamp {
+                let mut inner = self.progress.inner.lock().unwrap();
+                inner.wanted.remove(stamp);
+                inner.resolved.remove(stamp);
+            }
+        }
+    }
+
+    // sends actions with acks from other threads, see Store::ack_dispatcher
+    pub struct AckDispatcher<Action, State: Clone> {
+        pub(crate) dispatcher: Dispatcher<Action>,
+        pub(crate) progress: Arc<Progress<State>>,
+    }
+
+    impl<Action, State: Clone> AckDispatcher<Action, State> {
+        pub fn send_with_ack(&self, action: Action) -> Result<Ack<State>, DispatchError<Action>> {
+            self.progress.send_with_ack(&self.dispatcher, action, false)
+        }
+        pub fn send_with_snapshot(
+            &self,
+            action: Action,
+        ) -> Result<Ack<State>, DispatchError<Action>> {
+            self.progress.send_with_ack(&self.dispatcher, action, true)
+        }
+    }
+
+    // follows the states the store commits from another thread
+    pub struct Watcher<State: Clone> {
+        progress: Arc<Progress<State>>,
+    }
+
+    impl<State: Clone> Watcher<State> {
+        fn new(progress: Arc<Progress<State>>) -> Self {
+            progress.watch();
+            Watcher { progress }
+        }
+        pub fn version(&self) -> u64 {
+            self.progress.inner.lock().unwrap().version
+        }
+        // waits for a committed state the predicate accepts, the state the store
+        // had when the watcher was created counts too. returns the version and state
+        pub fn wait_until<P>(&self, mut predicate: P, timeout: Duration) -> Option<(u64, State)>
+        where
+            P: FnMut(&State) -> bool,
+        {
+            self.progress.wait(timeout, |inner| match &inner.state {
+                Some(state) if predicate(state) => Some((inner.version, state.clone())),
+                _ => None,
+            })
+        }
+        pub fn wait_for_version(&self, version: u64, timeout: Duration) -> Option<State> {
+            self.progress.wait(timeout, |inner| match &inner.state {
+                Some(state) if inner.version >= version => Some(state.clone()),
+                _ => None,
+            })
+        }
+    }
+
+    impl<State: Clone> Drop for Watcher<State> {
+        fn drop(&mut self) {
+            self.progress.unwatch();
+        }
+    }
+
+    impl<Action, State, RootReducer> Store<Action, State, RootReducer>
+    where
+        Action: Debug + Clone,
+        State: Debug + Clone,
+        RootReducer: Reducer<State, Action> + Send + Sync + 'static,
+    {
+        // counts the states update() and time travel committed, starting at 0
+        pub fn version(&self) -> u64 {
+            self.version
+        }
+        pub(crate) fn progress(&mut self) -> &Arc<Progress<State>> {
+            let version = self.version;
+            &self
+                .progress
+                .get_or_insert_with(|| Closing(Arc::new(Progress::new(version))))
+                .0
+        }
+        // dispatch, returning an ack that resolves when update() processed the action
+        pub fn dispatch_with_ack(
+            &mut self,
+            action: Action,
+        ) -> Result<Ack<State>, DispatchError<Action>>
+        where
+            Action: Send + Sync,
+        {
+            self.dispatch_acked(action, false)
+        }
+        // like dispatch_with_ack, the ack also carries the committed state
+        pub fn dispatch_with_snapshot(
+            &mut self,
+            action: Action,
+        ) -> Result<Ack<State>, DispatchError<Action>>
+        where
+            Action: Send + Sync,
+        {
+            self.dispatch_acked(action, true)
+        }
+        pub(crate) fn dispatch_acked(
+            &mut self,
+            action: Action,
+            snapshot: bool,
+        ) -> Result<Ack<State>, DispatchError<Action>>
+        where
+            Action: Send + Sync,
+        {
+            let progress = self.progress().clone();
+            match self.on_dispatch(action) {
+                Some(action) => progress.send_with_ack(&self.dispatcher, action, snapshot),
+                None => Ok(Ack {
+                    stamp: None,
+                    progress,
+                }),
+            }
+        }
+        // sends with acks from other threads. like a cloned dispatcher it skips
+        // the on_dispatch middleware
+        pub fn ack_dispatcher(&mut self) -> AckDispatcher<Action, State> {
+            AckDispatcher {
+                dispatcher: self.dispatcher.clone(),
+                progress: self.progress().clone(),
+            }
+        }
+        // like ack_dispatcher, for the named lane
+        pub fn lane_ack_dispatcher(&mut self, name: &str) -> Option<AckDispatcher<Action, State>> {
+            let dispatcher = self.lane(name)?;
+            Some(AckDispatcher {
+                dispatcher,
+                progress: self.progress().clone(),
+            })
+        }
+        pub fn watcher(&mut self) -> Watcher<State> {
+            let watcher = Watcher::new(self.progress().clone());
+            watcher
+                .progress
+                .settle(self.version, &self.state, Vec::new());
+            watcher
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        thread,
+        time::Duration,
+    };
+
+    use super::ack::Acked;
+    use crate::{
+        coalesce::coalesce::Coalescer,
+        queue::queue::{Backpressure, QueueConfig},
+        simple::simple::{Middleware, Store},
+    };
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Action {
+        Add(i32),
+        Ignored,
+    }
+
+    type R = fn(i32, Action) -> i32;
+    const REDUCER: R = |state, action| match action {
+        Action::Add(n) => state + n,
+        Action::Ignored => state,
+    };
+
+    struct DropIgnored;
+    impl Middleware<i32, Action> for DropIgnored {
+        fn on_tick(&self, action: Action) -> Option<Action> {
+            match action {
+                Action::Ignored => None,
+                action => Some(action),
+            }
+        }
+    }
+
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    #[test]
+    fn acks_resolve_to_committed_version() {
+        let mut store = Store::<Action, i32, R>::new(REDUCER, 0).with_middleware(DropIgnored);
+        let first = store.dispatch_with_ack(Action::Add(1)).unwrap();
+        let ignored = store.dispatch_with_ack(Action::Ignored).unwrap();
+        assert_eq!(first.try_acked(), None);
+        store.tick();
+        store.update();
+        let second = store.dispatch_with_snapshot(Action::Add(2)).unwrap();
+        store.tick();
+        store.update();
+        assert_eq!(store.version(), 2);
+        assert_eq!(
+            first.wait_timeout(TIMEOUT),
+            Some(Acked::Applied {
+                version: 1,
+                state: None
+            })
+        );
+        assert_eq!(ignored.wait_timeout(TIMEOUT), Some(Acked::Dropped));
+        assert_eq!(
+            second.wait_timeout(TIMEOUT),
+            Some(Acked::Applied {
+                version: 2,
+                state: Some(3)
+            })
+        );
+    }
+
+    #[test]
+    fn other_threads_wait_for_their_actions() {
+        let mut store = Store::<Action, i32, R>::new(REDUCER, 0);
+        let dispatcher = store.ack_dispatcher();
+        let watcher = store.watcher();
+        let handle = thread::spawn(move || {
+            let ack = dispatcher.send_with_snapshot(Action::Add(5)).unwrap();
+            let acked = ack.wait_timeout(TIMEOUT);
+            let seen = watcher.wait_until(|state| *state >= 5, TIMEOUT);
+            (acked, seen)
+        });
+        while !handle.is_finished() {
+            store.tick();
+            store.update();
+            thread::yield_now();
+        }
+        let (acked, seen) = handle.join().unwrap();
+        assert_eq!(
+            acked,
+            Some(Acked::Applied {
+                version: 1,
+                state: Some(5)
+            })
+        );
+        assert_eq!(seen, Some((1, 5)));
+    }
+
+    #[test]
+    fn acks_on_different_lanes_resolve_apart() {
+        let mut store = Store::<Action, i32, R>::new(REDUCER, 0)
+            .with_lane("ui", 1, QueueConfig::Unbounded)
+            .with_tick_budget(1);
+        let default = store.lane_ack_dispatcher("default").unwrap();
+        let ui = store.lane_ack_dispatcher("ui").unwrap();
+        let slow = default.send_with_ack(Action::Add(1)).unwrap();
+        let fast = ui.send_with_ack(Action::Add(2)).unwrap();
+        // the budget only lets the higher priority lane through
+        store.tick();
+        store.update();
+        assert_eq!(
+            fast.try_acked(),
+            Some(Acked::Applied {
+                version: 1,
+                state: None
+            })
+        );
+        assert_eq!(slow.try_acked(), None);
+        store.tick();
+        store.update();
+        assert_eq!(
+            slow.try_acked(),
+            Some(Acked::Applied {
+                version: 2,
+                state: None
+            })
+        );
+    }
+
+    #[test]
+    fn merged_and_overwritten_actions_resolve() {
+        let mut store = Store::<Action, i32, R>::new(REDUCER, 0)
+            .with_coalescer(Coalescer::new().keep_last(|_: &Action| Some(())));
+        let older = store.dispatch_with_ack(Action::Add(1)).unwrap();
+        let newer = store.dispatch_with_ack(Action::Add(2)).unwrap();
+        store.tick();
+        store.update();
+        // the merged action keeps the stamp of the older one
+        assert_eq!(
+            older.try_acked(),
+            Some(Acked::Applied {
+                version: 1,
+                state: None
+            })
+        );
+        assert_eq!(newer.try_acked(), Some(Acked::Coalesced { version: 1 }));
+
+        let overwriting = QueueConfig::Bounded {
+            capacity: 2,
+            backpressure: Backpressure::OverwriteOldest,
+        };
+        let mut store = Store::<Action, i32, R>::with_queue(REDUCER, 0, overwriting);
+        let overwritten = store.dispatch_with_ack(Action::Add(1)).unwrap();
+        let kept = store.dispatch_with_ack(Action::Add(2)).unwrap();
+        store.dispatch(Action::Add(4)).unwrap();
+        store.tick();
+        store.update();
+        assert_eq!(overwritten.try_acked(), Some(Acked::Dropped));
+        assert!(matches!(kept.try_acked(), Some(Acked::Applied { .. })));
+        assert_eq!(store.state, 6);
+    }
+
+    #[test]
+    fn wait_until_times_out() {
+        let mut store = Store::<Action, i32, R>::new(REDUCER, 1);
+        let watcher = store.watcher();
+        assert_eq!(
+            watcher.wait_until(|state| *state == 1, TIMEOUT),
+            Some((0, 1))
+        );
+        assert_eq!(
+            watcher.wait_until(|state| *state == 2, Duration::from_millis(10)),
+            None
+        );
+        store.dispatch(Action::Add(1)).unwrap();
+        store.tick();
+        store.update();
+        assert_eq!(watcher.wait_for_version(1, TIMEOUT), Some(2));
+    }
+
+    // a state that counts how often it was cloned
+    #[derive(Debug)]
+    struct Counted(Arc<AtomicUsize>);
+
+    impl Clone for Counted {
+        fn clone(&self) -> Self {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Counted(self.0.clone())
+        }
+    }
+
+    #[test]
+    fn dropped_watchers_stop_the_state_clones() {
+        let clones = Arc::new(AtomicUsize::new(0));
+        let reducer: fn(Counted, ()) -> Counted = |state, _| state;
+        let mut store = Store::new(reducer, Counted(clones.clone()));
+        let cloned_by_update = |store: &mut Store<(), Counted, _>| {
+            let before = clones.load(Ordering::SeqCst);
+            store.dispatch(()).unwrap();
+            store.tick();
+            store.update();
+            clones.load(Ordering::SeqCst) - before
+        };
+        let unwatched = cloned_by_update(&mut store);
+        let watcher = store.watcher();
+        let stream = store.state_stream();
+        assert_eq!(cloned_by_update(&mut store), unwatched + 1);
+        drop(watcher);
+        assert_eq!(cloned_by_update(&mut store), unwatched + 1);
+        drop(stream);
+        assert_eq!(cloned_by_update(&mut store), unwatched);
+    }
+}
@@ -0,0 +1,254 @@
+// allow dead_code when checking the lib without tests
+// cargo-analyzer runs `cargo check` for lib the lib with and without tests.
+// dead_code warnings in the ide won't show if the code is used in tests.
+#![cfg_attr(not(test), allow(dead_code))]
+pub mod action {
+    use std::{
+        fmt::Debug,
+        hash::{Hash, Hasher},
+    };
+
+    pub use mars_derive::Action;
+    // the serde the derive's Serialize and Deserialize impls are written against
+    #[doc(hidden)]
+    pub use serde;
+
+    use crate::{
+        coalesce::coalesce::Coalescer,
+        queue::queue::DispatchError,
+        simple::simple::{Reducer, Store},
+    };
+
+    // which of the actions with the same tag and key a tick keeps
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum Coalesce {
+        Last,
+        First,
+    }
+
+    // what #[derive(Action)] knows about a variant
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct ActionMeta {
+        pub tag: &'static str,
+        pub lane: Option<&'static str>,
+        pub coalesce: Option<Coalesce>,
+    }
+
+    // implemented by #[derive(Action)], see mars_derive for the attributes
+    pub trait Action {
+        fn meta(&self) -> &'static ActionMeta;
+        // the metadata of every variant, in declaration order
+        fn all() -> &'static [ActionMeta]
+        where
+            Self: Sized;
+        // a name for the variant that stays the same when the variant is renamed
+        fn tag(&self) -> &'static str {
+            self.meta().tag
+        }
+        // whether two actions are coalesced together: they have the same tag
+        // and equal #[action(key = ...)] fields
+        fn same_key(&self, other: &Self) -> bool
+        where
+            Self: Sized,
+        {
+            self.tag() == other.tag()
+        }
+        // hashes the #[action(key = ...)] fields
+        fn hash_key(&self, _state: &mut dyn Hasher) {}
+    }
+
+    // an action standing for its coalescing key, see Action::same_key
+    struct Key<A>(A);
+
+    impl<A: Action> Hash for Key<A> {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            self.0.tag().hash(state);
+            self.0.hash_key(state);
+        }
+    }
+
+    impl<A: Action> PartialEq for Key<A> {
+        fn eq(&self, other: &Self) -> bool {
+            self.0.same_key(&other.0)
+        }
+    }
+
+    impl<A: Action> Eq for Key<A> {}
+
+    impl<A: Action + Clone + 'static> Coalescer<A> {
+        // merges the actions of variants marked #[action(coalesce = ...)] with
+        // the other actions of their variant that have the same key fields,
+        // keeping the last or the first. one rule covers both policies, so
+        // variants of one policy are no barrier for the other
+        pub fn action_meta(self) -> Self {
+            self.merge(
+                |action: &A| action.meta().coalesce.map(|_| Key(action.clone())),
+                // actions with the same key share their variant and its policy
+                |older, newer| match older.meta().coalesce {
+                    Some(Coalesce::First) => older,
+                    _ => newer,
+                },
+            )
+        }
+    }
+
+    impl<A, State, RootReducer> Store<A, State, RootReducer>
+    where
+        A: Action + Debug + Clone + Send + Sync,
+        State: Debug + Clone,
+        RootReducer: Reducer<State, A> + Send + Sync + 'static,
+    {
+        // dispatches to the lane of the action's #[action(lane = ...)], or the
+        // default lane for actions without one
+        pub fn dispatch_routed(&self, action: A) -> Result<(), DispatchError<A>> {
+            match action.meta().lane {
+                Some(lane) => self.dispatch_to(lane, action),
+                None => self.dispatch(action),
+            }
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::action::{Action, ActionMeta, Coalesce};
+    use crate::{coalesce::coalesce::Coalescer, queue::queue::QueueConfig, simple::simple::Store};
+
+    #[derive(Clone, Debug, PartialEq, Action)]
+    #[action(serde)]
+    enum Mixer {
+        #[action(
+            tag = "mixer/gain",
+            alias = "Gain",
+            coalesce = "last",
+            key = 0,
+            lane = "realtime"
+        )]
+        SetGain(usize, f32),
+        #[action(tag = "mixer/rename", coalesce = "last", key = "track")]
+        Rename {
+            track: usize,
+            name: String,
+        },
+        #[action(coalesce = "first")]
+        Move,
+        Clear,
+    }
+
+    #[test]
+    fn creators_tags_and_meta() {
+        assert_eq!(Mixer::set_gain(1, 0.5), Mixer::SetGain(1, 0.5));
+        assert_eq!(
+            Mixer::rename(2, "bass".to_string()),
+            Mixer::Rename {
+                track: 2,
+                name: "bass".to_string()
+            }
+        );
+        assert_eq!(Mixer::r#move(), Mixer::Move);
+        assert_eq!(Mixer::clear().tag(), "Clear");
+        assert_eq!(
+            *Mixer::SetGain(0, 1.0).meta(),
+            ActionMeta {
+                tag: "mixer/gain",
+                lane: Some("realtime"),
+                coalesce: Some(Coalesce::Last),
+            }
+        );
+        let tags = Mixer::all().iter().map(|m| m.tag).collect::<Vec<_>>();
+        assert_eq!(tags, ["mixer/gain", "mixer/rename", "Move", "Clear"]);
+    }
+
+    #[test]
+    fn serde_uses_tags() {
+        let rename = Mixer::rename(1, "keys".to_string());
+        let value = serde_json::to_value(&rename).unwrap();
+        assert_eq!(
+            value,
+            json!({ "type": "mixer/rename", "payload": { "track": 1, "name": "keys" } })
+        );
+        assert_eq!(serde_json::from_value::<Mixer>(value).unwrap(), rename);
+        assert_eq!(
+            serde_json::to_value(Mixer::Clear).unwrap(),
+            json!({ "type": "Clear" })
+        );
+        // logs written before the tag was pinned
+        let old = json!({ "type": "Gain", "payload": [3, 0.25] });
+        assert_eq!(
+            serde_json::from_value::<Mixer>(old).unwrap(),
+            Mixer::SetGain(3, 0.25)
+        );
+    }
+
+    #[test]
+    fn store_routes_and_coalesces_by_meta() {
+        let reducer = |mut log: Vec<Mixer>, action: Mixer| {
+            log.push(action);
+            log
+        };
+        let mut store = Store::new(reducer, Vec::new())
+            .with_lane("realtime", 10, QueueConfig::Unbounded)
+            .with_coalescer(Coalescer::new().action_meta());
+        store.dispatch_routed(Mixer::Move).unwrap();
+        store.dispatch_routed(Mixer::set_gain(0, 0.1)).unwrap();
+        store.dispatch_routed(Mixer::Move).unwrap();
+        store.dispatch_routed(Mixer::set_gain(0, 0.9)).unwrap();
+        store.tick();
+        store.update();
+        // the realtime lane drains first
+        assert_eq!(store.state, vec![Mixer::SetGain(0, 0.9), Mixer::Move]);
+    }
+
+    #[test]
+    fn policies_interleave() {
+        let reducer = |mut log: Vec<Mixer>, action: Mixer| {
+            log.push(action);
+            log
+        };
+        let mut store =
+            Store::new(reducer, Vec::new()).with_coalescer(Coalescer::new().action_meta());
+        store.dispatch(Mixer::set_gain(0, 0.1)).unwrap();
+        store.dispatch(Mixer::Move).unwrap();
+        store.dispatch(Mixer::set_gain(0, 0.2)).unwrap();
+        store.dispatch(Mixer::Move).unwrap();
+        store.dispatch(Mixer::set_gain(0, 0.3)).unwrap();
+        store.dispatch(Mixer::Clear).unwrap();
+        store.dispatch(Mixer::set_gain(0, 0.4)).unwrap();
+        store.tick();
+        store.update();
+        // only Clear, without a policy, keeps actions apart
+        assert_eq!(
+            store.state,
+            vec![
+                Mixer::SetGain(0, 0.3),
+                Mixer::Move,
+                Mixer::Clear,
+                Mixer::SetGain(0, 0.4)
+            ]
+        );
+    }
+
+    #[test]
+    fn keys_keep_tracks_apart() {
+        let reducer = |mut log: Vec<Mixer>, action: Mixer| {
+            log.push(action);
+            log
+        };
+        let mut store =
+            Store::new(reducer, Vec::new()).with_coalescer(Coalescer::new().action_meta());
+        store.dispatch(Mixer::set_gain(0, 0.1)).unwrap();
+        store.dispatch(Mixer::set_gain(1, 0.2)).unwrap();
+        store.dispatch(Mixer::set_gain(0, 0.3)).unwrap();
+        store.dispatch(Mixer::set_gain(1, 0.4)).unwrap();
+        store.tick();
+        store.update();
+        assert_eq!(
+            store.state,
+            vec![Mixer::SetGain(0, 0.3), Mixer::SetGain(1, 0.4)]
+        );
+        assert!(Mixer::set_gain(2, 0.0).same_key(&Mixer::set_gain(2, 1.0)));
+        assert!(!Mixer::set_gain(2, 0.0).same_key(&Mixer::set_gain(3, 0.0)));
+        assert!(!Mixer::set_gain(2, 0.0).same_key(&Mixer::Move));
+    }
+}
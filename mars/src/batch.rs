@@ -0,0 +1,376 @@
+// allow dead_code when checking the lib without tests
+// cargo-analyzer runs `cargo check` for lib the lib with and without tests.
+// dead_code warnings in the ide won't show if the code is used in tests.
+#![cfg_attr(not(test), allow(dead_code))]
+pub mod batch {
+    use std::{
+        fmt::{self, Debug, Display},
+        sync::{mpsc, Arc, Mutex},
+        time::Duration,
+    };
+
+    use crate::{
+        queue::queue::{DispatchError, Dispatcher},
+        simple::simple::{Reducer, Store},
+    };
+
+    // a reducer that can refuse an action
+    pub trait TryReducer<State, Action> {
+        type Error;
+        fn try_reduce(&self, state: State, action: Action) -> Result<State, Self::Error>;
+    }
+
+    impl<F, State, Action, E> TryReducer<State, Action> for F
+    where
+        F: Fn(State, Action) -> Result<State, E>,
+    {
+        type Error = E;
+        fn try_reduce(&self, state: State, action: Action) -> Result<State, E> {
+            self(state, action)
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub enum BatchError<E> {
+        // the action at index failed, the state was rolled back to before the batch
+        Failed { index: usize, error: E },
+        // the batch never reached the reducer, e.g. a middleware dropped it
+        Dropped,
+    }
+
+    impl<E: Display> Display for BatchError<E> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                BatchError::Failed { index, error } => {
+                    write!(f, "action {} of the batch failed: {}", index, error)
+                }
+                BatchError::Dropped => write!(f, "the batch was dropped before it was reduced"),
+            }
+        }
+    }
+
+    impl<E: Debug + Display> std::error::Error for BatchError<E> {}
+
+    type Outcome<E> = Result<(), BatchError<E>>;
+
+    // the sending half of a batch's reply channel. the action holding it may be
+    // cloned (middleware, time travel), only the first reduction replies
+    pub struct Reply<E> {
+        sender: Arc<Mutex<Option<mpsc::Sender<Outcome<E>>>>>,
+    }
+
+    impl<E> Clone for Reply<E> {
+        fn clone(&self) -> Self {
+            Reply {
+                sender: self.sender.clone(),
+            }
+        }
+    }
+
+    impl<E> Debug for Reply<E> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("Reply")
+        }
+    }
+
+    impl<E> Reply<E> {
+        fn send(&self, outcome: Outcome<E>) {
+            if let Some(sender) = self.sender.lock().unwrap().take() {
+                let _ = sender.send(outcome);
+            }
+        }
+    }
+
+    // the receiving half, returned to the thread that dispatched the batch
+    pub struct BatchReceipt<E> {
+        receiver: mpsc::Receiver<Outcome<E>>,
+    }
+
+    impl<E> BatchReceipt<E> {
+        // blocks until update() reduced the batch
+        pub fn wait(&self) -> Outcome<E> {
+            self.receiver.recv().unwrap_or(Err(BatchError::Dropped))
+        }
+        pub fn wait_timeout(&self, timeout: Duration) -> Option<Outcome<E>> {
+            match self.receiver.recv_timeout(timeout) {
+                Ok(outcome) => Some(outcome),
+                Err(mpsc::RecvTimeoutError::Timeout) => None,
+                Err(mpsc::RecvTimeoutError::Disconnected) => Some(Err(BatchError::Dropped)),
+            }
+        }
+        // the outcome if the batch was reduced already
+        pub fn try_result(&self) -> Option<Outcome<E>> {
+            match self.receiver.try_recv() {
+                Ok(outcome) => Some(outcome),
+                Err(mpsc::TryRecvError::Empty) => None,
+                Err(mpsc::TryRecvError::Disconnected) => Some(Err(BatchError::Dropped)),
+            }
+        }
+    }
+
+    // the action type of a store with a fallible reducer
+    #[derive(Debug)]
+    pub enum Transaction<Action, E> {
+        // a failing action leaves the state as it was
+        Action(Action),
+        // all actions apply or none do
+        Batch {
+            actions: Vec<Action>,
+            reply: Reply<E>,
+        },
+    }
+
+    // manual so errors do not need to be Clone
+    impl<Action: Clone, E> Clone for Transaction<Action, E> {
+        fn clone(&self) -> Self {
+            match self {
+                Transaction::Action(action) => Transaction::Action(action.clone()),
+                Transaction::Batch { actions, reply } => Transaction::Batch {
+                    actions: actions.clone(),
+                    reply: reply.clone(),
+                },
+            }
+        }
+    }
+
+    /*
+     adapts a TryReducer to the store's Reducer over Transactions. a failed
+     batch hands its error to the BatchReceipt, the error of an action that
+     failed on its own goes to the on_error callback, or nowhere without one
+    */
+    pub struct Fallible<R, E> {
+        reducer: R,
+        on_error: Option<Box<dyn Fn(E) + Send + Sync>>,
+    }
+
+    impl<R, E> Fallible<R, E> {
+        pub fn new(reducer: R) -> Self {
+            Fallible {
+                reducer,
+                on_error: None,
+            }
+        }
+        // called on the thread running update(), before the next action is reduced
+        pub fn on_error<F>(mut self, on_error: F) -> Self
+        where
+            F: Fn(E) + Send + Sync + 'static,
+        {
+            self.on_error = Some(Box::new(on_error));
+            self
+        }
+    }
+
+    impl<R, State, Action, E> Reducer<State, Transaction<Action, E>> for Fallible<R, E>
+    where
+        R: TryReducer<State, Action, Error = E>,
+        State: Clone,
+    {
+        fn reduce(&self, state: State, transaction: Transaction<Action, E>) -> State {
+            match transaction {
+                Transaction::Action(action) => {
+                    match self.reducer.try_reduce(state.clone(), action) {
+                        Ok(state) => state,
+                        Err(error) => {
+                            if let Some(on_error) = self.on_error.as_ref() {
+                                on_error(error);
+                            }
+                            state
+                        }
+                    }
+                }
+                Transaction::Batch { actions, reply } => {
+                    let result = actions.into_iter().enumerate().try_fold(
+                        state.clone(),
+                        |state, (index, action)| {
+                            self.reducer
+                                .try_reduce(state, action)
+                                .map_err(|error| BatchError::Failed { index, error })
+                        },
+                    );
+                    match result {
+                        Ok(state) => {
+                            reply.send(Ok(()));
+                            state
+                        }
+                        Err(error) => {
+                            reply.send(Err(error));
+                            state
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    impl<Action, State, R, E> Store<Transaction<Action, E>, State, Fallible<R, E>>
+    where
+        Action: Debug + Clone + Send + Sync,
+        State: Debug + Clone,
+        E: Debug + Send + Sync,
+        Fallible<R, E>: Reducer<State, Transaction<Action, E>> + Send + Sync + 'static,
+    {
+        // queues the actions to be reduced as one unit in a single update(). the
+        // receipt reports whether they were applied or rolled back
+        pub fn dispatch_batch(
+            &self,
+            actions: Vec<Action>,
+        ) -> Result<BatchReceipt<E>, DispatchError<Transaction<Action, E>>> {
+            let (transaction, receipt) = batch(actions);
+            self.dispatch(transaction)?;
+            Ok(receipt)
+        }
+    }
+
+    impl<Action, E> Dispatcher<Transaction<Action, E>> {
+        // dispatch_batch for threads that only hold a dispatcher. like send, this
+        // skips the store's on_dispatch middleware
+        pub fn send_batch(
+            &self,
+            actions: Vec<Action>,
+        ) -> Result<BatchReceipt<E>, DispatchError<Transaction<Action, E>>> {
+            let (transaction, receipt) = batch(actions);
+            self.send(transaction)?;
+            Ok(receipt)
+        }
+    }
+
+    fn batch<Action, E>(actions: Vec<Action>) -> (Transaction<Action, E>, BatchReceipt<E>) {
+        let (sender, receiver) = mpsc::channel();
+        let reply = Reply {
+            sender: Arc::new(Mutex::new(Some(sender))),
+        };
+        (
+            Transaction::Batch { actions, reply },
+            BatchReceipt { receiver },
+        )
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::{sync::mpsc, thread, time::Duration};
+
+    use super::batch::{BatchError, Fallible, Transaction};
+    use crate::simple::simple::{Middleware, Store};
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Action {
+        Deposit(u32),
+        Withdraw(u32),
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Overdrawn(u32);
+
+    type R = fn(u32, Action) -> Result<u32, Overdrawn>;
+    const ACCOUNT: R = |balance, action| match action {
+        Action::Deposit(n) => Ok(balance + n),
+        Action::Withdraw(n) if n <= balance => Ok(balance - n),
+        Action::Withdraw(n) => Err(Overdrawn(n - balance)),
+    };
+
+    type Account = Store<Transaction<Action, Overdrawn>, u32, Fallible<R, Overdrawn>>;
+
+    fn update(store: &mut Account) {
+        store.tick();
+        store.update();
+    }
+
+    #[test]
+    fn batch_applies_all_actions() {
+        let mut store: Account = Store::new(Fallible::new(ACCOUNT), 10);
+        let receipt = store
+            .dispatch_batch(vec![Action::Withdraw(10), Action::Deposit(5)])
+            .unwrap();
+        assert_eq!(receipt.try_result(), None);
+        update(&mut store);
+        assert_eq!(receipt.wait(), Ok(()));
+        assert_eq!(store.state, 5);
+    }
+
+    #[test]
+    fn failing_batch_rolls_back() {
+        let mut store: Account = Store::new(Fallible::new(ACCOUNT), 10);
+        store
+            .dispatch(Transaction::Action(Action::Deposit(1)))
+            .unwrap();
+        let receipt = store
+            .dispatch_batch(vec![
+                Action::Withdraw(5),
+                Action::Withdraw(5),
+                Action::Withdraw(5),
+            ])
+            .unwrap();
+        store
+            .dispatch(Transaction::Action(Action::Deposit(1)))
+            .unwrap();
+        update(&mut store);
+        assert_eq!(
+            receipt.wait(),
+            Err(BatchError::Failed {
+                index: 2,
+                error: Overdrawn(4)
+            })
+        );
+        assert_eq!(store.state, 12);
+    }
+
+    #[test]
+    fn failing_action_leaves_state() {
+        let (errors_tx, errors) = mpsc::channel();
+        let reducer = Fallible::new(ACCOUNT).on_error(move |error| {
+            errors_tx.send(error).unwrap();
+        });
+        let mut store: Account = Store::new(reducer, 3);
+        store
+            .dispatch(Transaction::Action(Action::Withdraw(4)))
+            .unwrap();
+        store
+            .dispatch(Transaction::Action(Action::Withdraw(2)))
+            .unwrap();
+        update(&mut store);
+        assert_eq!(store.state, 1);
+        assert_eq!(errors.try_iter().collect::<Vec<_>>(), vec![Overdrawn(1)]);
+    }
+
+    #[test]
+    fn outcome_reaches_dispatching_thread() {
+        let mut store: Account = Store::new(Fallible::new(ACCOUNT), 0);
+        let dispatcher = store.dispatcher.clone();
+        let (sent_tx, sent_rx) = mpsc::channel();
+        let handle = thread::spawn(move || {
+            let receipt = dispatcher.send_batch(vec![Action::Withdraw(1)]).unwrap();
+            sent_tx.send(()).unwrap();
+            receipt.wait_timeout(Duration::from_secs(10))
+        });
+        sent_rx.recv().unwrap();
+        update(&mut store);
+        assert_eq!(
+            handle.join().unwrap(),
+            Some(Err(BatchError::Failed {
+                index: 0,
+                error: Overdrawn(1)
+            }))
+        );
+    }
+
+    #[test]
+    fn dropped_batch_is_reported() {
+        struct DropBatches;
+        impl Middleware<u32, Transaction<Action, Overdrawn>> for DropBatches {
+            fn on_tick(
+                &self,
+                action: Transaction<Action, Overdrawn>,
+            ) -> Option<Transaction<Action, Overdrawn>> {
+                match action {
+                    Transaction::Batch { .. } => None,
+                    action => Some(action),
+                }
+            }
+        }
+        let mut store: Account = Store::new(Fallible::new(ACCOUNT), 0).with_middleware(DropBatches);
+        let receipt = store.dispatch_batch(vec![Action::Deposit(1)]).unwrap();
+        update(&mut store);
+        assert_eq!(receipt.wait(), Err(BatchError::Dropped));
+        assert_eq!(store.state, 0);
+    }
+}
@@ -0,0 +1,389 @@
+// allow dead_code when checking the lib without tests
+// cargo-analyzer runs `cargo check` for lib the lib with and without tests.
+// dead_code warnings in the ide won't show if the code is used in tests.
+#![cfg_attr(not(test), allow(dead_code))]
+pub mod coalesce {
+    use std::{
+        collections::HashMap,
+        fmt::Debug,
+        hash::Hash,
+        ops::Add,
+        time::{Duration, Instant},
+    };
+
+    use crate::{
+        queue::queue::{DispatchError, Dispatcher, Envelope},
+        simple::simple::{Reducer, Store},
+    };
+
+    trait Rule<Action>: Send {
+        fn coalesce(&self, envelopes: Vec<Envelope<Action>>) -> Vec<Envelope<Action>>;
+    }
+
+    struct Keyed<KeyFn, Merge> {
+        key: KeyFn,
+        merge: Merge,
+    }
+
+    impl<Action, K, KeyFn, Merge> Rule<Action> for Keyed<KeyFn, Merge>
+    where
+        K: Hash + Eq,
+        KeyFn: Fn(&Action) -> Option<K> + Send,
+        Merge: Fn(Action, Action) -> Action + Send,
+    {
+        fn coalesce(&self, envelopes: Vec<Envelope<Action>>) -> Vec<Envelope<Action>> {
+            // a slot is only empty while its action is being merged
+            let mut out: Vec<Option<Envelope<Action>>> = Vec::with_capacity(envelopes.len());
+            // where the action of each key went since the last action without a key
+            let mut slots: HashMap<K, usize> = HashMap::new();
+            for envelope in envelopes {
+                let key = match (self.key)(&envelope.action) {
+                    Some(key) => key,
+                    None => {
+                        slots.clear();
+                        out.push(Some(envelope));
+                        continue;
+                    }
+                };
+                match slots.get(&key) {
+                    Some(&slot) => {
+                        // the merged action takes the place, and stamp, of the older one
+                        let older = out[slot].take().unwrap();
+                        let action = (self.merge)(older.action, envelope.action);
+                        out[slot] = Some(Envelope { action, ..older });
+                    }
+                    None => {
+                        slots.insert(key, out.len());
+                        out.push(Some(envelope));
+                    }
+                }
+            }
+            out.into_iter().flatten().collect()
+        }
+    }
+
+    /*
+     coalescing rules tick() applies to the actions it collected, so redundant
+     actions never reach the reducer. a rule has a key function: actions with the
+     same key are merged into one, the merged action takes the place of the first
+     one. actions the key function returns None for are barriers, nothing is
+     merged across them, so a rule only has to make sure that actions with a key
+     don't care about their order relative to each other. rules run in the order
+     they were added
+    */
+    pub struct Coalescer<Action> {
+        rules: Vec<Box<dyn Rule<Action>>>,
+    }
+
+    impl<Action> Default for Coalescer<Action> {
+        fn default() -> Self {
+            Coalescer { rules: Vec::new() }
+        }
+    }
+
+    impl<Action: 'static> Coalescer<Action> {
+        pub fn new() -> Self {
+            Self::default()
+        }
+        // merge actions with the same key with fn(older, newer)
+        pub fn merge<K, KeyFn, Merge>(mut self, key: KeyFn, merge: Merge) -> Self
+        where
+            K: Hash + Eq + 'static,
+            KeyFn: Fn(&Action) -> Option<K> + Send + 'static,
+            Merge: Fn(Action, Action) -> Action + Send + 'static,
+        {
+            self.rules.push(Box::new(Keyed { key, merge }));
+            self
+        }
+        // only the newest action per key is reduced, e.g. setting a parameter
+        pub fn keep_last<K, KeyFn>(self, key: KeyFn) -> Self
+        where
+            K: Hash + Eq + 'static,
+            KeyFn: Fn(&Action) -> Option<K> + Send + 'static,
+        {
+            self.merge(key, |_, newer| newer)
+        }
+        // actions per key become one action carrying the sum of their deltas, e.g.
+        // relative knob moves. delta reads an action's delta, with builds the
+        // merged action from the newer action and the sum
+        pub fn sum<K, T, KeyFn, Delta, With>(self, key: KeyFn, delta: Delta, with: With) -> Self
+        where
+            K: Hash + Eq + 'static,
+            T: Add<Output = T>,
+            KeyFn: Fn(&Action) -> Option<K> + Send + 'static,
+            Delta: Fn(&Action) -> T + Send + 'static,
+            With: Fn(Action, T) -> Action + Send + 'static,
+        {
+            self.merge(key, move |older, newer| {
+                let sum = delta(&older) + delta(&newer);
+                with(newer, sum)
+            })
+        }
+    }
+
+    impl<Action> Coalescer<Action> {
+        pub(crate) fn coalesce(&self, envelopes: Vec<Envelope<Action>>) -> Vec<Envelope<Action>> {
+            self.rules
+                .iter()
+                .fold(envelopes, |envelopes, rule| rule.coalesce(envelopes))
+        }
+    }
+
+    impl<Action, State, RootReducer> Store<Action, State, RootReducer>
+    where
+        Action: Debug + Clone + 'static,
+        State: Debug + Clone,
+        RootReducer: Reducer<State, Action> + Send + Sync + 'static,
+    {
+        // coalesces the actions collected by every tick(), after the on_tick
+        // middleware ran. the acks of actions merged into another one resolve
+        // as Acked::Coalesced
+        pub fn with_coalescer(mut self, coalescer: Coalescer<Action>) -> Self {
+            self.coalescer = Some(coalescer);
+            self
+        }
+    }
+
+    // sends at most one action per interval. an action sent too early is held
+    // back, replacing the one held before, until poll() or a later send finds
+    // the interval passed
+    pub struct Throttled<Action> {
+        dispatcher: Dispatcher<Action>,
+        interval: Duration,
+        last_sent: Option<Instant>,
+        held: Option<Action>,
+    }
+
+    // sends an action once no other action was sent for the wait time. every
+    // send replaces the held action and restarts the wait, poll() sends it when
+    // the wait is over
+    pub struct Debounced<Action> {
+        dispatcher: Dispatcher<Action>,
+        wait: Duration,
+        held: Option<(Action, Instant)>,
+    }
+
+    impl<Action> Dispatcher<Action> {
+        pub fn throttled(&self, interval: Duration) -> Throttled<Action> {
+            Throttled {
+                dispatcher: self.clone(),
+                interval,
+                last_sent: None,
+                held: None,
+            }
+        }
+        pub fn debounced(&self, wait: Duration) -> Debounced<Action> {
+            Debounced {
+                dispatcher: self.clone(),
+                wait,
+                held: None,
+            }
+        }
+    }
+
+    /*
+     neither helper runs a timer, whoever sends has to call poll() regularly,
+     e.g. once per ui frame, for held back actions to go out. the _at variants
+     take the current time from the caller, for tests and callers with their own clock
+    */
+    impl<Action> Throttled<Action> {
+        pub fn send(&mut self, action: Action) -> Result<(), DispatchError<Action>> {
+            self.send_at(action, Instant::now())
+        }
+        pub fn send_at(
+            &mut self,
+            action: Action,
+            now: Instant,
+        ) -> Result<(), DispatchError<Action>> {
+            self.held = Some(action);
+            self.poll_at(now)
+        }
+        pub fn poll(&mut self) -> Result<(), DispatchError<Action>> {
+            self.poll_at(Instant::now())
+        }
+        pub fn poll_at(&mut self, now: Instant) -> Result<(), DispatchError<Action>> {
+            let due = match self.last_sent {
+                Some(last_sent) => now.duration_since(last_sent) >= self.interval,
+                None => true,
+            };
+            match (due, self.held.take()) {
+                (true, Some(action)) => {
+                    self.last_sent = Some(now);
+                    self.dispatcher.send(action)
+                }
+                (_, held) => {
+                    self.held = held;
+                    Ok(())
+                }
+            }
+        }
+        pub fn is_holding(&self) -> bool {
+            self.held.is_some()
+        }
+    }
+
+    impl<Action> Debounced<Action> {
+        pub fn send(&mut self, action: Action) {
+            self.send_at(action, Instant::now())
+        }
+        pub fn send_at(&mut self, action: Action, now: Instant) {
+            self.held = Some((action, now + self.wait));
+        }
+        pub fn poll(&mut self) -> Result<(), DispatchError<Action>> {
+            self.poll_at(Instant::now())
+        }
+        pub fn poll_at(&mut self, now: Instant) -> Result<(), DispatchError<Action>> {
+            match self.held.take() {
+                Some((action, due)) if now >= due => self.dispatcher.send(action),
+                held => {
+                    self.held = held;
+                    Ok(())
+                }
+            }
+        }
+        // sends the held action without waiting, e.g. when the drag ended
+        pub fn flush(&mut self) -> Result<(), DispatchError<Action>> {
+            match self.held.take() {
+                Some((action, _)) => self.dispatcher.send(action),
+                None => Ok(()),
+            }
+        }
+        pub fn is_holding(&self) -> bool {
+            self.held.is_some()
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::coalesce::Coalescer;
+    use crate::simple::simple::Store;
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Action {
+        SetParam(u32, f32),
+        Nudge(u32, i32),
+        Reset,
+    }
+
+    type State = Vec<Action>;
+    type R = fn(State, Action) -> State;
+    // records what reached the reducer
+    const RECORD: R = |mut state, action| {
+        state.push(action);
+        state
+    };
+
+    fn coalescer() -> Coalescer<Action> {
+        Coalescer::new()
+            .keep_last(|action: &Action| match action {
+                Action::SetParam(id, _) => Some(*id),
+                _ => None,
+            })
+            .sum(
+                |action: &Action| match action {
+                    Action::Nudge(id, _) => Some(*id),
+                    _ => None,
+                },
+                |action: &Action| match action {
+                    Action::Nudge(_, delta) => *delta,
+                    _ => 0,
+                },
+                |action, sum| match action {
+                    Action::Nudge(id, _) => Action::Nudge(id, sum),
+                    action => action,
+                },
+            )
+    }
+
+    #[test]
+    fn tick_coalesces_by_key() {
+        let mut store = Store::<Action, State, R>::new(RECORD, vec![]).with_coalescer(coalescer());
+        for i in 0..100 {
+            store.dispatch(Action::SetParam(1, i as f32)).unwrap();
+            store.dispatch(Action::SetParam(2, -i as f32)).unwrap();
+        }
+        store.tick();
+        assert_eq!(store.pending_actions.len(), 2);
+        store.update();
+        assert_eq!(
+            store.state,
+            vec![Action::SetParam(1, 99.0), Action::SetParam(2, -99.0)]
+        );
+    }
+
+    #[test]
+    fn nothing_is_merged_across_barriers() {
+        let mut store = Store::<Action, State, R>::new(RECORD, vec![]).with_coalescer(coalescer());
+        for action in [
+            Action::Nudge(1, 2),
+            Action::Nudge(1, 3),
+            Action::Reset,
+            Action::Nudge(1, 4),
+            Action::Nudge(2, 1),
+            Action::Nudge(1, 4),
+        ] {
+            store.dispatch(action).unwrap();
+        }
+        store.tick();
+        store.update();
+        assert_eq!(
+            store.state,
+            vec![
+                Action::Nudge(1, 5),
+                Action::Reset,
+                Action::Nudge(1, 8),
+                Action::Nudge(2, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn throttle_holds_back_until_interval() {
+        let mut store = Store::<Action, State, R>::new(RECORD, vec![]);
+        let mut throttled = store.dispatcher.throttled(Duration::from_millis(10));
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        throttled.send_at(Action::SetParam(1, 0.0), at(0)).unwrap();
+        throttled.send_at(Action::SetParam(1, 1.0), at(3)).unwrap();
+        throttled.send_at(Action::SetParam(1, 2.0), at(6)).unwrap();
+        throttled.poll_at(at(9)).unwrap();
+        assert!(throttled.is_holding());
+        throttled.poll_at(at(10)).unwrap();
+        assert!(!throttled.is_holding());
+        throttled.send_at(Action::SetParam(1, 3.0), at(15)).unwrap();
+        store.tick();
+        store.update();
+        assert_eq!(
+            store.state,
+            vec![Action::SetParam(1, 0.0), Action::SetParam(1, 2.0)]
+        );
+        throttled.poll_at(at(20)).unwrap();
+        store.tick();
+        store.update();
+        assert_eq!(store.state.last(), Some(&Action::SetParam(1, 3.0)));
+    }
+
+    #[test]
+    fn debounce_sends_after_quiet_period() {
+        let mut store = Store::<Action, State, R>::new(RECORD, vec![]);
+        let mut debounced = store.dispatcher.debounced(Duration::from_millis(10));
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        for ms in 0..5 {
+            debounced.send_at(Action::SetParam(1, ms as f32), at(ms * 5));
+            debounced.poll_at(at(ms * 5 + 1)).unwrap();
+        }
+        store.tick();
+        assert!(store.pending_actions.is_empty());
+        debounced.poll_at(at(29)).unwrap();
+        assert!(debounced.is_holding());
+        debounced.poll_at(at(30)).unwrap();
+        debounced.send_at(Action::Reset, at(31));
+        debounced.flush().unwrap();
+        store.tick();
+        store.update();
+        assert_eq!(store.state, vec![Action::SetParam(1, 4.0), Action::Reset]);
+    }
+}
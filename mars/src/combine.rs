@@ -0,0 +1,313 @@
+// allow dead_code when checking the lib without tests
+// cargo-analyzer runs `cargo check` for lib the lib with and without tests.
+// dead_code warnings in the ide won't show if the code is used in tests.
+#![cfg_attr(not(test), allow(dead_code))]
+pub mod combine {
+    use std::marker::PhantomData;
+
+    use crate::simple::simple::Reducer;
+
+    type Getter<State, Slice> = Box<dyn Fn(&State) -> Slice + Send + Sync>;
+    type Setter<State, Slice> = Box<dyn Fn(&mut State, Slice) + Send + Sync>;
+
+    // a getter/setter pair that focuses on one slice of a state
+    pub struct Lens<State, Slice> {
+        get: Getter<State, Slice>,
+        set: Setter<State, Slice>,
+    }
+
+    impl<State, Slice> Lens<State, Slice> {
+        pub fn new<Get, Set>(get: Get, set: Set) -> Self
+        where
+            Get: Fn(&State) -> Slice + Send + Sync + 'static,
+            Set: Fn(&mut State, Slice) + Send + Sync + 'static,
+        {
+            Lens {
+                get: Box::new(get),
+                set: Box::new(set),
+            }
+        }
+        pub fn get(&self, state: &State) -> Slice {
+            (self.get)(state)
+        }
+        pub fn set(&self, state: &mut State, slice: Slice) {
+            (self.set)(state, slice)
+        }
+    }
+
+    // lens!(State, field) builds a Lens onto a field of a struct, cloning the field on get
+    #[macro_export]
+    macro_rules! lens {
+        ($state:ty, $field:ident) => {
+            $crate::combine::combine::Lens::new(
+                |state: &$state| state.$field.clone(),
+                |state: &mut $state, slice| state.$field = slice,
+            )
+        };
+    }
+
+    // runs a slice reducer on the part of the state a lens focuses on
+    pub struct Focus<State, Slice, R> {
+        lens: Lens<State, Slice>,
+        reducer: R,
+    }
+
+    pub fn focus<State, Slice, R>(lens: Lens<State, Slice>, reducer: R) -> Focus<State, Slice, R> {
+        Focus { lens, reducer }
+    }
+
+    impl<State, Slice, Action, R> Reducer<State, Action> for Focus<State, Slice, R>
+    where
+        R: Reducer<Slice, Action>,
+    {
+        fn reduce(&self, mut state: State, action: Action) -> State {
+            let slice = self.reducer.reduce(self.lens.get(&state), action);
+            self.lens.set(&mut state, slice);
+            state
+        }
+    }
+
+    // a reducer made of slice reducers, each one attached to its own field through
+    // a lens. every action is handed to every slice reducer in the order they were added
+    pub struct Combined<State, Action> {
+        slices: Vec<Box<dyn Reducer<State, Action> + Send + Sync>>,
+    }
+
+    pub fn combine_reducers<State, Action>() -> Combined<State, Action> {
+        Combined { slices: Vec::new() }
+    }
+
+    impl<State, Action> Combined<State, Action>
+    where
+        State: 'static,
+        Action: 'static,
+    {
+        pub fn slice<Slice, R>(mut self, lens: Lens<State, Slice>, reducer: R) -> Self
+        where
+            Slice: 'static,
+            R: Reducer<Slice, Action> + Send + Sync + 'static,
+        {
+            self.slices.push(Box::new(focus(lens, reducer)));
+            self
+        }
+    }
+
+    impl<State, Action> Reducer<State, Action> for Combined<State, Action>
+    where
+        Action: Clone,
+    {
+        fn reduce(&self, state: State, action: Action) -> State {
+            self.slices
+                .iter()
+                .fold(state, |state, slice| slice.reduce(state, action.clone()))
+        }
+    }
+
+    // combinators for building reducer trees out of smaller reducers
+    pub trait ReducerExt<State, Action>: Reducer<State, Action> + Sized {
+        // adapts the reducer to another action type. actions mapped to None leave
+        // the state untouched, which lets a reducer pick its variant out of an app wide enum
+        fn map_action<Outer, F>(self, map: F) -> MapAction<Self, F, Action>
+        where
+            F: Fn(Outer) -> Option<Action>,
+        {
+            MapAction {
+                reducer: self,
+                map,
+                action: PhantomData,
+            }
+        }
+        // only hands the reducer the actions the predicate accepts
+        fn filter_action<F>(self, filter: F) -> FilterAction<Self, F>
+        where
+            F: Fn(&Action) -> bool,
+        {
+            FilterAction {
+                reducer: self,
+                filter,
+            }
+        }
+        // runs next on the state this reducer returned, with the same action
+        fn chain<Next>(self, next: Next) -> Chain<Self, Next>
+        where
+            Next: Reducer<State, Action>,
+        {
+            Chain { first: self, next }
+        }
+    }
+
+    impl<R, State, Action> ReducerExt<State, Action> for R where R: Reducer<State, Action> {}
+
+    pub struct MapAction<R, F, Inner> {
+        reducer: R,
+        map: F,
+        action: PhantomData<fn(Inner)>,
+    }
+
+    impl<R, F, State, Inner, Outer> Reducer<State, Outer> for MapAction<R, F, Inner>
+    where
+        R: Reducer<State, Inner>,
+        F: Fn(Outer) -> Option<Inner>,
+    {
+        fn reduce(&self, state: State, action: Outer) -> State {
+            match (self.map)(action) {
+                Some(action) => self.reducer.reduce(state, action),
+                None => state,
+            }
+        }
+    }
+
+    pub struct FilterAction<R, F> {
+        reducer: R,
+        filter: F,
+    }
+
+    impl<R, F, State, Action> Reducer<State, Action> for FilterAction<R, F>
+    where
+        R: Reducer<State, Action>,
+        F: Fn(&Action) -> bool,
+    {
+        fn reduce(&self, state: State, action: Action) -> State {
+            match (self.filter)(&action) {
+                true => self.reducer.reduce(state, action),
+                false => state,
+            }
+        }
+    }
+
+    pub struct Chain<First, Next> {
+        first: First,
+        next: Next,
+    }
+
+    impl<First, Next, State, Action> Reducer<State, Action> for Chain<First, Next>
+    where
+        First: Reducer<State, Action>,
+        Next: Reducer<State, Action>,
+        Action: Clone,
+    {
+        fn reduce(&self, state: State, action: Action) -> State {
+            let state = self.first.reduce(state, action.clone());
+            self.next.reduce(state, action)
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::combine::{combine_reducers, focus, Lens, ReducerExt};
+    use crate::{
+        lens,
+        simple::simple::{Reducer, Store},
+    };
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Mixer {
+        volume: i32,
+        muted: bool,
+        names: Vec<String>,
+    }
+
+    #[derive(Clone, Debug)]
+    enum Action {
+        Volume(i32),
+        Mute,
+        Name(String),
+        Reset,
+    }
+
+    fn mixer() -> Mixer {
+        Mixer {
+            volume: 0,
+            muted: false,
+            names: Vec::new(),
+        }
+    }
+
+    fn volume(state: i32, action: Action) -> i32 {
+        match action {
+            Action::Volume(delta) => state + delta,
+            Action::Reset => 0,
+            _ => state,
+        }
+    }
+
+    fn muted(state: bool, action: Action) -> bool {
+        match action {
+            Action::Mute => !state,
+            Action::Reset => false,
+            _ => state,
+        }
+    }
+
+    fn names(mut state: Vec<String>, action: Action) -> Vec<String> {
+        if let Action::Name(name) = action {
+            state.push(name);
+        }
+        state
+    }
+
+    #[test]
+    fn combined_reducer_updates_each_slice() {
+        let reducer = combine_reducers::<Mixer, Action>()
+            .slice(lens!(Mixer, volume), volume)
+            .slice(lens!(Mixer, muted), muted)
+            .slice(
+                Lens::new(
+                    |state: &Mixer| state.names.clone(),
+                    |state: &mut Mixer, names| state.names = names,
+                ),
+                names,
+            );
+        let mut store = Store::new(reducer, mixer());
+        store.dispatch(Action::Volume(3)).unwrap();
+        store.dispatch(Action::Mute).unwrap();
+        store.dispatch(Action::Name("kick".to_string())).unwrap();
+        store.dispatch(Action::Volume(2)).unwrap();
+        store.tick();
+        store.update();
+        assert_eq!(
+            store.state,
+            Mixer {
+                volume: 5,
+                muted: true,
+                names: vec!["kick".to_string()],
+            }
+        );
+        store.dispatch(Action::Reset).unwrap();
+        store.tick();
+        store.update();
+        assert_eq!(store.state.volume, 0);
+        assert!(!store.state.muted);
+        assert_eq!(store.state.names.len(), 1);
+    }
+
+    #[test]
+    fn combinators_build_reducer_trees() {
+        #[derive(Clone, Debug)]
+        enum App {
+            Mixer(Action),
+            Quit,
+        }
+        // only positive volume changes get through, then the volume is clamped
+        let clamp = |state: i32, _: Action| state.min(10);
+        let reducer = focus(
+            lens!(Mixer, volume),
+            (volume as fn(i32, Action) -> i32)
+                .filter_action(|action: &Action| !matches!(action, Action::Volume(d) if *d < 0))
+                .chain(clamp),
+        )
+        .map_action(|app: App| match app {
+            App::Mixer(action) => Some(action),
+            App::Quit => None,
+        });
+        let state = [
+            App::Mixer(Action::Volume(4)),
+            App::Mixer(Action::Volume(-3)),
+            App::Quit,
+            App::Mixer(Action::Volume(9)),
+        ]
+        .into_iter()
+        .fold(mixer(), |state, action| reducer.reduce(state, action));
+        assert_eq!(state.volume, 10);
+    }
+}
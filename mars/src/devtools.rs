@@ -0,0 +1,642 @@
+// allow dead_code when checking the lib without tests
+// cargo-analyzer runs `cargo check` for lib the lib with and without tests.
+// dead_code warnings in the ide won't show if the code is used in tests.
+#![cfg_attr(not(test), allow(dead_code))]
+pub mod devtools {
+    use std::{
+        fmt::Debug,
+        io::{self, BufRead, BufReader, Read, Write},
+        net::{SocketAddr, TcpListener, TcpStream},
+        path::PathBuf,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            mpsc, Arc, Mutex,
+        },
+        thread,
+    };
+
+    use serde::{de::DeserializeOwned, Serialize};
+    use serde_json::{json, Value};
+
+    use crate::{
+        queue::queue::Dispatcher,
+        simple::simple::{Middleware, Reducer, Store},
+    };
+
+    /*
+     the protocol is one json object per line in both directions.
+
+     the server sends
+       {"event":"action","seq":1,"action":..,"diff":[..]} for every applied action,
+         diff is a json patch (rfc 6902) from the state before to the state after
+       {"event":"state","state":..} answering get_state
+       {"event":"dispatched"} or {"event":"error","message":".."} answering dispatch
+
+     clients send
+       {"request":"get_state"}
+       {"request":"dispatch","action":..}
+    */
+
+    pub enum Endpoint {
+        // e.g. "127.0.0.1:0" to let the os pick a port
+        Tcp(SocketAddr),
+        #[cfg(unix)]
+        Unix(PathBuf),
+    }
+
+    pub(crate) enum Listener {
+        Tcp(TcpListener),
+        #[cfg(unix)]
+        Unix(std::os::unix::net::UnixListener),
+    }
+
+    // refuses tcp endpoints that are not loopback addresses unless
+    // allow_remote, builder names the builder method that allows them
+    pub(crate) fn check_loopback(
+        endpoint: &Endpoint,
+        allow_remote: bool,
+        builder: &str,
+    ) -> io::Result<()> {
+        match endpoint {
+            Endpoint::Tcp(addr) if !allow_remote && !addr.ip().is_loopback() => {
+                Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    format!(
+                        "{} is not a loopback address, see {}::allow_remote",
+                        addr, builder
+                    ),
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // binds endpoint, returning it with the port the os picked
+    pub(crate) fn listen(endpoint: Endpoint) -> io::Result<(Listener, Endpoint)> {
+        Ok(match endpoint {
+            Endpoint::Tcp(addr) => {
+                let listener = TcpListener::bind(addr)?;
+                let addr = listener.local_addr()?;
+                (Listener::Tcp(listener), Endpoint::Tcp(addr))
+            }
+            #[cfg(unix)]
+            Endpoint::Unix(path) => {
+                let listener = std::os::unix::net::UnixListener::bind(&path)?;
+                (Listener::Unix(listener), Endpoint::Unix(path))
+            }
+        })
+    }
+
+    // connects once so a thread blocked accepting on endpoint sees it was closed,
+    // and removes the socket file of a unix endpoint
+    pub(crate) fn wake(endpoint: &Endpoint) {
+        match endpoint {
+            Endpoint::Tcp(addr) => {
+                let _ = TcpStream::connect(addr);
+            }
+            #[cfg(unix)]
+            Endpoint::Unix(path) => {
+                let _ = std::os::unix::net::UnixStream::connect(path);
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+
+    // a connection, split into the halves the reader and writer threads own
+    trait Connection: Read + Write + Send + Sized + 'static {
+        fn split(self) -> io::Result<(Self, Self)>;
+    }
+
+    impl Connection for TcpStream {
+        fn split(self) -> io::Result<(Self, Self)> {
+            Ok((self.try_clone()?, self))
+        }
+    }
+
+    #[cfg(unix)]
+    impl Connection for std::os::unix::net::UnixStream {
+        fn split(self) -> io::Result<(Self, Self)> {
+            Ok((self.try_clone()?, self))
+        }
+    }
+
+    struct Shared {
+        // every connected client's outgoing line queue, drained by its writer thread
+        clients: Mutex<Vec<mpsc::Sender<String>>>,
+        // the latest state the store applied an action to or committed, as json
+        state: Mutex<Value>,
+        closed: AtomicBool,
+    }
+
+    impl Shared {
+        fn broadcast(&self, line: String) {
+            self.clients
+                .lock()
+                .unwrap()
+                .retain(|client| client.send(line.clone()).is_ok());
+        }
+    }
+
+    /*
+     an inspector for a live store. the server accepts clients on its own
+     thread, every client gets a reader and a writer thread, so the store thread
+     only serializes and queues lines. actions dispatched by clients go through a
+     dispatcher like any other thread's actions. dropping the server stops
+     accepting and streaming, connected clients can still query and dispatch
+    */
+    pub struct DevServer {
+        shared: Arc<Shared>,
+        endpoint: Endpoint,
+    }
+
+    impl DevServer {
+        // a server that only listens on loopback addresses
+        pub fn bind<Action>(endpoint: Endpoint, dispatcher: Dispatcher<Action>) -> io::Result<Self>
+        where
+            Action: DeserializeOwned + Send + 'static,
+        {
+            DevServer::builder().bind(endpoint, dispatcher)
+        }
+        pub fn tcp<Action>(addr: &str, dispatcher: Dispatcher<Action>) -> io::Result<Self>
+        where
+            Action: DeserializeOwned + Send + 'static,
+        {
+            DevServer::builder().tcp(addr, dispatcher)
+        }
+        #[cfg(unix)]
+        pub fn unix<Action, P: Into<PathBuf>>(
+            path: P,
+            dispatcher: Dispatcher<Action>,
+        ) -> io::Result<Self>
+        where
+            Action: DeserializeOwned + Send + 'static,
+        {
+            DevServer::builder().unix(path, dispatcher)
+        }
+        pub fn builder() -> DevServerBuilder {
+            DevServerBuilder {
+                allow_remote: false,
+            }
+        }
+        // the address clients connect to, with the port the os picked
+        pub fn endpoint(&self) -> &Endpoint {
+            &self.endpoint
+        }
+        // streams the actions the store applies to the clients, see Store::with_devtools
+        pub fn middleware(&self) -> DevTools {
+            DevTools {
+                shared: self.shared.clone(),
+                seq: Mutex::new(0),
+                reduced: Arc::new(AtomicBool::new(false)),
+            }
+        }
+    }
+
+    /*
+     options for a DevServer. clients read the whole state and dispatch any
+     action without authenticating, so tcp endpoints are refused unless they
+     are loopback addresses. allow_remote(true) lifts that for trusted networks
+    */
+    pub struct DevServerBuilder {
+        allow_remote: bool,
+    }
+
+    impl DevServerBuilder {
+        pub fn allow_remote(mut self, allow: bool) -> Self {
+            self.allow_remote = allow;
+            self
+        }
+        pub fn bind<Action>(
+            self,
+            endpoint: Endpoint,
+            dispatcher: Dispatcher<Action>,
+        ) -> io::Result<DevServer>
+        where
+            Action: DeserializeOwned + Send + 'static,
+        {
+            check_loopback(&endpoint, self.allow_remote, "DevServerBuilder")?;
+            let (listener, endpoint) = listen(endpoint)?;
+            let shared = Arc::new(Shared {
+                clients: Mutex::new(Vec::new()),
+                state: Mutex::new(Value::Null),
+                closed: AtomicBool::new(false),
+            });
+            let accept_shared = shared.clone();
+            thread::spawn(move || match listener {
+                Listener::Tcp(listener) => accept(listener.incoming(), &accept_shared, dispatcher),
+                #[cfg(unix)]
+                Listener::Unix(listener) => accept(listener.incoming(), &accept_shared, dispatcher),
+            });
+            Ok(DevServer { shared, endpoint })
+        }
+        pub fn tcp<Action>(
+            self,
+            addr: &str,
+            dispatcher: Dispatcher<Action>,
+        ) -> io::Result<DevServer>
+        where
+            Action: DeserializeOwned + Send + 'static,
+        {
+            let addr = addr
+                .parse()
+                .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?;
+            self.bind(Endpoint::Tcp(addr), dispatcher)
+        }
+        #[cfg(unix)]
+        pub fn unix<Action, P: Into<PathBuf>>(
+            self,
+            path: P,
+            dispatcher: Dispatcher<Action>,
+        ) -> io::Result<DevServer>
+        where
+            Action: DeserializeOwned + Send + 'static,
+        {
+            self.bind(Endpoint::Unix(path.into()), dispatcher)
+        }
+    }
+
+    impl Drop for DevServer {
+        fn drop(&mut self) {
+            self.shared.closed.store(true, Ordering::SeqCst);
+            self.shared.clients.lock().unwrap().clear();
+            wake(&self.endpoint);
+        }
+    }
+
+    fn accept<C, I, Action>(incoming: I, shared: &Arc<Shared>, dispatcher: Dispatcher<Action>)
+    where
+        C: Connection,
+        I: Iterator<Item = io::Result<C>>,
+        Action: DeserializeOwned + Send + 'static,
+    {
+        for connection in incoming {
+            if shared.closed.load(Ordering::SeqCst) {
+                break;
+            }
+            let (read, mut write) = match connection.and_then(Connection::split) {
+                Ok(halves) => halves,
+                Err(error) => {
+                    println!("DevServer: failed to accept a client: {}", error);
+                    continue;
+                }
+            };
+            let (tx, rx) = mpsc::channel::<String>();
+            shared.clients.lock().unwrap().push(tx.clone());
+            // the writer ends when the server drops the client's sender and the reader is done
+            thread::spawn(move || {
+                for line in rx {
+                    if writeln!(write, "{}", line).is_err() {
+                        break;
+                    }
+                }
+            });
+            let shared = shared.clone();
+            let dispatcher = dispatcher.clone();
+            thread::spawn(move || {
+                for line in BufReader::new(read).lines() {
+                    let line = match line {
+                        Ok(line) => line,
+                        Err(_) => break,
+                    };
+                    if tx.send(respond(&line, &shared, &dispatcher)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    }
+
+    fn respond<Action>(line: &str, shared: &Shared, dispatcher: &Dispatcher<Action>) -> String
+    where
+        Action: DeserializeOwned,
+    {
+        let error = |message: String| json!({ "event": "error", "message": message });
+        let reply = match serde_json::from_str::<Value>(line) {
+            Ok(request) => match request["request"].as_str() {
+                Some("get_state") => {
+                    json!({ "event": "state", "state": *shared.state.lock().unwrap() })
+                }
+                Some("dispatch") => {
+                    match serde_json::from_value::<Action>(request["action"].clone()) {
+                        Ok(action) => match dispatcher.send(action) {
+                            Ok(()) => json!({ "event": "dispatched" }),
+                            Err(dispatch_error) => error(format!("{:?}", dispatch_error)),
+                        },
+                        Err(parse_error) => error(format!("not an action: {}", parse_error)),
+                    }
+                }
+                _ => error(format!("unknown request: {}", line)),
+            },
+            Err(parse_error) => error(format!("not json: {}", parse_error)),
+        };
+        reply.to_string()
+    }
+
+    // the middleware half of the server
+    pub struct DevTools {
+        shared: Arc<Shared>,
+        seq: Mutex<u64>,
+        // set by after_reduce, the state the next commit hands to subscribers
+        // is serialized already
+        reduced: Arc<AtomicBool>,
+    }
+
+    impl<State, Action> Middleware<State, Action> for DevTools
+    where
+        State: Serialize,
+        Action: Serialize,
+    {
+        fn after_reduce(
+            &self,
+            _before: &State,
+            after: &State,
+            action: &Action,
+            _dispatcher: &Dispatcher<Action>,
+        ) {
+            self.reduced.store(true, Ordering::Release);
+            let after = serde_json::to_value(after).unwrap_or(Value::Null);
+            let mut state = self.shared.state.lock().unwrap();
+            let diff = json_diff(&state, &after);
+            *state = after;
+            drop(state);
+            let mut seq = self.seq.lock().unwrap();
+            *seq += 1;
+            let event = json!({
+                "event": "action",
+                "seq": *seq,
+                "action": serde_json::to_value(action).unwrap_or(Value::Null),
+                "diff": diff,
+            });
+            self.shared.broadcast(event.to_string());
+        }
+    }
+
+    impl<Action, State, RootReducer> Store<Action, State, RootReducer>
+    where
+        Action: Debug + Clone + Serialize,
+        State: Debug + Clone + Serialize,
+        RootReducer: Reducer<State, Action> + Send + Sync + 'static,
+    {
+        // appends the server's middleware, clients can query the state from now on.
+        // states committed without reducing an action, by time travel or
+        // reconcile, are picked up through a subscription. commits of reduced
+        // actions skip it, the middleware serialized their state already
+        pub fn with_devtools(mut self, server: &DevServer) -> Self {
+            *server.shared.state.lock().unwrap() =
+                serde_json::to_value(&self.state).unwrap_or(Value::Null);
+            let middleware = server.middleware();
+            let reduced = middleware.reduced.clone();
+            let shared = server.shared.clone();
+            self.subscribe_all(move |state: &State| {
+                if !reduced.swap(false, Ordering::AcqRel) {
+                    *shared.state.lock().unwrap() =
+                        serde_json::to_value(state).unwrap_or(Value::Null);
+                }
+            });
+            self.with_middleware(middleware)
+        }
+    }
+
+    // a json patch (rfc 6902) turning before into after. objects are compared
+    // key by key and arrays index by index, everything else is replaced whole
+    pub fn json_diff(before: &Value, after: &Value) -> Vec<Value> {
+        let mut patch = Vec::new();
+        diff_into(&mut patch, &mut String::new(), before, after);
+        patch
+    }
+
+    fn diff_into(patch: &mut Vec<Value>, path: &mut String, before: &Value, after: &Value) {
+        match (before, after) {
+            _ if before == after => {}
+            (Value::Object(before), Value::Object(after)) => {
+                for (key, old) in before {
+                    let len = push_segment(path, key);
+                    match after.get(key) {
+                        Some(new) => diff_into(patch, path, old, new),
+                        None => patch.push(json!({ "op": "remove", "path": path })),
+                    }
+                    path.truncate(len);
+                }
+                for (key, new) in after {
+                    if !before.contains_key(key) {
+                        let len = push_segment(path, key);
+                        patch.push(json!({ "op": "add", "path": path, "value": new }));
+                        path.truncate(len);
+                    }
+                }
+            }
+            (Value::Array(before), Value::Array(after)) => {
+                for (i, (old, new)) in before.iter().zip(after).enumerate() {
+                    let len = push_segment(path, &i.to_string());
+                    diff_into(patch, path, old, new);
+                    path.truncate(len);
+                }
+                for (i, new) in after.iter().enumerate().skip(before.len()) {
+                    let len = push_segment(path, &i.to_string());
+                    patch.push(json!({ "op": "add", "path": path, "value": new }));
+                    path.truncate(len);
+                }
+                // remove from the back so the indexes stay valid
+                for i in (after.len()..before.len()).rev() {
+                    let len = push_segment(path, &i.to_string());
+                    patch.push(json!({ "op": "remove", "path": path }));
+                    path.truncate(len);
+                }
+            }
+            _ => patch.push(json!({ "op": "replace", "path": path, "value": after })),
+        }
+    }
+
+    // appends an escaped json pointer segment, returns the length to truncate back to
+    fn push_segment(path: &mut String, segment: &str) -> usize {
+        let len = path.len();
+        path.push('/');
+        path.push_str(&crate::diff::diff::escape(segment));
+        len
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::Cell,
+        io::{BufRead, BufReader, ErrorKind, Write},
+        net::TcpStream,
+        time::Duration,
+    };
+
+    use serde::{Deserialize, Serialize, Serializer};
+    use serde_json::{json, Value};
+
+    use super::devtools::{json_diff, DevServer, Endpoint};
+    use crate::simple::simple::Store;
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    enum Action {
+        Rename(String),
+        Push(i32),
+    }
+
+    #[derive(Clone, Debug, Default, Serialize)]
+    struct State {
+        name: String,
+        values: Vec<i32>,
+    }
+
+    type R = fn(State, Action) -> State;
+    const REDUCER: R = |mut state, action| {
+        match action {
+            Action::Rename(name) => state.name = name,
+            Action::Push(value) => state.values.push(value),
+        }
+        state
+    };
+
+    #[test]
+    fn diff_is_a_json_patch() {
+        let before = json!({ "a": 1, "b": [1, 2, 3], "c/d": { "e": true }, "gone": 0 });
+        let after = json!({ "a": 2, "b": [1, 5], "c/d": { "e": true, "f": null } });
+        assert_eq!(
+            json_diff(&before, &after),
+            vec![
+                json!({ "op": "replace", "path": "/a", "value": 2 }),
+                json!({ "op": "replace", "path": "/b/1", "value": 5 }),
+                json!({ "op": "remove", "path": "/b/2" }),
+                json!({ "op": "add", "path": "/c~1d/f", "value": null }),
+                json!({ "op": "remove", "path": "/gone" }),
+            ]
+        );
+    }
+
+    fn read(lines: &mut impl Iterator<Item = std::io::Result<String>>) -> Value {
+        serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn clients_inspect_and_dispatch() {
+        let store = Store::<Action, State, R>::new(REDUCER, State::default());
+        let server = DevServer::tcp("127.0.0.1:0", store.dispatcher.clone()).unwrap();
+        let mut store = store.with_devtools(&server);
+        let addr = match server.endpoint() {
+            Endpoint::Tcp(addr) => *addr,
+            #[cfg(unix)]
+            Endpoint::Unix(_) => unreachable!(),
+        };
+        let mut client = TcpStream::connect(addr).unwrap();
+        client
+            .set_read_timeout(Some(Duration::from_secs(10)))
+            .unwrap();
+        let mut lines = BufReader::new(client.try_clone().unwrap()).lines();
+
+        // a reply also tells the client is registered for the stream
+        writeln!(client, "{}", json!({ "request": "get_state" })).unwrap();
+        assert_eq!(
+            read(&mut lines),
+            json!({ "event": "state", "state": { "name": "", "values": [] } })
+        );
+        writeln!(
+            client,
+            "{}",
+            json!({ "request": "dispatch", "action": { "Push": 7 } })
+        )
+        .unwrap();
+        assert_eq!(read(&mut lines), json!({ "event": "dispatched" }));
+        writeln!(client, "{}", json!({ "request": "dispatch", "action": 7 })).unwrap();
+        assert_eq!(read(&mut lines)["event"], "error");
+
+        store.dispatch(Action::Rename("mix".to_string())).unwrap();
+        store.tick();
+        store.update();
+        assert_eq!(
+            read(&mut lines),
+            json!({
+                "event": "action",
+                "seq": 1,
+                "action": { "Push": 7 },
+                "diff": [{ "op": "add", "path": "/values/0", "value": 7 }],
+            })
+        );
+        assert_eq!(
+            read(&mut lines)["diff"],
+            json!([{ "op": "replace", "path": "/name", "value": "mix" }])
+        );
+        assert_eq!(store.state.values, vec![7]);
+    }
+
+    #[test]
+    fn remote_addresses_need_allow_remote() {
+        let store = Store::<Action, State, R>::new(REDUCER, State::default());
+        let refused = DevServer::tcp("0.0.0.0:0", store.dispatcher.clone());
+        assert_eq!(
+            refused.err().map(|error| error.kind()),
+            Some(ErrorKind::PermissionDenied)
+        );
+        assert!(DevServer::builder()
+            .allow_remote(true)
+            .tcp("0.0.0.0:0", store.dispatcher.clone())
+            .is_ok());
+    }
+
+    #[test]
+    fn time_travel_refreshes_the_state() {
+        let store = Store::<Action, State, R>::new(REDUCER, State::default()).with_time_travel();
+        let server = DevServer::tcp("127.0.0.1:0", store.dispatcher.clone()).unwrap();
+        let mut store = store.with_devtools(&server);
+        let addr = match server.endpoint() {
+            Endpoint::Tcp(addr) => *addr,
+            #[cfg(unix)]
+            Endpoint::Unix(_) => unreachable!(),
+        };
+        let mut client = TcpStream::connect(addr).unwrap();
+        client
+            .set_read_timeout(Some(Duration::from_secs(10)))
+            .unwrap();
+        let mut lines = BufReader::new(client.try_clone().unwrap()).lines();
+
+        store.dispatch(Action::Push(1)).unwrap();
+        store.tick();
+        store.update();
+        store.step_back();
+        writeln!(client, "{}", json!({ "request": "get_state" })).unwrap();
+        // the action event may have been queued before the client registered
+        let mut reply = read(&mut lines);
+        if reply["event"] == "action" {
+            reply = read(&mut lines);
+        }
+        assert_eq!(
+            reply,
+            json!({ "event": "state", "state": { "name": "", "values": [] } })
+        );
+    }
+
+    thread_local! {
+        static SERIALIZED: Cell<usize> = const { Cell::new(0) };
+    }
+
+    // a state that counts how often the store thread serialized it
+    #[derive(Clone, Debug, Default)]
+    struct Counted(i32);
+
+    impl Serialize for Counted {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            SERIALIZED.with(|n| n.set(n.get() + 1));
+            self.0.serialize(serializer)
+        }
+    }
+
+    #[test]
+    fn commits_serialize_the_state_once() {
+        let reducer: fn(Counted, i32) -> Counted = |state, n| Counted(state.0 + n);
+        let store = Store::new(reducer, Counted::default()).with_time_travel();
+        let server = DevServer::tcp("127.0.0.1:0", store.dispatcher.clone()).unwrap();
+        let mut store = store.with_devtools(&server);
+        let serialized = || SERIALIZED.with(Cell::get);
+        let before = serialized();
+        store.dispatch(1).unwrap();
+        store.tick();
+        store.update();
+        // once by the middleware, the commit reuses it
+        assert_eq!(serialized() - before, 1);
+        store.step_back();
+        assert_eq!(serialized() - before, 2);
+    }
+}
@@ -0,0 +1,645 @@
+// allow dead_code when checking the lib without tests
+// cargo-analyzer runs `cargo check` for lib the lib with and without tests.
+// dead_code warnings in the ide won't show if the code is used in tests.
+#![cfg_attr(not(test), allow(dead_code))]
+pub mod diff {
+    use std::{
+        fmt::Debug,
+        hash::{BuildHasher, Hash},
+    };
+
+    use ::rpds::{HashTrieMap, RedBlackTreeMap, Vector as RpdsVector};
+    use archery::SharedPointerKind;
+    pub use mars_derive::Diff;
+    use serde::Serialize;
+    use serde_json::json;
+    pub use serde_json::Value;
+
+    use crate::simple::simple::{Reducer, Store, Subscription};
+
+    /*
+     what changed between two values of a type. diff returns None for equal
+     values, otherwise a typed patch that apply turns the old value into the new
+     one with. json_patch writes the same patch as rfc 6902 operations, rooted at
+     path (a json pointer, "" for the whole value) and using the serde
+     representation of the values.
+
+     what a diff costs depends on the type. im::OrdMap skips the subtrees both
+     maps share, so it costs about as much as the actions changed. im::Vector
+     and im::HashMap return at once when both values share their root, otherwise
+     they compare every element. the rpds collections can't tell what they share
+     and always compare every element, Vec and plain values compare whole
+    */
+    pub trait Diff {
+        type Patch: Clone + Debug + PartialEq;
+        fn diff(&self, other: &Self) -> Option<Self::Patch>;
+        fn apply(&mut self, patch: Self::Patch);
+        fn json_patch(patch: &Self::Patch, path: &str, ops: &mut Vec<Value>);
+    }
+
+    // the rfc 6902 operations turning before into after
+    pub fn json_patch<T: Diff>(before: &T, after: &T) -> Vec<Value> {
+        let mut ops = Vec::new();
+        if let Some(patch) = before.diff(after) {
+            T::json_patch(&patch, "", &mut ops);
+        }
+        ops
+    }
+
+    // escapes a json pointer segment, ~ and / are special
+    pub(crate) fn escape(segment: &str) -> String {
+        segment.replace('~', "~0").replace('/', "~1")
+    }
+
+    // map keys serialized to a string become the segment as is, other keys
+    // their json text
+    fn key_path<K: Serialize>(path: &str, key: &K) -> String {
+        let key = match serde_json::to_value(key) {
+            Ok(Value::String(key)) => key,
+            Ok(key) => key.to_string(),
+            Err(_) => String::new(),
+        };
+        format!("{}/{}", path, escape(&key))
+    }
+
+    fn to_json<T: Serialize>(value: &T) -> Value {
+        serde_json::to_value(value).unwrap_or(Value::Null)
+    }
+
+    // values without structure are replaced whole, their patch is the new value
+    macro_rules! replace_diff {
+        ($($ty:ty),*) => {$(
+            impl Diff for $ty {
+                type Patch = $ty;
+                fn diff(&self, other: &Self) -> Option<$ty> {
+                    (self != other).then(|| other.clone())
+                }
+                fn apply(&mut self, patch: $ty) {
+                    *self = patch;
+                }
+                fn json_patch(patch: &$ty, path: &str, ops: &mut Vec<Value>) {
+                    ops.push(json!({ "op": "replace", "path": path, "value": to_json(patch) }));
+                }
+            }
+        )*};
+    }
+
+    replace_diff!(
+        bool, char, i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64,
+        String
+    );
+
+    impl<T> Diff for Option<T>
+    where
+        T: Clone + Debug + PartialEq + Serialize,
+    {
+        type Patch = Option<T>;
+        fn diff(&self, other: &Self) -> Option<Option<T>> {
+            (self != other).then(|| other.clone())
+        }
+        fn apply(&mut self, patch: Option<T>) {
+            *self = patch;
+        }
+        fn json_patch(patch: &Option<T>, path: &str, ops: &mut Vec<Value>) {
+            ops.push(json!({ "op": "replace", "path": path, "value": to_json(patch) }));
+        }
+    }
+
+    // std vecs are replaced whole as well, states that change parts of a list
+    // should hold an im or rpds vector
+    impl<T> Diff for Vec<T>
+    where
+        T: Clone + Debug + PartialEq + Serialize,
+    {
+        type Patch = Vec<T>;
+        fn diff(&self, other: &Self) -> Option<Vec<T>> {
+            (self != other).then(|| other.clone())
+        }
+        fn apply(&mut self, patch: Vec<T>) {
+            *self = patch;
+        }
+        fn json_patch(patch: &Vec<T>, path: &str, ops: &mut Vec<Value>) {
+            ops.push(json!({ "op": "replace", "path": path, "value": to_json(patch) }));
+        }
+    }
+
+    // one change to a vector. indexes refer to the vector as the previous
+    // operations of the patch left it
+    #[derive(Clone, Debug, PartialEq)]
+    pub enum VectorOp<T: Diff> {
+        Update(usize, T::Patch),
+        Push(T),
+        Truncate(usize),
+    }
+
+    // one change to a map
+    #[derive(Clone, Debug, PartialEq)]
+    pub enum MapOp<K, V: Diff> {
+        Insert(K, V),
+        Remove(K),
+        Update(K, V::Patch),
+    }
+
+    // compares two sequences index by index
+    fn diff_sequences<'a, T, I>(
+        before: I,
+        after: I,
+        before_len: usize,
+        after_len: usize,
+    ) -> Vec<VectorOp<T>>
+    where
+        T: Diff + Clone + 'a,
+        I: Iterator<Item = &'a T>,
+    {
+        let mut ops = before
+            .zip(after.take(before_len))
+            .enumerate()
+            .filter_map(|(i, (old, new))| old.diff(new).map(|patch| VectorOp::Update(i, patch)))
+            .collect::<Vec<_>>();
+        if after_len < before_len {
+            ops.push(VectorOp::Truncate(after_len));
+        }
+        ops
+    }
+
+    fn vector_json_patch<T>(patch: &[VectorOp<T>], path: &str, ops: &mut Vec<Value>, mut len: usize)
+    where
+        T: Diff + Serialize,
+    {
+        for op in patch {
+            match op {
+                VectorOp::Update(i, patch) => T::json_patch(patch, &format!("{}/{}", path, i), ops),
+                VectorOp::Push(value) => {
+                    ops.push(json!({ "op": "add", "path": format!("{}/{}", path, len), "value": to_json(value) }));
+                    len += 1;
+                }
+                // remove from the back so the indexes stay valid
+                VectorOp::Truncate(to) => {
+                    for i in (*to..len).rev() {
+                        ops.push(json!({ "op": "remove", "path": format!("{}/{}", path, i) }));
+                    }
+                    len = *to;
+                }
+            }
+        }
+    }
+
+    impl<T> Diff for im::Vector<T>
+    where
+        T: Diff + Clone + Debug + PartialEq + Serialize,
+    {
+        type Patch = VectorPatch<T>;
+        fn diff(&self, other: &Self) -> Option<VectorPatch<T>> {
+            if self.ptr_eq(other) {
+                return None;
+            }
+            let mut ops = diff_sequences(self.iter(), other.iter(), self.len(), other.len());
+            ops.extend(other.iter().skip(self.len()).cloned().map(VectorOp::Push));
+            (!ops.is_empty()).then(|| VectorPatch {
+                len: self.len(),
+                ops,
+            })
+        }
+        fn apply(&mut self, patch: VectorPatch<T>) {
+            for op in patch.ops {
+                match op {
+                    VectorOp::Update(i, patch) => {
+                        if let Some(value) = self.get_mut(i) {
+                            value.apply(patch);
+                        }
+                    }
+                    VectorOp::Push(value) => self.push_back(value),
+                    VectorOp::Truncate(len) => self.truncate(len),
+                }
+            }
+        }
+        fn json_patch(patch: &VectorPatch<T>, path: &str, ops: &mut Vec<Value>) {
+            vector_json_patch(&patch.ops, path, ops, patch.len);
+        }
+    }
+
+    // the operations turning a vector of len elements into the new vector
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct VectorPatch<T: Diff> {
+        pub len: usize,
+        pub ops: Vec<VectorOp<T>>,
+    }
+
+    impl<T, P> Diff for RpdsVector<T, P>
+    where
+        T: Diff + Clone + Debug + PartialEq + Serialize,
+        P: SharedPointerKind,
+    {
+        type Patch = VectorPatch<T>;
+        fn diff(&self, other: &Self) -> Option<VectorPatch<T>> {
+            let mut ops = diff_sequences(self.iter(), other.iter(), self.len(), other.len());
+            ops.extend(other.iter().skip(self.len()).cloned().map(VectorOp::Push));
+            (!ops.is_empty()).then(|| VectorPatch {
+                len: self.len(),
+                ops,
+            })
+        }
+        fn apply(&mut self, patch: VectorPatch<T>) {
+            for op in patch.ops {
+                match op {
+                    VectorOp::Update(i, patch) => {
+                        if let Some(mut value) = self.get(i).cloned() {
+                            value.apply(patch);
+                            self.set_mut(i, value);
+                        }
+                    }
+                    VectorOp::Push(value) => self.push_back_mut(value),
+                    VectorOp::Truncate(len) => {
+                        while self.len() > len {
+                            self.drop_last_mut();
+                        }
+                    }
+                }
+            }
+        }
+        fn json_patch(patch: &VectorPatch<T>, path: &str, ops: &mut Vec<Value>) {
+            vector_json_patch(&patch.ops, path, ops, patch.len);
+        }
+    }
+
+    fn map_json_patch<K, V>(patch: &[MapOp<K, V>], path: &str, ops: &mut Vec<Value>)
+    where
+        K: Serialize,
+        V: Diff + Serialize,
+    {
+        for op in patch {
+            match op {
+                MapOp::Insert(key, value) => ops.push(
+                    json!({ "op": "add", "path": key_path(path, key), "value": to_json(value) }),
+                ),
+                MapOp::Remove(key) => {
+                    ops.push(json!({ "op": "remove", "path": key_path(path, key) }))
+                }
+                MapOp::Update(key, patch) => V::json_patch(patch, &key_path(path, key), ops),
+            }
+        }
+    }
+
+    // the changes between two maps, from iterating both and looking keys up
+    fn diff_maps<'a, K, V, I, Before, After>(
+        before: I,
+        after: I,
+        get_before: Before,
+        get_after: After,
+    ) -> Vec<MapOp<K, V>>
+    where
+        K: Clone + 'a,
+        V: Diff + Clone + 'a,
+        I: Iterator<Item = (&'a K, &'a V)>,
+        Before: Fn(&K) -> Option<&'a V>,
+        After: Fn(&K) -> Option<&'a V>,
+    {
+        let mut ops = Vec::new();
+        for (key, old) in before {
+            match get_after(key) {
+                Some(new) => {
+                    if let Some(patch) = old.diff(new) {
+                        ops.push(MapOp::Update(key.clone(), patch));
+                    }
+                }
+                None => ops.push(MapOp::Remove(key.clone())),
+            }
+        }
+        for (key, new) in after {
+            if get_before(key).is_none() {
+                ops.push(MapOp::Insert(key.clone(), new.clone()));
+            }
+        }
+        ops
+    }
+
+    impl<K, V, S> Diff for im::HashMap<K, V, S>
+    where
+        K: Hash + Eq + Clone + Debug + Serialize,
+        V: Diff + Clone + Debug + PartialEq + Serialize,
+        S: BuildHasher + Default,
+    {
+        type Patch = Vec<MapOp<K, V>>;
+        fn diff(&self, other: &Self) -> Option<Vec<MapOp<K, V>>> {
+            if self.ptr_eq(other) {
+                return None;
+            }
+            let ops = diff_maps(
+                self.iter(),
+                other.iter(),
+                |key| self.get(key),
+                |key| other.get(key),
+            );
+            (!ops.is_empty()).then_some(ops)
+        }
+        fn apply(&mut self, patch: Vec<MapOp<K, V>>) {
+            for op in patch {
+                match op {
+                    MapOp::Insert(key, value) => {
+                        self.insert(key, value);
+                    }
+                    MapOp::Remove(key) => {
+                        self.remove(&key);
+                    }
+                    MapOp::Update(key, patch) => {
+                        if let Some(value) = self.get_mut(&key) {
+                            value.apply(patch);
+                        }
+                    }
+                }
+            }
+        }
+        fn json_patch(patch: &Vec<MapOp<K, V>>, path: &str, ops: &mut Vec<Value>) {
+            map_json_patch(patch, path, ops);
+        }
+    }
+
+    impl<K, V> Diff for im::OrdMap<K, V>
+    where
+        K: Ord + Clone + Debug + Serialize,
+        V: Diff + Clone + Debug + PartialEq + Serialize,
+    {
+        type Patch = Vec<MapOp<K, V>>;
+        fn diff(&self, other: &Self) -> Option<Vec<MapOp<K, V>>> {
+            // OrdMap::diff skips the subtrees both maps share
+            let ops = im::OrdMap::diff(self, other)
+                .filter_map(|item| match item {
+                    im::ordmap::DiffItem::Add(key, value) => {
+                        Some(MapOp::Insert(key.clone(), value.clone()))
+                    }
+                    im::ordmap::DiffItem::Remove(key, _) => Some(MapOp::Remove(key.clone())),
+                    im::ordmap::DiffItem::Update { old, new } => old
+                        .1
+                        .diff(new.1)
+                        .map(|patch| MapOp::Update(new.0.clone(), patch)),
+                })
+                .collect::<Vec<_>>();
+            (!ops.is_empty()).then_some(ops)
+        }
+        fn apply(&mut self, patch: Vec<MapOp<K, V>>) {
+            for op in patch {
+                match op {
+                    MapOp::Insert(key, value) => {
+                        self.insert(key, value);
+                    }
+                    MapOp::Remove(key) => {
+                        self.remove(&key);
+                    }
+                    MapOp::Update(key, patch) => {
+                        if let Some(value) = self.get_mut(&key) {
+                            value.apply(patch);
+                        }
+                    }
+                }
+            }
+        }
+        fn json_patch(patch: &Vec<MapOp<K, V>>, path: &str, ops: &mut Vec<Value>) {
+            map_json_patch(patch, path, ops);
+        }
+    }
+
+    impl<K, V, P, H> Diff for HashTrieMap<K, V, P, H>
+    where
+        K: Hash + Eq + Clone + Debug + Serialize,
+        V: Diff + Clone + Debug + PartialEq + Serialize,
+        P: SharedPointerKind,
+        H: BuildHasher + Clone,
+    {
+        type Patch = Vec<MapOp<K, V>>;
+        fn diff(&self, other: &Self) -> Option<Vec<MapOp<K, V>>> {
+            let ops = diff_maps(
+                self.iter(),
+                other.iter(),
+                |key| self.get(key),
+                |key| other.get(key),
+            );
+            (!ops.is_empty()).then_some(ops)
+        }
+        fn apply(&mut self, patch: Vec<MapOp<K, V>>) {
+            for op in patch {
+                match op {
+                    MapOp::Insert(key, value) => self.insert_mut(key, value),
+                    MapOp::Remove(key) => {
+                        self.remove_mut(&key);
+                    }
+                    MapOp::Update(key, patch) => {
+                        if let Some(mut value) = self.get(&key).cloned() {
+                            value.apply(patch);
+                            self.insert_mut(key, value);
+                        }
+                    }
+                }
+            }
+        }
+        fn json_patch(patch: &Vec<MapOp<K, V>>, path: &str, ops: &mut Vec<Value>) {
+            map_json_patch(patch, path, ops);
+        }
+    }
+
+    impl<K, V, P> Diff for RedBlackTreeMap<K, V, P>
+    where
+        K: Ord + Clone + Debug + Serialize,
+        V: Diff + Clone + Debug + PartialEq + Serialize,
+        P: SharedPointerKind,
+    {
+        type Patch = Vec<MapOp<K, V>>;
+        fn diff(&self, other: &Self) -> Option<Vec<MapOp<K, V>>> {
+            let ops = diff_maps(
+                self.iter(),
+                other.iter(),
+                |key| self.get(key),
+                |key| other.get(key),
+            );
+            (!ops.is_empty()).then_some(ops)
+        }
+        fn apply(&mut self, patch: Vec<MapOp<K, V>>) {
+            for op in patch {
+                match op {
+                    MapOp::Insert(key, value) => self.insert_mut(key, value),
+                    MapOp::Remove(key) => {
+                        self.remove_mut(&key);
+                    }
+                    MapOp::Update(key, patch) => {
+                        if let Some(mut value) = self.get(&key).cloned() {
+                            value.apply(patch);
+                            self.insert_mut(key, value);
+                        }
+                    }
+                }
+            }
+        }
+        fn json_patch(patch: &Vec<MapOp<K, V>>, path: &str, ops: &mut Vec<Value>) {
+            map_json_patch(patch, path, ops);
+        }
+    }
+
+    impl<Action, State, RootReducer> Store<Action, State, RootReducer>
+    where
+        Action: Debug + Clone,
+        State: Debug + Clone + Diff + Send + 'static,
+        RootReducer: Reducer<State, Action> + Send + Sync + 'static,
+    {
+        // calls back with the patch from the previously committed state to the
+        // new one, after every update() that changed the state. keeps a clone of
+        // the last state it reported on, cloned again whenever the state changed
+        pub fn subscribe_diff<Callback>(&mut self, mut callback: Callback) -> Subscription
+        where
+            Callback: FnMut(&State::Patch) + Send + 'static,
+        {
+            let mut last = self.state.clone();
+            self.subscribe_all(move |state: &State| {
+                if let Some(patch) = last.diff(state) {
+                    callback(&patch);
+                    last = state.clone();
+                }
+            })
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use serde::Serialize;
+    use serde_json::json;
+
+    use super::diff::{json_patch, Diff, MapOp};
+    use crate::simple::simple::Store;
+
+    #[derive(Clone, Debug, PartialEq, Serialize, Diff)]
+    struct Todo {
+        title: String,
+        done: bool,
+    }
+
+    #[derive(Clone, Debug, PartialEq, Serialize, Diff)]
+    struct State {
+        todos: im::Vector<Todo>,
+        tags: im::HashMap<String, u32>,
+        order: im::OrdMap<u32, String>,
+        filter: Option<String>,
+    }
+
+    fn state() -> State {
+        State {
+            todos: im::vector![
+                Todo {
+                    title: "write".to_string(),
+                    done: false,
+                },
+                Todo {
+                    title: "test".to_string(),
+                    done: false,
+                },
+            ],
+            tags: im::hashmap! { "a/b".to_string() => 1 },
+            order: im::ordmap! { 1 => "write".to_string(), 2 => "test".to_string() },
+            filter: None,
+        }
+    }
+
+    #[test]
+    fn patch_reproduces_new_state() {
+        let before = state();
+        let mut after = before.clone();
+        after.todos[1].done = true;
+        after.todos.push_back(Todo {
+            title: "ship".to_string(),
+            done: false,
+        });
+        after.tags.remove("a/b");
+        after.tags.insert("c".to_string(), 2);
+        after.order.insert(2, "review".to_string());
+        after.filter = Some("open".to_string());
+
+        let patch = before.diff(&after).unwrap();
+        assert!(patch.filter.is_some());
+        let mut applied = before.clone();
+        applied.apply(patch);
+        assert_eq!(applied, after);
+
+        assert_eq!(before.diff(&before.clone()), None);
+        after.todos.truncate(1);
+        let mut applied = before.clone();
+        applied.apply(before.diff(&after).unwrap());
+        assert_eq!(applied, after);
+    }
+
+    #[test]
+    fn json_patch_paths() {
+        let before = state();
+        let mut after = before.clone();
+        after.todos[0].title = "draft".to_string();
+        after.todos.pop_back();
+        after.tags.insert("a/b".to_string(), 3);
+        after.order.remove(&1);
+
+        assert_eq!(
+            json_patch(&before, &after),
+            vec![
+                json!({ "op": "replace", "path": "/todos/0/title", "value": "draft" }),
+                json!({ "op": "remove", "path": "/todos/1" }),
+                json!({ "op": "replace", "path": "/tags/a~1b", "value": 3 }),
+                json!({ "op": "remove", "path": "/order/1" }),
+            ]
+        );
+        after.todos.push_back(before.todos[1].clone());
+        after.todos.push_back(before.todos[1].clone());
+        assert_eq!(
+            json_patch(&before.todos, &after.todos)[1..],
+            [json!({ "op": "add", "path": "/2", "value": { "title": "test", "done": false } })]
+        );
+    }
+
+    #[test]
+    fn rpds_collections() {
+        let before = ::rpds::HashTrieMap::new()
+            .insert("x".to_string(), 1)
+            .insert("y".to_string(), 2);
+        let after = before.remove("x").insert("y".to_string(), 5);
+        let patch = before.diff(&after).unwrap();
+        // hash maps iterate in no particular order
+        assert_eq!(patch.len(), 2);
+        assert!(patch.contains(&MapOp::Update("y".to_string(), 5)));
+        assert!(patch.contains(&MapOp::Remove("x".to_string())));
+        let mut applied = before.clone();
+        applied.apply(patch);
+        assert_eq!(applied, after);
+
+        let before = ::rpds::Vector::new().push_back(1).push_back(2);
+        let after = before.set(0, 4).unwrap().push_back(3);
+        let mut applied = before.clone();
+        applied.apply(before.diff(&after).unwrap());
+        assert_eq!(applied, after);
+
+        let before = ::rpds::RedBlackTreeMap::new().insert(1, "a".to_string());
+        let after = before.insert(2, "b".to_string());
+        assert_eq!(
+            json_patch(&before, &after),
+            vec![json!({ "op": "add", "path": "/2", "value": "b" })]
+        );
+    }
+
+    #[test]
+    fn subscribe_diff_reports_patches() {
+        let reducer = |mut state: State, title: String| {
+            state.todos.push_back(Todo { title, done: false });
+            state
+        };
+        let mut store = Store::new(reducer, state());
+        let patches = Arc::new(Mutex::new(Vec::new()));
+        let seen = patches.clone();
+        let _subscription = store.subscribe_diff(move |patch: &StatePatch| {
+            seen.lock().unwrap().push(patch.clone());
+        });
+        store.dispatch("ship".to_string()).unwrap();
+        store.tick();
+        store.update();
+
+        let patches = patches.lock().unwrap();
+        assert_eq!(patches.len(), 1);
+        let mut applied = state();
+        applied.apply(patches[0].clone());
+        assert_eq!(applied, store.state);
+    }
+}
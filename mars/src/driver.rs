@@ -0,0 +1,322 @@
+// allow dead_code when checking the lib without tests
+// cargo-analyzer runs `cargo check` for lib the lib with and without tests.
+// dead_code warnings in the ide won't show if the code is used in tests.
+#![cfg_attr(not(test), allow(dead_code))]
+pub mod driver {
+    use std::{
+        any::Any,
+        fmt::Debug,
+        panic,
+        sync::{
+            atomic::{AtomicBool, AtomicU64, Ordering},
+            Arc,
+        },
+        thread::{self, JoinHandle, Thread},
+        time::{Duration, Instant},
+    };
+
+    use crate::{
+        queue::queue::Dispatcher,
+        simple::simple::{Reducer, Store},
+    };
+
+    // when the driver thread runs tick() and update()
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum DriveMode {
+        // once per period, e.g. 60 hz for a ui. a driver that falls behind
+        // skips the periods it missed instead of catching up
+        FixedRate(Duration),
+        // whenever actions arrive. the thread parks while the lanes are empty and
+        // every dispatcher of the store unparks it
+        OnDemand,
+        // once per DriverHandle::step, e.g. from an audio callback once per
+        // block. steps the driver had no time for are merged into one
+        External,
+    }
+
+    struct Shared {
+        stop: AtomicBool,
+        steps: AtomicU64,
+        cycles: AtomicU64,
+    }
+
+    // steps an External driver, and stops any driver. cheap to clone and
+    // realtime safe: step never blocks or allocates. it unparks the driver
+    // thread, which can be a futex syscall
+    #[derive(Clone)]
+    pub struct DriverHandle {
+        shared: Arc<Shared>,
+        thread: Thread,
+    }
+
+    impl DriverHandle {
+        pub fn step(&self) {
+            self.shared.steps.fetch_add(1, Ordering::Release);
+            self.thread.unpark();
+        }
+        // asks the driver to stop after its current cycle
+        pub fn stop(&self) {
+            self.shared.stop.store(true, Ordering::Release);
+            self.thread.unpark();
+        }
+        // how many tick() and update() pairs the driver ran
+        pub fn cycles(&self) -> u64 {
+            self.shared.cycles.load(Ordering::Acquire)
+        }
+    }
+
+    /*
+     owns a store on its own thread and runs its tick() and update() loop. set
+     the store up (lanes, middleware, subscriptions, readers) before spawning
+     it, then talk to it through dispatchers. stop() hands the store back, with
+     whatever actions were still queued. a panic on the driver thread comes
+     back from stop() as its payload, or is resumed on the thread dropping the
+     driver
+    */
+    pub struct StoreDriver<Action, State, RootReducer> {
+        thread: Option<JoinHandle<Store<Action, State, RootReducer>>>,
+        handle: DriverHandle,
+        dispatcher: Dispatcher<Action>,
+    }
+
+    impl<Action, State, RootReducer> StoreDriver<Action, State, RootReducer>
+    where
+        Action: Debug + Clone + Send + 'static,
+        State: Debug + Clone + Send + 'static,
+        RootReducer: Reducer<State, Action> + Send + Sync + 'static,
+        Store<Action, State, RootReducer>: Send,
+    {
+        pub fn spawn(store: Store<Action, State, RootReducer>, mode: DriveMode) -> Self {
+            let shared = Arc::new(Shared {
+                stop: AtomicBool::new(false),
+                steps: AtomicU64::new(0),
+                cycles: AtomicU64::new(0),
+            });
+            let dispatcher = store.dispatcher.clone();
+            let thread_shared = shared.clone();
+            let thread = thread::Builder::new()
+                .name("mars driver".to_string())
+                .spawn(move || drive(store, mode, &thread_shared))
+                .expect("failed to spawn the store driver thread");
+            StoreDriver {
+                handle: DriverHandle {
+                    shared,
+                    thread: thread.thread().clone(),
+                },
+                thread: Some(thread),
+                dispatcher,
+            }
+        }
+        // a dispatcher for the store's default lane
+        pub fn dispatcher(&self) -> Dispatcher<Action> {
+            self.dispatcher.clone()
+        }
+        pub fn handle(&self) -> DriverHandle {
+            self.handle.clone()
+        }
+        pub fn is_running(&self) -> bool {
+            self.thread
+                .as_ref()
+                .is_some_and(|thread| !thread.is_finished())
+        }
+        // stops the driver after its current cycle and returns the store, or
+        // the payload of the panic that ended the driver thread
+        pub fn stop(mut self) -> Result<Store<Action, State, RootReducer>, Box<dyn Any + Send>> {
+            self.join()
+        }
+        fn join(&mut self) -> Result<Store<Action, State, RootReducer>, Box<dyn Any + Send>> {
+            self.handle.stop();
+            self.thread
+                .take()
+                .expect("the driver thread was already joined")
+                .join()
+        }
+    }
+
+    impl<Action, State, RootReducer> Drop for StoreDriver<Action, State, RootReducer> {
+        fn drop(&mut self) {
+            let thread = match self.thread.take() {
+                Some(thread) => thread,
+                None => return,
+            };
+            self.handle.stop();
+            if let Err(payload) = thread.join() {
+                if !thread::panicking() {
+                    panic::resume_unwind(payload);
+                }
+            }
+        }
+    }
+
+    fn drive<Action, State, RootReducer>(
+        mut store: Store<Action, State, RootReducer>,
+        mode: DriveMode,
+        shared: &Shared,
+    ) -> Store<Action, State, RootReducer>
+    where
+        Action: Debug + Clone,
+        State: Debug + Clone,
+        RootReducer: Reducer<State, Action> + Send + Sync + 'static,
+    {
+        let cycle = |store: &mut Store<Action, State, RootReducer>| {
+            let taken = store.take_actions();
+            store.update();
+            shared.cycles.fetch_add(1, Ordering::Release);
+            taken
+        };
+        // the threads the lanes and reports woke before, handed back when the
+        // driver stops
+        let (woken_before, report_woke_before): (Vec<_>, _) = match mode {
+            DriveMode::OnDemand => (
+                store
+                    .lanes
+                    .iter()
+                    .map(|lane| lane.actions.wake_on_send(Some(thread::current())))
+                    .collect(),
+                store.wake_on_report(Some(thread::current())),
+            ),
+            _ => (Vec::new(), None),
+        };
+        let mut next = Instant::now();
+        while !shared.stop.load(Ordering::Acquire) {
+            match mode {
+                DriveMode::FixedRate(period) => {
+                    cycle(&mut store);
+                    let now = Instant::now();
+                    next = match next + period {
+                        deadline if deadline > now => deadline,
+                        _ => now + period,
+                    };
+                    // parked rather than slept so stop() wakes it early
+                    while !shared.stop.load(Ordering::Acquire) {
+                        match next.checked_duration_since(Instant::now()) {
+                            Some(left) => thread::park_timeout(left),
+                            None => break,
+                        }
+                    }
+                }
+                // a send between the empty tick and park leaves the unpark
+                // token set, so park returns right away
+                DriveMode::OnDemand => {
+                    if cycle(&mut store) == 0 {
+                        thread::park();
+                    }
+                }
+                DriveMode::External => match shared.steps.swap(0, Ordering::Acquire) {
+                    0 => thread::park(),
+                    _ => {
+                        cycle(&mut store);
+                    }
+                },
+            }
+        }
+        for (lane, thread) in store.lanes.iter().zip(woken_before) {
+            lane.actions.wake_on_send(thread);
+        }
+        if mode == DriveMode::OnDemand {
+            store.wake_on_report(report_woke_before);
+        }
+        store
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicI32, Ordering},
+            Arc,
+        },
+        time::{Duration, Instant},
+    };
+
+    use super::driver::{DriveMode, StoreDriver};
+    use crate::simple::simple::Store;
+
+    type R = fn(i32, i32) -> i32;
+    const REDUCER: R = |state, n| match n {
+        0 => panic!("zero"),
+        n => state + n,
+    };
+
+    // the store's state as seen through a subscription, from the test thread
+    fn observed(store: &mut Store<i32, i32, R>) -> Arc<AtomicI32> {
+        let seen = Arc::new(AtomicI32::new(store.state));
+        let subscriber = seen.clone();
+        store.subscribe(
+            |s: &i32| *s,
+            move |s: &i32| subscriber.store(*s, Ordering::SeqCst),
+        );
+        seen
+    }
+
+    fn eventually(mut done: impl FnMut() -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !done() {
+            assert!(Instant::now() < deadline, "timed out");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn on_demand_and_fixed_rate() {
+        let mut store = Store::<i32, i32, R>::new(REDUCER, 0);
+        let seen = observed(&mut store);
+        let driver = StoreDriver::spawn(store, DriveMode::OnDemand);
+        let cycles = driver.handle().cycles();
+        driver.dispatcher().send(2).unwrap();
+        driver.dispatcher().send(3).unwrap();
+        eventually(|| seen.load(Ordering::SeqCst) == 5);
+        // parked again, not spinning
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(driver.handle().cycles() - cycles < 10);
+        let store = driver.stop().unwrap();
+        assert_eq!(store.state, 5);
+
+        let driver = StoreDriver::spawn(store, DriveMode::FixedRate(Duration::from_millis(1)));
+        driver.dispatcher().send(1).unwrap();
+        eventually(|| driver.handle().cycles() > 3);
+        let store = driver.stop().unwrap();
+        assert_eq!(store.state, 6);
+
+        // a new driver thread is woken instead of the old one
+        let driver = StoreDriver::spawn(store, DriveMode::OnDemand);
+        driver.dispatcher().send(1).unwrap();
+        eventually(|| seen.load(Ordering::SeqCst) == 7);
+        drop(driver);
+    }
+
+    #[test]
+    fn on_demand_hands_the_wake_back() {
+        let store = Store::<i32, i32, R>::new(REDUCER, 0);
+        store.lanes[0]
+            .actions
+            .wake_on_send(Some(std::thread::current()));
+        let store = StoreDriver::spawn(store, DriveMode::OnDemand)
+            .stop()
+            .unwrap();
+        // the lane wakes the test thread again, not the finished driver thread
+        assert_eq!(
+            store.lanes[0].actions.wake_on_send(None).map(|t| t.id()),
+            Some(std::thread::current().id())
+        );
+    }
+
+    #[test]
+    fn external_steps_and_panics() {
+        let mut store = Store::<i32, i32, R>::new(REDUCER, 0);
+        let seen = observed(&mut store);
+        let driver = StoreDriver::spawn(store, DriveMode::External);
+        let handle = driver.handle();
+        driver.dispatcher().send(4).unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(seen.load(Ordering::SeqCst), 0);
+        handle.step();
+        eventually(|| seen.load(Ordering::SeqCst) == 4);
+
+        driver.dispatcher().send(0).unwrap();
+        handle.step();
+        eventually(|| !driver.is_running());
+        let payload = driver.stop().err().unwrap();
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"zero"));
+    }
+}
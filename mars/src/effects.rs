@@ -0,0 +1,564 @@
+// allow dead_code when checking the lib without tests
+// cargo-analyzer runs `cargo check` for lib the lib with and without tests.
+// dead_code warnings in the ide won't show if the code is used in tests.
+#![cfg_attr(not(test), allow(dead_code))]
+pub mod effects {
+    use std::{
+        collections::{HashMap, VecDeque},
+        fmt::Debug,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            mpsc, Arc, Mutex, OnceLock, Weak,
+        },
+        thread::{self, JoinHandle},
+    };
+
+    use crate::{
+        queue::queue::{Dispatcher, QueueConfig},
+        simple::simple::{Reducer, Store},
+    };
+
+    // the work an effect does off the store thread. it can dispatch follow up
+    // actions through the context until it is cancelled
+    pub type Task<Action> = Box<dyn FnOnce(&EffectContext<Action>) + Send>;
+
+    enum Kind<Action> {
+        Run {
+            key: Option<String>,
+            task: Task<Action>,
+        },
+        Cancel(String),
+    }
+
+    // something a reducer wants done besides changing the state: io, timers,
+    // follow up dispatches. effects are run by the store's executor
+    pub struct Effect<Action> {
+        kind: Kind<Action>,
+    }
+
+    impl<Action> Effect<Action> {
+        pub fn new<F>(task: F) -> Self
+        where
+            F: FnOnce(&EffectContext<Action>) + Send + 'static,
+        {
+            Effect {
+                kind: Kind::Run {
+                    key: None,
+                    task: Box::new(task),
+                },
+            }
+        }
+        // only the latest effect with a key runs to completion, starting one
+        // cancels the one still in flight under the same key
+        pub fn keyed<K: Into<String>>(mut self, key: K) -> Self {
+            if let Kind::Run { key: slot, .. } = &mut self.kind {
+                *slot = Some(key.into());
+            }
+            self
+        }
+        // cancels the effect in flight under key, if any
+        pub fn cancel<K: Into<String>>(key: K) -> Self {
+            Effect {
+                kind: Kind::Cancel(key.into()),
+            }
+        }
+        // dispatches action from the executor, after the current update()
+        pub fn dispatch(action: Action) -> Self
+        where
+            Action: Send + 'static,
+        {
+            Effect::new(move |context: &EffectContext<Action>| {
+                context.dispatch(action);
+            })
+        }
+    }
+
+    // a reducer that also returns the effects an action causes
+    pub trait EffectReducer<State, Action> {
+        fn reduce(&self, state: State, action: Action) -> (State, Vec<Effect<Action>>);
+    }
+
+    impl<F, State, Action> EffectReducer<State, Action> for F
+    where
+        F: Fn(State, Action) -> (State, Vec<Effect<Action>>),
+    {
+        fn reduce(&self, state: State, action: Action) -> (State, Vec<Effect<Action>>) {
+            self(state, action)
+        }
+    }
+
+    struct Flags {
+        cancelled: AtomicBool,
+        finished: AtomicBool,
+    }
+
+    // cancels an effect that was handed to the executor. cancelling is cooperative:
+    // a running task keeps running, but nothing it dispatches reaches the store
+    #[derive(Clone)]
+    pub struct EffectHandle {
+        flags: Arc<Flags>,
+    }
+
+    impl EffectHandle {
+        fn new() -> Self {
+            EffectHandle {
+                flags: Arc::new(Flags {
+                    cancelled: AtomicBool::new(false),
+                    finished: AtomicBool::new(false),
+                }),
+            }
+        }
+        pub fn cancel(&self) {
+            self.flags.cancelled.store(true, Ordering::SeqCst);
+        }
+        pub fn is_cancelled(&self) -> bool {
+            self.flags.cancelled.load(Ordering::SeqCst)
+        }
+        pub fn is_finished(&self) -> bool {
+            self.flags.finished.load(Ordering::SeqCst)
+        }
+    }
+
+    // what a task sees of the store while it runs
+    pub struct EffectContext<Action> {
+        dispatcher: Dispatcher<Action>,
+        handle: EffectHandle,
+    }
+
+    impl<Action> EffectContext<Action> {
+        // sends action to the store, unless the effect was cancelled. returns
+        // whether the action was queued
+        pub fn dispatch(&self, action: Action) -> bool {
+            !self.handle.is_cancelled() && self.dispatcher.send(action).is_ok()
+        }
+        // long running tasks should check this and stop early
+        pub fn is_cancelled(&self) -> bool {
+            self.handle.is_cancelled()
+        }
+    }
+
+    type Keyed = Mutex<HashMap<String, EffectHandle>>;
+
+    // a task with the context it runs in. run() marks the effect finished and
+    // removes it from the runner's keyed effects
+    pub struct Job<Action> {
+        task: Task<Action>,
+        context: EffectContext<Action>,
+        key: Option<(String, Weak<Keyed>)>,
+    }
+
+    impl<Action> Job<Action> {
+        pub fn run(self) {
+            if !self.context.is_cancelled() {
+                (self.task)(&self.context);
+            }
+            self.context
+                .handle
+                .flags
+                .finished
+                .store(true, Ordering::SeqCst);
+            let (key, keyed) = match self.key {
+                Some((key, keyed)) => (key, keyed),
+                None => return,
+            };
+            if let Some(keyed) = keyed.upgrade() {
+                let mut keyed = keyed.lock().unwrap();
+                // a later effect may have taken the key over already
+                if keyed
+                    .get(&key)
+                    .is_some_and(|handle| Arc::ptr_eq(&handle.flags, &self.context.handle.flags))
+                {
+                    keyed.remove(&key);
+                }
+            }
+        }
+        pub fn is_cancelled(&self) -> bool {
+            self.context.is_cancelled()
+        }
+    }
+
+    // runs the jobs the effect runner hands it, somewhere other than the store thread
+    pub trait Executor<Action>: Send + Sync {
+        fn execute(&self, job: Job<Action>);
+    }
+
+    // a fixed number of threads taking jobs from a shared channel. dropping the
+    // pool lets the threads finish the queued jobs and joins them
+    pub struct WorkerPool<Action> {
+        jobs: Mutex<Option<mpsc::Sender<Job<Action>>>>,
+        workers: Vec<JoinHandle<()>>,
+    }
+
+    impl<Action: Send + 'static> WorkerPool<Action> {
+        pub fn new(threads: usize) -> Self {
+            let (tx, rx) = mpsc::channel::<Job<Action>>();
+            let rx = Arc::new(Mutex::new(rx));
+            let workers = (0..threads.max(1))
+                .map(|_| {
+                    let rx = rx.clone();
+                    thread::spawn(move || loop {
+                        let job = rx.lock().unwrap().recv();
+                        match job {
+                            Ok(job) => job.run(),
+                            Err(_) => break,
+                        }
+                    })
+                })
+                .collect();
+            WorkerPool {
+                jobs: Mutex::new(Some(tx)),
+                workers,
+            }
+        }
+    }
+
+    impl<Action: Send> Executor<Action> for WorkerPool<Action> {
+        fn execute(&self, job: Job<Action>) {
+            if let Some(jobs) = self.jobs.lock().unwrap().as_ref() {
+                let _ = jobs.send(job);
+            }
+        }
+    }
+
+    impl<Action> Drop for WorkerPool<Action> {
+        fn drop(&mut self) {
+            self.jobs.lock().unwrap().take();
+            for worker in self.workers.drain(..) {
+                let _ = worker.join();
+            }
+        }
+    }
+
+    // an executor for tests. jobs wait in a queue until the test runs them, on
+    // the test's thread and in the order the reducer returned them. clones share the queue
+    #[derive(Clone)]
+    pub struct ManualExecutor<Action> {
+        jobs: Arc<Mutex<VecDeque<Job<Action>>>>,
+    }
+
+    impl<Action> Default for ManualExecutor<Action> {
+        fn default() -> Self {
+            ManualExecutor {
+                jobs: Arc::new(Mutex::new(VecDeque::new())),
+            }
+        }
+    }
+
+    impl<Action> ManualExecutor<Action> {
+        pub fn new() -> Self {
+            Self::default()
+        }
+        // jobs waiting to run, including cancelled ones
+        pub fn pending(&self) -> usize {
+            self.jobs.lock().unwrap().len()
+        }
+        // runs the oldest job, returns false if there was none
+        pub fn run_next(&self) -> bool {
+            let job = self.jobs.lock().unwrap().pop_front();
+            job.map(Job::run).is_some()
+        }
+        // runs jobs until the queue is empty, returns how many ran
+        pub fn run_all(&self) -> usize {
+            let mut ran = 0;
+            while self.run_next() {
+                ran += 1;
+            }
+            ran
+        }
+    }
+
+    impl<Action: Send> Executor<Action> for ManualExecutor<Action> {
+        fn execute(&self, job: Job<Action>) {
+            self.jobs.lock().unwrap().push_back(job);
+        }
+    }
+
+    // hands effects to the executor and keeps the handles needed to cancel them
+    pub struct EffectRunner<Action> {
+        executor: Box<dyn Executor<Action>>,
+        dispatcher: OnceLock<Dispatcher<Action>>,
+        keyed: Arc<Keyed>,
+        in_flight: Mutex<Vec<EffectHandle>>,
+    }
+
+    impl<Action> EffectRunner<Action> {
+        fn new<E>(executor: E) -> Self
+        where
+            E: Executor<Action> + 'static,
+        {
+            EffectRunner {
+                executor: Box::new(executor),
+                dispatcher: OnceLock::new(),
+                keyed: Arc::new(Mutex::new(HashMap::new())),
+                in_flight: Mutex::new(Vec::new()),
+            }
+        }
+        fn run(&self, effect: Effect<Action>) {
+            let (key, task) = match effect.kind {
+                Kind::Run { key, task } => (key, task),
+                Kind::Cancel(key) => {
+                    if let Some(handle) = self.keyed.lock().unwrap().remove(&key) {
+                        handle.cancel();
+                    }
+                    return;
+                }
+            };
+            let dispatcher = match self.dispatcher.get() {
+                Some(dispatcher) => dispatcher.clone(),
+                None => return,
+            };
+            let handle = EffectHandle::new();
+            if let Some(key) = key.as_ref() {
+                let previous = self
+                    .keyed
+                    .lock()
+                    .unwrap()
+                    .insert(key.clone(), handle.clone());
+                if let Some(previous) = previous {
+                    previous.cancel();
+                }
+            }
+            let mut in_flight = self.in_flight.lock().unwrap();
+            in_flight.retain(|handle| !handle.is_finished());
+            in_flight.push(handle.clone());
+            drop(in_flight);
+            self.executor.execute(Job {
+                task,
+                context: EffectContext { dispatcher, handle },
+                key: key.map(|key| (key, Arc::downgrade(&self.keyed))),
+            });
+        }
+        // the keys of the keyed effects that have not finished yet
+        pub fn keyed(&self) -> Vec<String> {
+            self.keyed.lock().unwrap().keys().cloned().collect()
+        }
+        // handles of the effects that have not finished yet
+        pub fn in_flight(&self) -> Vec<EffectHandle> {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            in_flight.retain(|handle| !handle.is_finished());
+            in_flight.clone()
+        }
+        pub fn cancel_all(&self) {
+            self.keyed.lock().unwrap().clear();
+            for handle in self.in_flight.lock().unwrap().drain(..) {
+                handle.cancel();
+            }
+        }
+    }
+
+    /*
+     adapts an EffectReducer to the store's Reducer: the state goes back to the
+     store and the effects are kept until update() starts them with the runner,
+     right after the action that returned them. actions they dispatch are
+     queued for a later tick(), so a reducer never sees the results of its own
+     effects in the same update. time travel, replay and optimistic rebases
+     reduce actions again through replay_in_place, which drops the effects
+    */
+    pub struct WithEffects<R, Action> {
+        reducer: R,
+        runner: EffectRunner<Action>,
+        collected: Mutex<Vec<Effect<Action>>>,
+    }
+
+    impl<R, Action> WithEffects<R, Action> {
+        pub fn runner(&self) -> &EffectRunner<Action> {
+            &self.runner
+        }
+    }
+
+    impl<R, State, Action> Reducer<State, Action> for WithEffects<R, Action>
+    where
+        R: EffectReducer<State, Action>,
+    {
+        fn reduce(&self, state: State, action: Action) -> State {
+            let (state, effects) = self.reducer.reduce(state, action);
+            self.collected.lock().unwrap().extend(effects);
+            state
+        }
+        fn replay_in_place(&self, state: &mut State, action: &Action)
+        where
+            State: Clone,
+            Action: Clone,
+        {
+            *state = self.reducer.reduce(state.clone(), action.clone()).0;
+        }
+        fn run_effects(&self) {
+            let effects = std::mem::take(&mut *self.collected.lock().unwrap());
+            for effect in effects {
+                self.runner.run(effect);
+            }
+        }
+    }
+
+    impl<Action, State, R> Store<Action, State, WithEffects<R, Action>>
+    where
+        Action: Debug + Clone + Send + 'static,
+        State: Debug + Clone,
+        R: EffectReducer<State, Action> + Send + Sync + 'static,
+    {
+        // a store whose reducer returns effects, run by executor
+        pub fn with_effects<E>(reducer: R, state: State, executor: E) -> Self
+        where
+            E: Executor<Action> + 'static,
+        {
+            Self::with_effects_and_queue(reducer, state, executor, QueueConfig::Unbounded)
+        }
+        pub fn with_effects_and_queue<E>(
+            reducer: R,
+            state: State,
+            executor: E,
+            queue: QueueConfig<Action>,
+        ) -> Self
+        where
+            E: Executor<Action> + 'static,
+        {
+            let root_reducer = WithEffects {
+                reducer,
+                runner: EffectRunner::new(executor),
+                collected: Mutex::new(Vec::new()),
+            };
+            let store = Store::with_queue(root_reducer, state, queue);
+            let _ = store
+                .root_reducer
+                .runner
+                .dispatcher
+                .set(store.dispatcher.clone());
+            store
+        }
+        pub fn effects(&self) -> &EffectRunner<Action> {
+            &self.root_reducer.runner
+        }
+        // cancels every effect in flight, e.g. when the store shuts down
+        pub fn cancel_effects(&self) {
+            self.root_reducer.runner.cancel_all();
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::{
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use super::effects::{Effect, EffectContext, ManualExecutor, WithEffects, WorkerPool};
+    use crate::simple::simple::Store;
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Action {
+        Search(String),
+        Results(String, usize),
+        Ping,
+        Pong,
+        Stop,
+    }
+
+    #[derive(Clone, Debug, Default, PartialEq)]
+    struct State {
+        query: String,
+        results: Vec<(String, usize)>,
+        pongs: usize,
+    }
+
+    fn reducer(mut state: State, action: Action) -> (State, Vec<Effect<Action>>) {
+        match action {
+            Action::Search(query) => {
+                state.query = query.clone();
+                let effect = Effect::new(move |context: &EffectContext<Action>| {
+                    let len = query.len();
+                    context.dispatch(Action::Results(query, len));
+                });
+                (state, vec![effect.keyed("search")])
+            }
+            Action::Results(query, len) => {
+                state.results.push((query, len));
+                (state, vec![])
+            }
+            Action::Ping => (state, vec![Effect::dispatch(Action::Pong)]),
+            Action::Pong => {
+                state.pongs += 1;
+                (state, vec![])
+            }
+            Action::Stop => (state, vec![Effect::cancel("search")]),
+        }
+    }
+
+    type R = fn(State, Action) -> (State, Vec<Effect<Action>>);
+
+    fn step<I>(store: &mut Store<Action, State, WithEffects<R, Action>>, actions: I)
+    where
+        I: IntoIterator<Item = Action>,
+    {
+        for action in actions {
+            store.dispatch(action).unwrap();
+        }
+        store.tick();
+        store.update();
+    }
+
+    #[test]
+    fn effects_feed_actions_back_to_the_store() {
+        let executor = ManualExecutor::new();
+        let mut store = Store::with_effects(reducer as R, State::default(), executor.clone());
+        step(&mut store, [Action::Ping, Action::Ping]);
+        assert_eq!(executor.pending(), 2);
+        assert_eq!(store.state.pongs, 0);
+        assert_eq!(executor.run_all(), 2);
+        step(&mut store, []);
+        assert_eq!(store.state.pongs, 2);
+    }
+
+    #[test]
+    fn later_keyed_effect_cancels_earlier_one() {
+        let executor = ManualExecutor::new();
+        let mut store = Store::with_effects(reducer as R, State::default(), executor.clone());
+        step(&mut store, [Action::Search("a".to_string())]);
+        step(&mut store, [Action::Search("ab".to_string())]);
+        assert_eq!(store.effects().in_flight().len(), 2);
+        executor.run_all();
+        step(&mut store, []);
+        assert_eq!(store.state.results, vec![("ab".to_string(), 2)]);
+        assert!(store.effects().in_flight().is_empty());
+        assert!(store.effects().keyed().is_empty());
+    }
+
+    #[test]
+    fn time_travel_does_not_run_effects_again() {
+        let executor = ManualExecutor::new();
+        let mut store = Store::with_effects(reducer as R, State::default(), executor.clone())
+            .with_time_travel();
+        step(&mut store, [Action::Ping, Action::Search("a".to_string())]);
+        assert_eq!(executor.pending(), 2);
+        store.toggle_action(0);
+        store.toggle_action(0);
+        store.step_back();
+        assert_eq!(executor.pending(), 2);
+        assert_eq!(store.effects().keyed(), ["search"]);
+    }
+
+    #[test]
+    fn effects_can_be_cancelled() {
+        let executor = ManualExecutor::new();
+        let mut store = Store::with_effects(reducer as R, State::default(), executor.clone());
+        step(&mut store, [Action::Search("a".to_string()), Action::Stop]);
+        step(&mut store, [Action::Ping]);
+        store.cancel_effects();
+        assert_eq!(executor.run_all(), 2);
+        step(&mut store, []);
+        assert_eq!(store.state.query, "a");
+        assert!(store.state.results.is_empty());
+        assert_eq!(store.state.pongs, 0);
+    }
+
+    #[test]
+    fn worker_pool_runs_effects_off_the_store_thread() {
+        let mut store = Store::with_effects(reducer as R, State::default(), WorkerPool::new(4));
+        step(&mut store, (0..100).map(|_| Action::Ping));
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while store.state.pongs < 100 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(1));
+            step(&mut store, []);
+        }
+        assert_eq!(store.state.pongs, 100);
+    }
+}
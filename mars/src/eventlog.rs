@@ -0,0 +1,488 @@
+// allow dead_code when checking the lib without tests
+// cargo-analyzer runs `cargo check` for lib the lib with and without tests.
+// dead_code warnings in the ide won't show if the code is used in tests.
+#![cfg_attr(not(test), allow(dead_code))]
+pub mod eventlog {
+    use std::{
+        fmt::Debug,
+        fs::{File, OpenOptions},
+        io::{self, BufRead, BufReader, BufWriter, Read, Write},
+        path::Path,
+        sync::Mutex,
+    };
+
+    use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+    use crate::{
+        queue::queue::Dispatcher,
+        simple::simple::{Middleware, Reducer, Store},
+    };
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum Format {
+        // one json record per line, {"Action":...} or {"Snapshot":...}
+        JsonLines,
+        // a kind byte, a little endian u32 length and the bincode encoded payload per record
+        Binary,
+    }
+
+    const ACTION: u8 = 0;
+    const SNAPSHOT: u8 = 1;
+    // the largest binary record written or read. a corrupt length header must
+    // not make replay allocate gigabytes
+    pub const MAX_RECORD_LEN: u32 = 64 << 20;
+
+    // records are written from borrows and read back owned, both share the same encoding
+    #[derive(Serialize)]
+    enum RecordRef<'a, Action, State> {
+        Action(&'a Action),
+        Snapshot(&'a State),
+    }
+
+    #[derive(Deserialize)]
+    enum Record<Action, State> {
+        Action(Action),
+        Snapshot(State),
+    }
+
+    struct Writer {
+        file: BufWriter<File>,
+        actions: usize,
+        // the write that failed. nothing is logged after it, later records
+        // would not match the state
+        error: Option<io::Error>,
+    }
+
+    /*
+     appends every action the store applied to a file, as a middleware. every
+     snapshot_every actions the resulting state is written too, so replaying only
+     has to reduce the actions logged after the last snapshot. the first
+     failed write stops the log, last_error tells whether it is still complete
+    */
+    pub struct ActionLog {
+        writer: Mutex<Writer>,
+        format: Format,
+        snapshot_every: usize,
+    }
+
+    impl ActionLog {
+        // starts a new log, replacing the file if it exists
+        pub fn create<P: AsRef<Path>>(path: P, format: Format) -> io::Result<Self> {
+            Ok(Self::new(File::create(path)?, format))
+        }
+        // continues an existing log, e.g. after Store::replay recovered from it.
+        // a record a crash cut short is cut off first, so the records appended
+        // after it can be read back
+        pub fn append<P: AsRef<Path>>(path: P, format: Format) -> io::Result<Self> {
+            let file = OpenOptions::new()
+                .create(true)
+                .read(true)
+                .append(true)
+                .open(path)?;
+            // devices like /dev/null have no records to keep and never end
+            if file.metadata()?.is_file() {
+                let (_, complete) = read_raw(&file, format)?;
+                if file.metadata()?.len() > complete {
+                    file.set_len(complete)?;
+                }
+            }
+            Ok(Self::new(file, format))
+        }
+        fn new(file: File, format: Format) -> Self {
+            ActionLog {
+                writer: Mutex::new(Writer {
+                    file: BufWriter::new(file),
+                    actions: 0,
+                    error: None,
+                }),
+                format,
+                snapshot_every: 0,
+            }
+        }
+        // write a snapshot of the state after every n actions, 0 never snapshots
+        pub fn snapshot_every(mut self, n: usize) -> Self {
+            self.snapshot_every = n;
+            self
+        }
+        fn write<Action, State>(
+            &self,
+            file: &mut BufWriter<File>,
+            record: RecordRef<Action, State>,
+        ) -> io::Result<()>
+        where
+            Action: Serialize,
+            State: Serialize,
+        {
+            match self.format {
+                Format::JsonLines => {
+                    serde_json::to_writer(&mut *file, &record)?;
+                    file.write_all(b"\n")?;
+                }
+                Format::Binary => {
+                    let kind = match record {
+                        RecordRef::Action(_) => ACTION,
+                        RecordRef::Snapshot(_) => SNAPSHOT,
+                    };
+                    let payload = bincode::serialize(&record).map_err(invalid_data)?;
+                    if payload.len() > MAX_RECORD_LEN as usize {
+                        return Err(too_long(payload.len()));
+                    }
+                    file.write_all(&[kind])?;
+                    file.write_all(&(payload.len() as u32).to_le_bytes())?;
+                    file.write_all(&payload)?;
+                }
+            }
+            file.flush()
+        }
+        // forces everything written so far to disk
+        pub fn sync(&self) -> io::Result<()> {
+            let mut writer = self.writer.lock().unwrap();
+            if let Some(error) = writer.error.as_ref() {
+                return Err(copy(error));
+            }
+            writer.file.flush()?;
+            writer.file.get_ref().sync_data()
+        }
+        // the write that stopped the log, None while every action was logged
+        pub fn last_error(&self) -> Option<io::Error> {
+            self.writer.lock().unwrap().error.as_ref().map(copy)
+        }
+    }
+
+    fn copy(error: &io::Error) -> io::Error {
+        io::Error::new(error.kind(), error.to_string())
+    }
+
+    impl<State, Action> Middleware<State, Action> for ActionLog
+    where
+        Action: Serialize,
+        State: Serialize,
+    {
+        fn after_reduce(
+            &self,
+            _before: &State,
+            after: &State,
+            action: &Action,
+            _dispatcher: &Dispatcher<Action>,
+        ) {
+            let mut writer = self.writer.lock().unwrap();
+            let Writer {
+                file,
+                actions,
+                error,
+            } = &mut *writer;
+            if error.is_some() {
+                return;
+            }
+            let mut result = self.write::<Action, State>(file, RecordRef::Action(action));
+            *actions += 1;
+            if result.is_ok() && self.snapshot_every > 0 && *actions % self.snapshot_every == 0 {
+                result = self
+                    .write::<Action, State>(file, RecordRef::Snapshot(after))
+                    .and_then(|_| file.get_ref().sync_data());
+            }
+            if let Err(failed) = result {
+                println!(
+                    "ActionLog: failed to write to the log, stopping: {}",
+                    failed
+                );
+                *error = Some(failed);
+            }
+        }
+    }
+
+    fn invalid_data<E>(error: E) -> io::Error
+    where
+        E: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        io::Error::new(io::ErrorKind::InvalidData, error)
+    }
+
+    fn too_long(len: usize) -> io::Error {
+        invalid_data(format!(
+            "a record of {} bytes is longer than MAX_RECORD_LEN",
+            len
+        ))
+    }
+
+    // a record as it was found in the file, only decoded if replay needs it
+    enum Raw {
+        Json(String),
+        Binary(u8, Vec<u8>),
+    }
+
+    impl Raw {
+        fn is_snapshot(&self) -> bool {
+            match self {
+                Raw::Json(line) => line.starts_with("{\"Snapshot\""),
+                Raw::Binary(kind, _) => *kind == SNAPSHOT,
+            }
+        }
+        fn decode<Action, State>(&self) -> io::Result<Record<Action, State>>
+        where
+            Action: DeserializeOwned,
+            State: DeserializeOwned,
+        {
+            match self {
+                Raw::Json(line) => serde_json::from_str(line).map_err(invalid_data),
+                Raw::Binary(_, payload) => bincode::deserialize(payload).map_err(invalid_data),
+            }
+        }
+    }
+
+    /*
+     reads every complete record, and returns them with the length of the file
+     up to the end of the last one. a record cut short by a crash ends the log:
+     a json line without its newline, or a binary record missing bytes
+    */
+    fn read_raw(file: &File, format: Format) -> io::Result<(Vec<Raw>, u64)> {
+        let mut reader = BufReader::new(file);
+        let mut records = Vec::new();
+        let mut complete = 0u64;
+        match format {
+            Format::JsonLines => loop {
+                let mut line = Vec::new();
+                let len = reader.read_until(b'\n', &mut line)?;
+                if line.last() != Some(&b'\n') {
+                    break;
+                }
+                complete += len as u64;
+                let line = String::from_utf8(line).map_err(invalid_data)?;
+                let line = line.trim_end_matches(['\n', '\r']);
+                if !line.is_empty() {
+                    records.push(Raw::Json(line.to_string()));
+                }
+            },
+            Format::Binary => loop {
+                let mut header = [0u8; 5];
+                match reader.read_exact(&mut header) {
+                    Ok(()) => {}
+                    Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => break,
+                    Err(error) => return Err(error),
+                }
+                let len = u32::from_le_bytes([header[1], header[2], header[3], header[4]]);
+                if len > MAX_RECORD_LEN {
+                    return Err(too_long(len as usize));
+                }
+                let mut payload = vec![0u8; len as usize];
+                match reader.read_exact(&mut payload) {
+                    Ok(()) => records.push(Raw::Binary(header[0], payload)),
+                    Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => break,
+                    Err(error) => return Err(error),
+                }
+                complete += header.len() as u64 + len as u64;
+            },
+        }
+        Ok((records, complete))
+    }
+
+    impl<Action, State, RootReducer> Store<Action, State, RootReducer>
+    where
+        Action: Debug + Clone + DeserializeOwned,
+        State: Debug + Clone + DeserializeOwned,
+        RootReducer: Reducer<State, Action> + Send + Sync + 'static,
+    {
+        // rebuilds a store from the initial state and an ActionLog file. replay
+        // starts from the last snapshot in the log, or from the initial state if
+        // there is none, and reduces the actions logged after it
+        pub fn replay<P: AsRef<Path>>(
+            root_reducer: RootReducer,
+            initial_state: State,
+            path: P,
+            format: Format,
+        ) -> io::Result<Self> {
+            let (records, _) = read_raw(&File::open(path)?, format)?;
+            let start = records.iter().rposition(Raw::is_snapshot);
+            let mut state = initial_state;
+            for raw in &records[start.unwrap_or(0)..] {
+                match raw.decode::<Action, State>()? {
+                    Record::Snapshot(snapshot) => state = snapshot,
+                    Record::Action(action) => root_reducer.replay_in_place(&mut state, &action),
+                }
+            }
+            Ok(Store::new(root_reducer, state))
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::{self, OpenOptions},
+        io::Write,
+        path::PathBuf,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    use serde::{Deserialize, Serialize};
+
+    use super::eventlog::{ActionLog, Format, MAX_RECORD_LEN};
+    use crate::simple::simple::{Middleware, Reducer, Store};
+
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+    enum Action {
+        Add(i32),
+        Name(String),
+    }
+
+    #[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+    struct State {
+        total: i32,
+        names: Vec<String>,
+    }
+
+    // counts the actions it reduced, to check replay skips what a snapshot covers
+    #[derive(Default)]
+    struct Counting(Arc<AtomicUsize>);
+
+    impl Reducer<State, Action> for Counting {
+        fn reduce(&self, mut state: State, action: Action) -> State {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            match action {
+                Action::Add(n) => state.total += n,
+                Action::Name(name) => state.names.push(name),
+            }
+            state
+        }
+    }
+
+    fn log_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("mars-{}-{}.log", name, std::process::id()))
+    }
+
+    fn run(path: &PathBuf, format: Format, snapshot_every: usize) -> State {
+        let log = ActionLog::create(path, format)
+            .unwrap()
+            .snapshot_every(snapshot_every);
+        let mut store = Store::new(Counting::default(), State::default()).with_middleware(log);
+        for i in 1..=7 {
+            store.dispatch(Action::Add(i)).unwrap();
+        }
+        store.dispatch(Action::Name("kick".to_string())).unwrap();
+        store.tick();
+        store.update();
+        store.state
+    }
+
+    #[test]
+    fn replay_json_lines() {
+        let path = log_path("json");
+        let live = run(&path, Format::JsonLines, 0);
+        let lines = fs::read_to_string(&path).unwrap();
+        assert_eq!(lines.lines().next(), Some("{\"Action\":{\"Add\":1}}"));
+        let store = Store::replay(
+            Counting::default(),
+            State::default(),
+            &path,
+            Format::JsonLines,
+        )
+        .unwrap();
+        assert_eq!(store.state, live);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn replay_binary_from_last_snapshot() {
+        let path = log_path("binary");
+        let live = run(&path, Format::Binary, 3);
+        let reducer = Counting::default();
+        let store = Store::replay(reducer, State::default(), &path, Format::Binary).unwrap();
+        assert_eq!(store.state, live);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn replay_starts_at_snapshot() {
+        let path = log_path("snapshot");
+        let live = run(&path, Format::JsonLines, 3);
+        // 8 actions with a snapshot after the 6th, so only 2 need reducing
+        let reduced = Arc::new(AtomicUsize::new(0));
+        let reducer = Counting(reduced.clone());
+        let mut store = Store::replay(reducer, State::default(), &path, Format::JsonLines).unwrap();
+        assert_eq!(store.state, live);
+        assert_eq!(reduced.load(Ordering::SeqCst), 2);
+        store.dispatch(Action::Add(1)).unwrap();
+        store.tick();
+        store.update();
+        assert_eq!(store.state.total, live.total + 1);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn replay_ignores_torn_last_record() {
+        for format in [Format::JsonLines, Format::Binary] {
+            let path = log_path(&format!("torn-{:?}", format));
+            let live = run(&path, format, 0);
+            let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+            match format {
+                Format::JsonLines => file.write_all(b"{\"Action\":{\"Ad").unwrap(),
+                Format::Binary => file.write_all(&[0, 9, 0, 0, 0, 1]).unwrap(),
+            }
+            drop(file);
+            let store =
+                Store::replay(Counting::default(), State::default(), &path, format).unwrap();
+            assert_eq!(store.state, live);
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn append_after_a_crash_cuts_the_torn_record_off() {
+        for format in [Format::JsonLines, Format::Binary] {
+            let path = log_path(&format!("resumed-{:?}", format));
+            run(&path, format, 0);
+            let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+            match format {
+                Format::JsonLines => file.write_all(b"{\"Action\":{\"Ad").unwrap(),
+                Format::Binary => file.write_all(&[0, 9, 0, 0, 0, 1]).unwrap(),
+            }
+            drop(file);
+            let log = ActionLog::append(&path, format).unwrap();
+            let mut store = Store::replay(Counting::default(), State::default(), &path, format)
+                .unwrap()
+                .with_middleware(log);
+            store.dispatch(Action::Add(100)).unwrap();
+            store.dispatch(Action::Name("resumed".to_string())).unwrap();
+            store.tick();
+            store.update();
+            let resumed =
+                Store::replay(Counting::default(), State::default(), &path, format).unwrap();
+            assert_eq!(resumed.state, store.state);
+            assert_eq!(resumed.state.total, 28 + 100);
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn replay_refuses_oversized_records() {
+        let path = log_path("oversized");
+        let mut header = vec![0u8];
+        header.extend((MAX_RECORD_LEN + 1).to_le_bytes());
+        fs::write(&path, header).unwrap();
+        let error = Store::replay(Counting::default(), State::default(), &path, Format::Binary)
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        fs::remove_file(path).unwrap();
+    }
+
+    // every write to /dev/full fails with "no space left on device"
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn failed_writes_stop_the_log() {
+        let log = ActionLog::append("/dev/full", Format::JsonLines).unwrap();
+        assert!(log.last_error().is_none());
+        let store = Store::new(Counting::default(), State::default());
+        let state = State::default();
+        for _ in 0..2 {
+            log.after_reduce(&state, &state, &Action::Add(1), &store.dispatcher);
+        }
+        assert_eq!(
+            log.last_error().unwrap().kind(),
+            std::io::ErrorKind::StorageFull
+        );
+        assert!(log.sync().is_err());
+    }
+}
@@ -1,2 +1,25 @@
+// every module wraps its items in a module of the same name
+#![allow(clippy::module_inception)]
+// lets derives that name ::mars paths expand inside this crate too
+extern crate self as mars;
+
+pub mod ack;
+pub mod action;
+pub mod batch;
+pub mod coalesce;
+pub mod combine;
+pub mod devtools;
+pub mod diff;
+pub mod driver;
+pub mod effects;
+pub mod eventlog;
+pub mod optimistic;
+pub mod queue;
+pub mod reader;
+pub mod registry;
 pub mod rpds;
-pub mod simple;
\ No newline at end of file
+pub mod scope;
+pub mod simple;
+pub mod stats;
+pub mod stream;
+pub mod timetravel;
@@ -0,0 +1,456 @@
+// allow dead_code when checking the lib without tests
+// cargo-analyzer runs `cargo check` for lib the lib with and without tests.
+// dead_code warnings in the ide won't show if the code is used in tests.
+#![cfg_attr(not(test), allow(dead_code))]
+pub mod optimistic {
+    use std::{
+        collections::HashSet,
+        fmt::Debug,
+        sync::{
+            mpsc::{self, Receiver, SendError, Sender},
+            Arc, RwLock,
+        },
+        thread::Thread,
+    };
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::simple::simple::{Reducer, Store};
+
+    // an action applied locally and sent to the authority, numbered by the store
+    // that proposed it
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+    pub struct Proposal<Action> {
+        pub id: u64,
+        pub action: Action,
+    }
+
+    /*
+     what the authority sends back: its state, and the proposals it processed
+     since its last report. every proposal the state includes has to be listed
+     as confirmed, or the store applies it a second time on top. a report
+     with a lower version than one already reconciled is ignored
+    */
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+    pub struct Authoritative<State> {
+        pub version: u64,
+        pub state: State,
+        pub confirmed: Vec<u64>,
+        pub rejected: Vec<u64>,
+    }
+
+    type Outbox<Action> = Box<dyn FnMut(Proposal<Action>) + Send>;
+
+    /*
+     hands the authority's reports to a store from any thread. update()
+     reconciles the reports sent since the last update, so this is how a store
+     owned by a StoreDriver hears from its authority. the proposals a report
+     rejects go to the rejected callback of with_optimistic
+    */
+    pub struct Reports<State> {
+        sender: Sender<Authoritative<State>>,
+        // the thread to unpark after every report, see Store::wake_on_report
+        wake: Arc<RwLock<Option<Thread>>>,
+    }
+
+    impl<State> Clone for Reports<State> {
+        fn clone(&self) -> Self {
+            Reports {
+                sender: self.sender.clone(),
+                wake: self.wake.clone(),
+            }
+        }
+    }
+
+    impl<State> Reports<State> {
+        // fails with the report once the store is dropped
+        pub fn send(
+            &self,
+            report: Authoritative<State>,
+        ) -> Result<(), SendError<Authoritative<State>>> {
+            self.sender.send(report)?;
+            if let Some(thread) = self.wake.read().unwrap().as_ref() {
+                thread.unpark();
+            }
+            Ok(())
+        }
+    }
+
+    /*
+     the bookkeeping of a store whose state is owned by someone else, e.g. an
+     engine process. the store's state is always the last authoritative state
+     with the pending proposals reduced on top of it, in the order they were
+     applied
+    */
+    pub struct Optimistic<Action, State> {
+        confirmed: State,
+        version: Option<u64>,
+        pending: Vec<Proposal<Action>>,
+        next_id: u64,
+        send: Outbox<Action>,
+        rejected: Outbox<Action>,
+        reports: Receiver<Authoritative<State>>,
+        handle: Reports<State>,
+    }
+
+    impl<Action, State> Optimistic<Action, State>
+    where
+        Action: Clone,
+        State: Clone,
+    {
+        fn new(confirmed: State, send: Outbox<Action>, rejected: Outbox<Action>) -> Self {
+            let (sender, reports) = mpsc::channel();
+            Optimistic {
+                confirmed,
+                version: None,
+                pending: Vec::new(),
+                next_id: 1,
+                send,
+                rejected,
+                reports,
+                handle: Reports {
+                    sender,
+                    wake: Arc::new(RwLock::new(None)),
+                },
+            }
+        }
+        // the state of the last report, or the state the store started with
+        pub fn confirmed(&self) -> &State {
+            &self.confirmed
+        }
+        pub fn confirmed_version(&self) -> Option<u64> {
+            self.version
+        }
+        // the proposals the authority has not answered yet, oldest first
+        pub fn pending(&self) -> &[Proposal<Action>] {
+            &self.pending
+        }
+        pub(crate) fn propose(&mut self, action: &Action) {
+            let proposal = Proposal {
+                id: self.next_id,
+                action: action.clone(),
+            };
+            self.next_id += 1;
+            self.pending.push(proposal.clone());
+            (self.send)(proposal);
+        }
+        // takes the report in and returns the state to show with the rejected
+        // proposals, or None for a stale report
+        fn rebase<R>(
+            &mut self,
+            report: Authoritative<State>,
+            reducer: &R,
+        ) -> Option<(State, Vec<Proposal<Action>>)>
+        where
+            R: Reducer<State, Action>,
+        {
+            if self.version.is_some_and(|version| report.version < version) {
+                return None;
+            }
+            let confirmed = report.confirmed.into_iter().collect::<HashSet<_>>();
+            let rejected = report.rejected.into_iter().collect::<HashSet<_>>();
+            let (dropped, pending) = std::mem::take(&mut self.pending)
+                .into_iter()
+                .filter(|proposal| !confirmed.contains(&proposal.id))
+                .partition(|proposal| rejected.contains(&proposal.id));
+            self.pending = pending;
+            self.confirmed = report.state;
+            self.version = Some(report.version);
+            let mut state = self.confirmed.clone();
+            for proposal in self.pending.iter() {
+                reducer.replay_in_place(&mut state, &proposal.action);
+            }
+            Some((state, dropped))
+        }
+    }
+
+    impl<Action, State, RootReducer> Store<Action, State, RootReducer>
+    where
+        Action: Debug + Clone,
+        State: Debug + Clone,
+        RootReducer: Reducer<State, Action> + Send + Sync + 'static,
+    {
+        /*
+         makes every action update() applies from now on a proposal: it is
+         reduced right away as usual, kept as pending and handed to send, which
+         forwards it to the authority. the authority's answers come back through
+         reconcile, on the thread running update(), or through reports() from
+         any other thread. every proposal a report rejects is handed to
+         rejected, on the thread reconciling it, once its effect is gone from
+         the state
+        */
+        pub fn with_optimistic<F, G>(mut self, send: F, rejected: G) -> Self
+        where
+            F: FnMut(Proposal<Action>) + Send + 'static,
+            G: FnMut(Proposal<Action>) + Send + 'static,
+        {
+            self.optimistic = Some(Optimistic::new(
+                self.state.clone(),
+                Box::new(send),
+                Box::new(rejected),
+            ));
+            self
+        }
+        pub fn optimistic(&self) -> Option<&Optimistic<Action, State>> {
+            self.optimistic.as_ref()
+        }
+        // a handle sending reports to update(), None without with_optimistic
+        pub fn reports(&self) -> Option<Reports<State>> {
+            self.optimistic
+                .as_ref()
+                .map(|optimistic| optimistic.handle.clone())
+        }
+        // replaces the state with the authoritative one, drops the proposals
+        // it confirmed or rejected and reduces the rest on top again. readers
+        // and subscribers see the result like a state committed by update(),
+        // and time travel records it as an entry without an action.
+        // returns the rejected proposals, which the rejected callback got too
+        pub fn reconcile(&mut self, report: Authoritative<State>) -> Vec<Proposal<Action>> {
+            match self.rebase(report) {
+                Some(rejected) => {
+                    self.commit();
+                    rejected
+                }
+                None => Vec::new(),
+            }
+        }
+        // reconciles the reports sent through reports() without committing,
+        // update() commits once for them and the actions it reduces. returns
+        // whether the state changed
+        pub(crate) fn reconcile_reports(&mut self) -> bool {
+            let reports = match self.optimistic.as_ref() {
+                Some(optimistic) => optimistic.reports.try_iter().collect::<Vec<_>>(),
+                None => return false,
+            };
+            let mut changed = false;
+            for report in reports {
+                changed |= self.rebase(report).is_some();
+            }
+            changed
+        }
+        // unparks thread after every report sent through reports(), for a
+        // driver that parks between updates. returns the thread woken before
+        pub(crate) fn wake_on_report(&self, thread: Option<Thread>) -> Option<Thread> {
+            let optimistic = self.optimistic.as_ref()?;
+            std::mem::replace(&mut *optimistic.handle.wake.write().unwrap(), thread)
+        }
+        fn rebase(&mut self, report: Authoritative<State>) -> Option<Vec<Proposal<Action>>> {
+            let optimistic = self.optimistic.as_mut()?;
+            let (state, rejected) = optimistic.rebase(report, &self.root_reducer)?;
+            for proposal in rejected.iter() {
+                (optimistic.rejected)(proposal.clone());
+            }
+            self.state = state;
+            if let Some(timeline) = self.timeline.as_mut() {
+                timeline.record_state(self.state.clone());
+            }
+            Some(rejected)
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicI32, Ordering},
+            mpsc::{self, Receiver},
+            Arc,
+        },
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use super::optimistic::{Authoritative, Proposal};
+    use crate::{
+        ack::ack::Acked,
+        driver::driver::{DriveMode, StoreDriver},
+        queue::queue::Dispatcher,
+        simple::simple::{Middleware, Store},
+    };
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Action {
+        Add(i32),
+        Double,
+    }
+
+    type R = fn(i32, Action) -> i32;
+    const REDUCER: R = |state, action| match action {
+        Action::Add(n) => state + n,
+        Action::Double => state * 2,
+    };
+
+    type Proposals = Receiver<Proposal<Action>>;
+
+    // the store, the proposals it sent and the proposals it heard were rejected
+    fn ui(state: i32) -> (Store<Action, i32, R>, Proposals, Proposals) {
+        let (sent_tx, sent) = mpsc::channel();
+        let (rejected_tx, rejected) = mpsc::channel();
+        let store = Store::new(REDUCER, state).with_optimistic(
+            move |proposal| sent_tx.send(proposal).unwrap(),
+            move |proposal| rejected_tx.send(proposal).unwrap(),
+        );
+        (store, sent, rejected)
+    }
+
+    fn apply(store: &mut Store<Action, i32, R>, actions: Vec<Action>) {
+        for action in actions {
+            store.dispatch(action).unwrap();
+        }
+        store.tick();
+        store.update();
+    }
+
+    #[test]
+    fn pending_actions_rebase_on_authoritative_states() {
+        let (mut store, proposals, rejected_too) = ui(1);
+        apply(&mut store, vec![Action::Add(2), Action::Double]);
+        assert_eq!(store.state, 6);
+        let sent = proposals.try_iter().collect::<Vec<_>>();
+        assert_eq!(sent.iter().map(|p| p.id).collect::<Vec<_>>(), [1, 2]);
+
+        // the authority applied the first proposal after a change of its own
+        let report = |version, state, confirmed: &[u64], rejected: &[u64]| Authoritative {
+            version,
+            state,
+            confirmed: confirmed.to_vec(),
+            rejected: rejected.to_vec(),
+        };
+        assert!(store.reconcile(report(1, 13, &[1], &[])).is_empty());
+        assert_eq!(store.state, 26);
+        let optimistic = store.optimistic().unwrap();
+        assert_eq!(*optimistic.confirmed(), 13);
+        assert_eq!(optimistic.pending(), &sent[1..]);
+
+        // then turned the second one down
+        apply(&mut store, vec![Action::Add(1)]);
+        assert_eq!(store.state, 27);
+        let rejected = store.reconcile(report(2, 13, &[], &[2]));
+        assert_eq!(rejected, &sent[1..]);
+        assert_eq!(rejected_too.try_iter().collect::<Vec<_>>(), rejected);
+        assert_eq!(store.state, 14);
+
+        // a report overtaken by a newer one changes nothing
+        assert!(store.reconcile(report(1, 0, &[3], &[])).is_empty());
+        assert_eq!(store.state, 14);
+        assert_eq!(store.optimistic().unwrap().pending().len(), 1);
+    }
+
+    // the authority's rules: nothing may take the state past 100
+    struct Limit;
+
+    impl Middleware<i32, Action> for Limit {
+        fn before_reduce(
+            &self,
+            state: &i32,
+            action: Action,
+            _: &Dispatcher<Action>,
+        ) -> Option<Action> {
+            (REDUCER(*state, action.clone()) <= 100).then_some(action)
+        }
+    }
+
+    #[test]
+    fn authority_on_another_thread() {
+        let (mut store, proposals, _rejected) = ui(0);
+        let (reports_tx, reports) = mpsc::channel();
+        // a stand-in for the engine process, running its own store and
+        // answering every batch of proposals with its new state
+        let authority = thread::spawn(move || {
+            let mut engine = Store::new(REDUCER, 0).with_middleware(Limit);
+            for proposal in proposals.iter() {
+                let ack = engine.dispatch_with_ack(proposal.action).unwrap();
+                engine.tick();
+                engine.update();
+                let (confirmed, rejected) = match ack.try_acked() {
+                    Some(Acked::Applied { .. }) => (vec![proposal.id], vec![]),
+                    _ => (vec![], vec![proposal.id]),
+                };
+                let report = Authoritative {
+                    version: engine.version(),
+                    state: engine.state,
+                    confirmed,
+                    rejected,
+                };
+                reports_tx.send(report).unwrap();
+            }
+        });
+
+        apply(&mut store, vec![Action::Add(30), Action::Double]);
+        apply(&mut store, vec![Action::Double]);
+        assert_eq!(store.state, 120);
+        let mut rejected = Vec::new();
+        for _ in 0..3 {
+            rejected.extend(store.reconcile(reports.recv().unwrap()));
+        }
+        assert_eq!(
+            rejected.into_iter().map(|p| p.action).collect::<Vec<_>>(),
+            [Action::Double]
+        );
+        assert_eq!(store.state, 60);
+        assert!(store.optimistic().unwrap().pending().is_empty());
+
+        drop(store);
+        authority.join().unwrap();
+    }
+
+    #[test]
+    fn reports_reach_a_driven_store() {
+        let (store, proposals, rejected) = ui(0);
+        let mut store = store.with_time_travel();
+        let reports = store.reports().unwrap();
+        let seen = Arc::new(AtomicI32::new(0));
+        let subscriber = seen.clone();
+        store.subscribe(
+            |s: &i32| *s,
+            move |s: &i32| subscriber.store(*s, Ordering::SeqCst),
+        );
+        let driver = StoreDriver::spawn(store, DriveMode::OnDemand);
+        driver.dispatcher().send(Action::Add(5)).unwrap();
+        driver.dispatcher().send(Action::Double).unwrap();
+        let add = proposals.recv().unwrap();
+        let double = proposals.recv().unwrap();
+        // the authority adds 2 of its own, takes Add(5) and turns Double down
+        reports
+            .send(Authoritative {
+                version: 1,
+                state: 2 + 5,
+                confirmed: vec![add.id],
+                rejected: vec![double.id],
+            })
+            .unwrap();
+        assert_eq!(
+            rejected.recv_timeout(Duration::from_secs(10)).unwrap(),
+            double
+        );
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while seen.load(Ordering::SeqCst) != 7 {
+            assert!(Instant::now() < deadline, "timed out");
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        let store = driver.stop().unwrap();
+        assert!(store.optimistic().unwrap().pending().is_empty());
+        let entries = store.timeline().unwrap().entries();
+        assert_eq!(
+            entries
+                .iter()
+                .map(|e| (e.action.clone(), e.state))
+                .collect::<Vec<_>>(),
+            [
+                (Some(Action::Add(5)), 5),
+                (Some(Action::Double), 10),
+                (None, 7)
+            ]
+        );
+        drop(store);
+        assert!(reports
+            .send(Authoritative {
+                version: 2,
+                state: 0,
+                confirmed: vec![],
+                rejected: vec![],
+            })
+            .is_err());
+    }
+}
@@ -0,0 +1,695 @@
+// allow dead_code when checking the lib without tests
+// cargo-analyzer runs `cargo check` for lib the lib with and without tests.
+// dead_code warnings in the ide won't show if the code is used in tests.
+#![cfg_attr(not(test), allow(dead_code))]
+pub mod queue {
+    use std::{
+        cell::UnsafeCell,
+        error::Error,
+        fmt::{self, Debug, Display},
+        mem::MaybeUninit,
+        sync::{
+            atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+            mpsc, Arc, RwLock,
+        },
+        thread::Thread,
+    };
+
+    // an action stamped with the dispatcher that sent it and that dispatcher's
+    // send counter. every clone of a Dispatcher gets a new id and counts from 0,
+    // so (dispatcher, sequence) identifies an action and orders the actions one
+    // thread sent through the same dispatcher. threads sharing a &Dispatcher can
+    // enqueue their actions in another order than their sequence numbers
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct Envelope<T> {
+        pub dispatcher: u64,
+        pub sequence: u64,
+        pub action: T,
+    }
+
+    // what a bounded queue does with an action sent while it is full
+    pub enum Backpressure<T> {
+        // hand the action back in DispatchError::Full
+        Reject,
+        // drop the oldest queued action to make room
+        OverwriteOldest,
+        // merge the action into a single overflow slot with fn(older, newer). once
+        // the overflow is taken every send merges into it until the store drained
+        // the ring and the overflow, so the order of actions is kept
+        Coalesce(fn(T, T) -> T),
+    }
+
+    impl<T> Clone for Backpressure<T> {
+        fn clone(&self) -> Self {
+            *self
+        }
+    }
+
+    impl<T> Copy for Backpressure<T> {}
+
+    pub enum QueueConfig<T> {
+        // std mpsc channel, never full but allocates on send
+        Unbounded,
+        // preallocated ring buffer, sending never allocates. Reject and
+        // OverwriteOldest never lock either. Coalesce takes a spin lock on the
+        // overflow slot once the ring is full, held only while merging, and
+        // the store takes it to receive the overflow. while the ring has room
+        // and the overflow is empty neither side locks. when a
+        // thread registered with Receiver::wake_on_send, every successful send
+        // also does a try_read that never blocks and an unpark, which can be a
+        // futex syscall
+        Bounded {
+            capacity: usize,
+            backpressure: Backpressure<T>,
+        },
+    }
+
+    #[derive(PartialEq, Eq)]
+    pub enum DispatchError<T> {
+        Full(T),
+        Disconnected(T),
+    }
+
+    impl<T> DispatchError<T> {
+        pub fn into_inner(self) -> T {
+            match self {
+                DispatchError::Full(action) | DispatchError::Disconnected(action) => action,
+            }
+        }
+    }
+
+    impl<T> Debug for DispatchError<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                DispatchError::Full(_) => f.write_str("Full(..)"),
+                DispatchError::Disconnected(_) => f.write_str("Disconnected(..)"),
+            }
+        }
+    }
+
+    impl<T> Display for DispatchError<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                DispatchError::Full(_) => f.write_str("sending on a full queue"),
+                DispatchError::Disconnected(_) => f.write_str("sending on a closed queue"),
+            }
+        }
+    }
+
+    impl<T> Error for DispatchError<T> {}
+
+    /*
+     bounded multi producer queue over a preallocated ring of slots (dmitry vyukov's
+     bounded mpmc queue). every slot carries a sequence number that tells producers
+     and the consumer whose turn it is, so pushing and popping are a few atomic
+     operations and never allocate. producers pop too when overwriting the oldest.
+     sequence numbers count twice per position: 2 * position while the slot is
+     free for it, 2 * position + 1 once it was written. with one step per position
+     a ring of one slot would read "written" and "free for the next lap" alike
+    */
+    struct Slot<T> {
+        sequence: AtomicUsize,
+        value: UnsafeCell<MaybeUninit<Envelope<T>>>,
+    }
+
+    struct Ring<T> {
+        slots: Box<[Slot<T>]>,
+        head: AtomicUsize,
+        tail: AtomicUsize,
+        backpressure: Backpressure<T>,
+        overflow: SpinLock<Option<Envelope<T>>>,
+        // whether the overflow holds an action, so sends and receives only lock
+        // it while it does or the ring is full. set and cleared under the lock
+        overflowed: AtomicBool,
+        closed: AtomicBool,
+        // what the backpressure did to actions sent while the ring was full
+        rejected: AtomicU64,
+        overwritten: AtomicU64,
+        merged: AtomicU64,
+    }
+
+    unsafe impl<T: Send> Send for Ring<T> {}
+    unsafe impl<T: Send> Sync for Ring<T> {}
+
+    impl<T> Ring<T> {
+        fn new(capacity: usize, backpressure: Backpressure<T>) -> Self {
+            assert!(
+                capacity > 0,
+                "a bounded queue needs room for at least one action"
+            );
+            Ring {
+                slots: (0..capacity)
+                    .map(|i| Slot {
+                        sequence: AtomicUsize::new(i * 2),
+                        value: UnsafeCell::new(MaybeUninit::uninit()),
+                    })
+                    .collect(),
+                head: AtomicUsize::new(0),
+                tail: AtomicUsize::new(0),
+                backpressure,
+                overflow: SpinLock::new(None),
+                overflowed: AtomicBool::new(false),
+                closed: AtomicBool::new(false),
+                rejected: AtomicU64::new(0),
+                overwritten: AtomicU64::new(0),
+                merged: AtomicU64::new(0),
+            }
+        }
+        fn push(&self, value: Envelope<T>) -> Result<(), Envelope<T>> {
+            let capacity = self.slots.len();
+            let mut position = self.tail.load(Ordering::Relaxed);
+            loop {
+                let slot = &self.slots[position % capacity];
+                let sequence = slot.sequence.load(Ordering::Acquire);
+                match (sequence as isize).wrapping_sub(position.wrapping_mul(2) as isize) {
+                    0 => match self.tail.compare_exchange_weak(
+                        position,
+                        position.wrapping_add(1),
+                        Ordering::Relaxed,
+                        Ordering::Relaxed,
+                    ) {
+                        Ok(_) => {
+                            unsafe { (*slot.value.get()).write(value) };
+                            slot.sequence
+                                .store(position.wrapping_mul(2).wrapping_add(1), Ordering::Release);
+                            return Ok(());
+                        }
+                        Err(current) => position = current,
+                    },
+                    // the slot still holds a value from the previous lap
+                    diff if diff < 0 => return Err(value),
+                    _ => position = self.tail.load(Ordering::Relaxed),
+                }
+            }
+        }
+        fn pop(&self) -> Option<Envelope<T>> {
+            let capacity = self.slots.len();
+            let mut position = self.head.load(Ordering::Relaxed);
+            loop {
+                let slot = &self.slots[position % capacity];
+                let sequence = slot.sequence.load(Ordering::Acquire);
+                match (sequence as isize)
+                    .wrapping_sub(position.wrapping_mul(2).wrapping_add(1) as isize)
+                {
+                    0 => match self.head.compare_exchange_weak(
+                        position,
+                        position.wrapping_add(1),
+                        Ordering::Relaxed,
+                        Ordering::Relaxed,
+                    ) {
+                        Ok(_) => {
+                            let value = unsafe { (*slot.value.get()).assume_init_read() };
+                            slot.sequence.store(
+                                position.wrapping_add(capacity).wrapping_mul(2),
+                                Ordering::Release,
+                            );
+                            return Some(value);
+                        }
+                        Err(current) => position = current,
+                    },
+                    // the slot was not written yet in this lap
+                    diff if diff < 0 => return None,
+                    _ => position = self.head.load(Ordering::Relaxed),
+                }
+            }
+        }
+        fn send(&self, value: Envelope<T>) -> Result<(), DispatchError<T>> {
+            if self.closed.load(Ordering::Acquire) {
+                return Err(DispatchError::Disconnected(value.action));
+            }
+            match self.backpressure {
+                Backpressure::Reject => self.push(value).map_err(|value| {
+                    self.rejected.fetch_add(1, Ordering::Relaxed);
+                    DispatchError::Full(value.action)
+                }),
+                Backpressure::OverwriteOldest => {
+                    let mut value = value;
+                    loop {
+                        value = match self.push(value) {
+                            Ok(()) => return Ok(()),
+                            Err(value) => value,
+                        };
+                        if self.pop().is_some() {
+                            self.overwritten.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                }
+                Backpressure::Coalesce(merge) => {
+                    // an action that finds the overflow taken merges into it, or
+                    // it would be received before the older one
+                    let value = match self.overflowed.load(Ordering::Acquire) {
+                        false => match self.push(value) {
+                            Ok(()) => return Ok(()),
+                            Err(value) => value,
+                        },
+                        true => value,
+                    };
+                    let mut overflow = self.overflow.lock();
+                    *overflow = match overflow.take() {
+                        Some(older) => {
+                            self.merged.fetch_add(1, Ordering::Relaxed);
+                            Some(Envelope {
+                                action: merge(older.action, value.action),
+                                ..value
+                            })
+                        }
+                        // the store may have drained the ring since
+                        None => self.push(value).err(),
+                    };
+                    self.overflowed.store(overflow.is_some(), Ordering::Release);
+                    Ok(())
+                }
+            }
+        }
+        fn recv(&self) -> Option<Envelope<T>> {
+            self.pop()
+                .or_else(|| match self.overflowed.load(Ordering::Acquire) {
+                    true => {
+                        let mut overflow = self.overflow.lock();
+                        self.overflowed.store(false, Ordering::Release);
+                        overflow.take()
+                    }
+                    false => None,
+                })
+        }
+    }
+
+    impl<T> Drop for Ring<T> {
+        fn drop(&mut self) {
+            while self.pop().is_some() {}
+        }
+    }
+
+    // a tiny lock for the coalescing overflow slot, holding it never allocates
+    // and the critical section is a single merge
+    struct SpinLock<T> {
+        locked: AtomicBool,
+        value: UnsafeCell<T>,
+    }
+
+    struct SpinGuard<'a, T> {
+        lock: &'a SpinLock<T>,
+    }
+
+    impl<T> SpinLock<T> {
+        fn new(value: T) -> Self {
+            SpinLock {
+                locked: AtomicBool::new(false),
+                value: UnsafeCell::new(value),
+            }
+        }
+        fn lock(&self) -> SpinGuard<'_, T> {
+            while self
+                .locked
+                .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+                .is_err()
+            {
+                std::hint::spin_loop();
+            }
+            SpinGuard { lock: self }
+        }
+    }
+
+    impl<'a, T> std::ops::Deref for SpinGuard<'a, T> {
+        type Target = T;
+        fn deref(&self) -> &T {
+            unsafe { &*self.lock.value.get() }
+        }
+    }
+
+    impl<'a, T> std::ops::DerefMut for SpinGuard<'a, T> {
+        fn deref_mut(&mut self) -> &mut T {
+            unsafe { &mut *self.lock.value.get() }
+        }
+    }
+
+    impl<'a, T> Drop for SpinGuard<'a, T> {
+        fn drop(&mut self) {
+            self.lock.locked.store(false, Ordering::Release);
+        }
+    }
+
+    enum Sender<T> {
+        Unbounded(mpsc::Sender<Envelope<T>>),
+        Bounded(Arc<Ring<T>>),
+    }
+
+    // sending half of a store's action queue, clone it for every thread that dispatches
+    pub struct Dispatcher<T> {
+        sender: Sender<T>,
+        id: u64,
+        sequence: AtomicU64,
+        // shared by all clones to hand out dispatcher ids
+        next_id: Arc<AtomicU64>,
+        // the thread to unpark after every send, see Receiver::wake_on_send
+        wake: Arc<RwLock<Option<Thread>>>,
+    }
+
+    impl<T> Clone for Dispatcher<T> {
+        fn clone(&self) -> Self {
+            let sender = match &self.sender {
+                Sender::Unbounded(sender) => Sender::Unbounded(sender.clone()),
+                Sender::Bounded(ring) => Sender::Bounded(ring.clone()),
+            };
+            Dispatcher {
+                sender,
+                id: self.next_id.fetch_add(1, Ordering::Relaxed),
+                sequence: AtomicU64::new(0),
+                next_id: self.next_id.clone(),
+                wake: self.wake.clone(),
+            }
+        }
+    }
+
+    impl<T> Dispatcher<T> {
+        fn new(
+            sender: Sender<T>,
+            wake: Arc<RwLock<Option<Thread>>>,
+            next_id: Arc<AtomicU64>,
+        ) -> Self {
+            Dispatcher {
+                sender,
+                id: next_id.fetch_add(1, Ordering::Relaxed),
+                sequence: AtomicU64::new(0),
+                next_id,
+                wake,
+            }
+        }
+        pub fn id(&self) -> u64 {
+            self.id
+        }
+        // the id allocator this dispatcher and its clones share, see channel_sharing_ids
+        pub(crate) fn ids(&self) -> Arc<AtomicU64> {
+            self.next_id.clone()
+        }
+        pub fn send(&self, action: T) -> Result<(), DispatchError<T>> {
+            self.send_stamped(action).map(|_| ())
+        }
+        // like send, returning the sequence number the action's envelope got
+        pub fn send_stamped(&self, action: T) -> Result<u64, DispatchError<T>> {
+            let sequence = self.sequence.fetch_add(1, Ordering::Relaxed);
+            let envelope = Envelope {
+                dispatcher: self.id,
+                sequence,
+                action,
+            };
+            match &self.sender {
+                Sender::Unbounded(sender) => {
+                    sender.send(envelope).map_err(|mpsc::SendError(envelope)| {
+                        DispatchError::Disconnected(envelope.action)
+                    })
+                }
+                Sender::Bounded(ring) => ring.send(envelope),
+            }?;
+            // try_read never blocks, it only fails while wake_on_send replaces the
+            // thread. that thread looks at the queue after registering anyway
+            if let Ok(wake) = self.wake.try_read() {
+                if let Some(thread) = wake.as_ref() {
+                    thread.unpark();
+                }
+            }
+            Ok(sequence)
+        }
+    }
+
+    // how many actions a bounded queue's backpressure turned away, dropped or
+    // merged since it was created. unbounded queues never do any of that
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+    pub struct QueueCounters {
+        pub rejected: u64,
+        pub overwritten: u64,
+        pub merged: u64,
+    }
+
+    // receiving half, owned by the store
+    pub struct Receiver<T> {
+        receiver: ReceiverKind<T>,
+        wake: Arc<RwLock<Option<Thread>>>,
+    }
+
+    enum ReceiverKind<T> {
+        Unbounded(mpsc::Receiver<Envelope<T>>),
+        Bounded(Arc<Ring<T>>),
+    }
+
+    impl<T> Receiver<T> {
+        pub fn try_recv(&self) -> Option<Envelope<T>> {
+            match &self.receiver {
+                ReceiverKind::Unbounded(receiver) => receiver.try_recv().ok(),
+                ReceiverKind::Bounded(ring) => ring.recv(),
+            }
+        }
+        // drains whatever is queued right now
+        pub fn try_iter(&self) -> impl Iterator<Item = Envelope<T>> + '_ {
+            std::iter::from_fn(move || self.try_recv())
+        }
+        // makes every dispatcher of the queue unpark thread after sending, so a
+        // thread that parks while the queue is empty wakes up for the next action.
+        // None stops the unparking. returns the thread unparked before
+        pub fn wake_on_send(&self, thread: Option<Thread>) -> Option<Thread> {
+            std::mem::replace(&mut *self.wake.write().unwrap(), thread)
+        }
+        // how many actions wait in a bounded queue, not counting an action
+        // merged into the overflow slot. None for unbounded queues, they don't
+        // know their length
+        pub fn queued(&self) -> Option<usize> {
+            match &self.receiver {
+                ReceiverKind::Unbounded(_) => None,
+                ReceiverKind::Bounded(ring) => {
+                    let head = ring.head.load(Ordering::Relaxed);
+                    let tail = ring.tail.load(Ordering::Relaxed);
+                    Some(tail.wrapping_sub(head).min(ring.slots.len()))
+                }
+            }
+        }
+        // after try_recv returned None: whether every send that returned before
+        // was received or lost to the backpressure. a bounded queue returns None
+        // while an earlier send is still writing its slot
+        pub(crate) fn settled(&self) -> bool {
+            match &self.receiver {
+                ReceiverKind::Unbounded(_) => true,
+                ReceiverKind::Bounded(ring) => {
+                    ring.head.load(Ordering::Acquire) == ring.tail.load(Ordering::Acquire)
+                }
+            }
+        }
+        pub fn counters(&self) -> QueueCounters {
+            match &self.receiver {
+                ReceiverKind::Unbounded(_) => QueueCounters::default(),
+                ReceiverKind::Bounded(ring) => QueueCounters {
+                    rejected: ring.rejected.load(Ordering::Relaxed),
+                    overwritten: ring.overwritten.load(Ordering::Relaxed),
+                    merged: ring.merged.load(Ordering::Relaxed),
+                },
+            }
+        }
+    }
+
+    impl<T> Drop for Receiver<T> {
+        fn drop(&mut self) {
+            if let ReceiverKind::Bounded(ring) = &self.receiver {
+                ring.closed.store(true, Ordering::Release);
+            }
+        }
+    }
+
+    pub fn channel<T>(config: QueueConfig<T>) -> (Dispatcher<T>, Receiver<T>) {
+        channel_sharing_ids(config, Arc::new(AtomicU64::new(0)))
+    }
+
+    /*
+     like channel, but the dispatchers take their ids from an allocator shared
+     with other channels. a store's lanes all feed the same update(), so their
+     dispatchers must never stamp two envelopes alike
+    */
+    pub(crate) fn channel_sharing_ids<T>(
+        config: QueueConfig<T>,
+        ids: Arc<AtomicU64>,
+    ) -> (Dispatcher<T>, Receiver<T>) {
+        match config {
+            QueueConfig::Unbounded => {
+                let (tx, rx) = mpsc::channel::<Envelope<T>>();
+                let wake = Arc::new(RwLock::new(None));
+                (
+                    Dispatcher::new(Sender::Unbounded(tx), wake.clone(), ids),
+                    Receiver {
+                        receiver: ReceiverKind::Unbounded(rx),
+                        wake,
+                    },
+                )
+            }
+            QueueConfig::Bounded {
+                capacity,
+                backpressure,
+            } => {
+                let ring = Arc::new(Ring::new(capacity, backpressure));
+                let wake = Arc::new(RwLock::new(None));
+                (
+                    Dispatcher::new(Sender::Bounded(ring.clone()), wake.clone(), ids),
+                    Receiver {
+                        receiver: ReceiverKind::Bounded(ring),
+                        wake,
+                    },
+                )
+            }
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::queue::{channel, Backpressure, DispatchError, Envelope, QueueConfig};
+
+    fn actions<T>(envelopes: impl Iterator<Item = Envelope<T>>) -> Vec<T> {
+        envelopes.map(|envelope| envelope.action).collect()
+    }
+
+    #[test]
+    fn bounded_rejects_when_full() {
+        let (tx, rx) = channel::<i32>(QueueConfig::Bounded {
+            capacity: 2,
+            backpressure: Backpressure::Reject,
+        });
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        assert_eq!(tx.send(3), Err(DispatchError::Full(3)));
+        assert_eq!(actions(rx.try_iter()), vec![1, 2]);
+        tx.send(4).unwrap();
+        assert_eq!(rx.try_recv().map(|envelope| envelope.action), Some(4));
+        drop(rx);
+        assert_eq!(tx.send(5), Err(DispatchError::Disconnected(5)));
+    }
+
+    #[test]
+    fn bounded_overwrites_oldest() {
+        let (tx, rx) = channel::<i32>(QueueConfig::Bounded {
+            capacity: 3,
+            backpressure: Backpressure::OverwriteOldest,
+        });
+        for i in 0..5 {
+            tx.send(i).unwrap();
+        }
+        assert_eq!(actions(rx.try_iter()), vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn bounded_to_one_keeps_the_latest() {
+        let (tx, rx) = channel::<i32>(QueueConfig::Bounded {
+            capacity: 1,
+            backpressure: Backpressure::OverwriteOldest,
+        });
+        for i in 0..3 {
+            tx.send(i).unwrap();
+        }
+        assert_eq!(actions(rx.try_iter()), vec![2]);
+        let (tx, rx) = channel::<i32>(QueueConfig::Bounded {
+            capacity: 1,
+            backpressure: Backpressure::Reject,
+        });
+        tx.send(1).unwrap();
+        assert_eq!(tx.send(2), Err(DispatchError::Full(2)));
+        assert_eq!(actions(rx.try_iter()), vec![1]);
+        tx.send(3).unwrap();
+        assert_eq!(actions(rx.try_iter()), vec![3]);
+    }
+
+    #[test]
+    fn bounded_coalesces_overflow() {
+        let (tx, rx) = channel::<i32>(QueueConfig::Bounded {
+            capacity: 2,
+            backpressure: Backpressure::Coalesce(|older, newer| older + newer),
+        });
+        for i in 1..=5 {
+            tx.send(i).unwrap();
+        }
+        assert_eq!(actions(rx.try_iter()), vec![1, 2, 12]);
+        // the overflow stays in front of later actions until it was received
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        tx.send(3).unwrap();
+        assert_eq!(rx.try_recv().map(|envelope| envelope.action), Some(1));
+        tx.send(4).unwrap();
+        assert_eq!(actions(rx.try_iter()), vec![2, 7]);
+    }
+
+    #[test]
+    fn bounded_keeps_every_action_from_many_producers() {
+        let (tx, rx) = channel::<usize>(QueueConfig::Bounded {
+            capacity: 64,
+            backpressure: Backpressure::Reject,
+        });
+        let producers = (0..4)
+            .map(|p| {
+                let tx = tx.clone();
+                thread::spawn(move || {
+                    for i in 0..1_000 {
+                        let mut action = p * 1_000 + i;
+                        while let Err(DispatchError::Full(rejected)) = tx.send(action) {
+                            action = rejected;
+                            thread::yield_now();
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut received = Vec::new();
+        while received.len() < 4_000 {
+            received.extend(actions(rx.try_iter()));
+        }
+        for producer in producers {
+            producer.join().unwrap();
+        }
+        // every producer's actions arrive in the order it sent them
+        for p in 0..4 {
+            let own = received
+                .iter()
+                .filter(|action| **action / 1_000 == p)
+                .copied()
+                .collect::<Vec<_>>();
+            assert_eq!(own, (p * 1_000..(p + 1) * 1_000).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn coalescing_loses_nothing_between_producers_and_the_store() {
+        let (tx, rx) = channel::<u64>(QueueConfig::Bounded {
+            capacity: 4,
+            backpressure: Backpressure::Coalesce(|older, newer| older + newer),
+        });
+        let producers = (0..4)
+            .map(|_| {
+                let tx = tx.clone();
+                thread::spawn(move || {
+                    for _ in 0..10_000 {
+                        tx.send(1).unwrap();
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        // merged actions add up, so the sum is only complete if no send was lost
+        let mut sum = 0;
+        while sum < 40_000 {
+            sum += rx.try_iter().map(|envelope| envelope.action).sum::<u64>();
+        }
+        for producer in producers {
+            producer.join().unwrap();
+        }
+        assert_eq!(sum, 40_000);
+        assert!(rx.try_recv().is_none());
+    }
+
+    #[test]
+    fn envelopes_are_stamped_per_dispatcher() {
+        let (tx, rx) = channel::<&str>(QueueConfig::Unbounded);
+        let other = tx.clone();
+        tx.send("a").unwrap();
+        other.send("b").unwrap();
+        tx.send("c").unwrap();
+        let stamps = rx
+            .try_iter()
+            .map(|envelope| (envelope.dispatcher, envelope.sequence, envelope.action))
+            .collect::<Vec<_>>();
+        assert_eq!(stamps, vec![(0, 0, "a"), (1, 0, "b"), (0, 1, "c")]);
+    }
+}
@@ -0,0 +1,255 @@
+// allow dead_code when checking the lib without tests
+// cargo-analyzer runs `cargo check` for lib the lib with and without tests.
+// dead_code warnings in the ide won't show if the code is used in tests.
+#![cfg_attr(not(test), allow(dead_code))]
+pub mod reader {
+    use std::{
+        ops::Deref,
+        ptr,
+        sync::{
+            atomic::{AtomicPtr, Ordering},
+            Arc, Mutex,
+        },
+    };
+
+    /*
+     publishes the latest value of a T to any number of readers without locks.
+     the publisher swaps a pointer to the newest value in, readers load it and
+     protect it with a hazard pointer (one slot per reader) while they read.
+     superseded values are retired and only freed by the publisher, once no
+     hazard slot points at them, so readers never lock, allocate or drop a T.
+    */
+    struct Registry<T> {
+        hazards: Vec<Arc<AtomicPtr<T>>>,
+        retired: Vec<*mut T>,
+    }
+
+    struct Shared<T> {
+        latest: AtomicPtr<T>,
+        registry: Mutex<Registry<T>>,
+    }
+
+    // the raw pointers are owned boxes, only ever dropped by the publisher or by
+    // the last owner of Shared, and only shared as &T with readers
+    unsafe impl<T: Send + Sync> Send for Shared<T> {}
+    unsafe impl<T: Send + Sync> Sync for Shared<T> {}
+
+    impl<T> Shared<T> {
+        fn register(&self) -> Arc<AtomicPtr<T>> {
+            let hazard = Arc::new(AtomicPtr::new(ptr::null_mut()));
+            self.registry.lock().unwrap().hazards.push(hazard.clone());
+            hazard
+        }
+    }
+
+    impl<T> Drop for Shared<T> {
+        fn drop(&mut self) {
+            // no readers or publisher are left, everything can go
+            let registry = self.registry.get_mut().unwrap();
+            for retired in registry.retired.drain(..) {
+                drop(unsafe { Box::from_raw(retired) });
+            }
+            drop(unsafe { Box::from_raw(*self.latest.get_mut()) });
+        }
+    }
+
+    pub struct Publisher<T> {
+        shared: Arc<Shared<T>>,
+    }
+
+    impl<T> Publisher<T> {
+        pub fn new(value: T) -> Self {
+            Publisher {
+                shared: Arc::new(Shared {
+                    latest: AtomicPtr::new(Box::into_raw(Box::new(value))),
+                    registry: Mutex::new(Registry {
+                        hazards: Vec::new(),
+                        retired: Vec::new(),
+                    }),
+                }),
+            }
+        }
+        // makes value the latest, then frees every superseded value no reader is
+        // still holding. allocates and drops, so call it from the owning thread
+        pub fn publish(&mut self, value: T) {
+            let next = Box::into_raw(Box::new(value));
+            let previous = self.shared.latest.swap(next, Ordering::SeqCst);
+            let mut registry = self.shared.registry.lock().unwrap();
+            registry.retired.push(previous);
+            self.reclaim(&mut registry);
+        }
+        fn reclaim(&self, registry: &mut Registry<T>) {
+            // readers that were dropped leave their slot behind with only our reference
+            registry
+                .hazards
+                .retain(|hazard| Arc::strong_count(hazard) > 1);
+            let protected = registry
+                .hazards
+                .iter()
+                .map(|hazard| hazard.load(Ordering::SeqCst))
+                .collect::<Vec<*mut T>>();
+            registry.retired.retain(|retired| {
+                if protected.contains(retired) {
+                    return true;
+                }
+                drop(unsafe { Box::from_raw(*retired) });
+                false
+            });
+        }
+        // number of superseded values that could not be freed yet
+        pub fn retired(&self) -> usize {
+            self.shared.registry.lock().unwrap().retired.len()
+        }
+        // registers a new hazard slot, so call it from the owning thread and move
+        // the reader to the realtime thread afterwards
+        pub fn reader(&self) -> Reader<T> {
+            Reader {
+                hazard: self.shared.register(),
+                shared: self.shared.clone(),
+            }
+        }
+    }
+
+    // a realtime safe handle to the latest published value
+    pub struct Reader<T> {
+        shared: Arc<Shared<T>>,
+        hazard: Arc<AtomicPtr<T>>,
+    }
+
+    unsafe impl<T: Send + Sync> Send for Reader<T> {}
+
+    impl<T> Reader<T> {
+        // borrows the latest value without locking or allocating. the value stays
+        // valid, even if newer values are published, until the guard is dropped
+        pub fn read(&mut self) -> Guard<'_, T> {
+            let mut current = self.shared.latest.load(Ordering::Acquire);
+            loop {
+                self.hazard.store(current, Ordering::SeqCst);
+                // the value is only protected if it was still the latest after the
+                // hazard became visible to the publisher
+                let latest = self.shared.latest.load(Ordering::SeqCst);
+                if latest == current {
+                    break;
+                }
+                current = latest;
+            }
+            Guard {
+                value: unsafe { &*current },
+                hazard: &self.hazard,
+            }
+        }
+    }
+
+    impl<T> Clone for Reader<T> {
+        fn clone(&self) -> Self {
+            Reader {
+                hazard: self.shared.register(),
+                shared: self.shared.clone(),
+            }
+        }
+    }
+
+    pub struct Guard<'a, T> {
+        value: &'a T,
+        hazard: &'a AtomicPtr<T>,
+    }
+
+    impl<'a, T> Deref for Guard<'a, T> {
+        type Target = T;
+        fn deref(&self) -> &T {
+            self.value
+        }
+    }
+
+    impl<'a, T> Drop for Guard<'a, T> {
+        fn drop(&mut self) {
+            self.hazard.store(ptr::null_mut(), Ordering::Release);
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        thread,
+    };
+
+    use super::reader::Publisher;
+
+    // counts live values so the test can check every one of them was freed
+    struct Counted {
+        value: usize,
+        live: Arc<AtomicUsize>,
+    }
+
+    impl Counted {
+        fn new(value: usize, live: &Arc<AtomicUsize>) -> Self {
+            live.fetch_add(1, Ordering::SeqCst);
+            Counted {
+                value,
+                live: live.clone(),
+            }
+        }
+    }
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            self.live.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn guard_outlives_publish() {
+        let live = Arc::new(AtomicUsize::new(0));
+        let mut publisher = Publisher::new(Counted::new(0, &live));
+        let mut reader = publisher.reader();
+        {
+            let guard = reader.read();
+            publisher.publish(Counted::new(1, &live));
+            publisher.publish(Counted::new(2, &live));
+            // the value behind the guard is retired but not freed
+            assert_eq!(guard.value, 0);
+            assert_eq!(publisher.retired(), 1);
+        }
+        publisher.publish(Counted::new(3, &live));
+        assert_eq!(publisher.retired(), 0);
+        assert_eq!(reader.read().value, 3);
+        drop(reader);
+        drop(publisher);
+        assert_eq!(live.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn readers_see_monotonic_values_under_load() {
+        let live = Arc::new(AtomicUsize::new(0));
+        let mut publisher = Publisher::new(Counted::new(0, &live));
+        let readers = (0..4)
+            .map(|_| {
+                let mut reader = publisher.reader();
+                thread::spawn(move || {
+                    let mut last = 0;
+                    while last < 10_000 {
+                        let guard = reader.read();
+                        assert!(guard.value >= last);
+                        last = guard.value;
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for value in 1..=10_000 {
+            publisher.publish(Counted::new(value, &live));
+        }
+        for reader in readers {
+            reader.join().unwrap();
+        }
+        // all readers are gone, so the next publish frees everything retired
+        publisher.publish(Counted::new(10_001, &live));
+        assert_eq!(publisher.retired(), 0);
+        assert_eq!(live.load(Ordering::SeqCst), 1);
+        drop(publisher);
+        assert_eq!(live.load(Ordering::SeqCst), 0);
+    }
+}
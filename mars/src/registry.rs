@@ -0,0 +1,320 @@
+// allow dead_code when checking the lib without tests
+// cargo-analyzer runs `cargo check` for lib the lib with and without tests.
+// dead_code warnings in the ide won't show if the code is used in tests.
+#![cfg_attr(not(test), allow(dead_code))]
+pub mod registry {
+    use std::{
+        any::{type_name, Any, TypeId},
+        collections::HashMap,
+        fmt::{self, Debug},
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc, RwLock,
+        },
+    };
+
+    use crate::{
+        queue::queue::{DispatchError, QueueConfig},
+        simple::simple::{Reducer, Store},
+    };
+
+    /*
+     an action of any type, for stores whose actions are not known up front.
+     the store clones and prints actions, so DynAction keeps the clone and
+     Debug of the concrete type next to the boxed value
+    */
+    pub struct DynAction {
+        action: Box<dyn Any + Send>,
+        type_name: &'static str,
+        clone: fn(&(dyn Any + Send)) -> Box<dyn Any + Send>,
+        debug: fn(&(dyn Any + Send), &mut fmt::Formatter) -> fmt::Result,
+    }
+
+    impl DynAction {
+        pub fn new<A: Any + Send + Clone + Debug>(action: A) -> Self {
+            DynAction {
+                action: Box::new(action),
+                type_name: type_name::<A>(),
+                clone: |action| Box::new(action.downcast_ref::<A>().unwrap().clone()),
+                debug: |action, f| action.downcast_ref::<A>().unwrap().fmt(f),
+            }
+        }
+        pub fn type_id(&self) -> TypeId {
+            (*self.action).type_id()
+        }
+        pub fn type_name(&self) -> &'static str {
+            self.type_name
+        }
+        pub fn is<A: Any>(&self) -> bool {
+            self.action.is::<A>()
+        }
+        pub fn downcast_ref<A: Any>(&self) -> Option<&A> {
+            self.action.downcast_ref()
+        }
+        // the boxed action, or the DynAction back if it holds another type
+        pub fn downcast<A: Any>(self) -> Result<A, Self> {
+            match self.action.is::<A>() {
+                true => Ok(*self.action.downcast().unwrap()),
+                false => Err(self),
+            }
+        }
+        pub fn into_any(self) -> Box<dyn Any + Send> {
+            self.action
+        }
+    }
+
+    impl Clone for DynAction {
+        fn clone(&self) -> Self {
+            DynAction {
+                action: (self.clone)(&*self.action),
+                ..*self
+            }
+        }
+    }
+
+    impl Debug for DynAction {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            (self.debug)(&*self.action, f)
+        }
+    }
+
+    // shared so reduce can call them after releasing the table
+    type DynReducer<State> = Arc<dyn Fn(State, &DynAction) -> State + Send + Sync>;
+    type Unhandled = Arc<dyn Fn(&DynAction) + Send + Sync>;
+
+    struct Table<State> {
+        reducers: HashMap<TypeId, DynReducer<State>>,
+        unhandled: Option<Unhandled>,
+    }
+
+    /*
+     a root reducer that looks the reducer for an action up by the action's
+     type. reducers can be registered and unregistered while the store runs,
+     from any thread holding a clone of the registry: clones share one table,
+     and a change is seen by the next action update() reduces. actions no
+     reducer is registered for leave the state as it is, are counted and
+     passed to the on_unhandled callback
+    */
+    pub struct Registry<State> {
+        table: Arc<RwLock<Table<State>>>,
+        unhandled: Arc<AtomicU64>,
+    }
+
+    impl<State> Clone for Registry<State> {
+        fn clone(&self) -> Self {
+            Registry {
+                table: self.table.clone(),
+                unhandled: self.unhandled.clone(),
+            }
+        }
+    }
+
+    impl<State> Default for Registry<State> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl<State> Registry<State> {
+        pub fn new() -> Self {
+            Registry {
+                table: Arc::new(RwLock::new(Table {
+                    reducers: HashMap::new(),
+                    unhandled: None,
+                })),
+                unhandled: Arc::new(AtomicU64::new(0)),
+            }
+        }
+        // reduces actions of type A with reducer, replacing the reducer A had.
+        // returns whether there was one
+        pub fn register<A, R>(&self, reducer: R) -> bool
+        where
+            A: Any,
+            R: Fn(State, &A) -> State + Send + Sync + 'static,
+        {
+            let reducer: DynReducer<State> =
+                Arc::new(move |state, action| reducer(state, action.downcast_ref::<A>().unwrap()));
+            let mut table = self.table.write().unwrap();
+            table.reducers.insert(TypeId::of::<A>(), reducer).is_some()
+        }
+        // returns whether A had a reducer
+        pub fn unregister<A: Any>(&self) -> bool {
+            self.unregister_type(TypeId::of::<A>())
+        }
+        pub fn unregister_type(&self, type_id: TypeId) -> bool {
+            let mut table = self.table.write().unwrap();
+            table.reducers.remove(&type_id).is_some()
+        }
+        pub fn is_registered<A: Any>(&self) -> bool {
+            let table = self.table.read().unwrap();
+            table.reducers.contains_key(&TypeId::of::<A>())
+        }
+        // called on the thread running update() for every action without a reducer
+        pub fn on_unhandled<F>(&self, callback: F)
+        where
+            F: Fn(&DynAction) + Send + Sync + 'static,
+        {
+            self.table.write().unwrap().unhandled = Some(Arc::new(callback));
+        }
+        // how many actions had no reducer
+        pub fn unhandled(&self) -> u64 {
+            self.unhandled.load(Ordering::Relaxed)
+        }
+    }
+
+    impl<State> Reducer<State, DynAction> for Registry<State> {
+        // the table is only locked to look the reducer up, so reducers and the
+        // unhandled callback may register and unregister reducers themselves
+        fn reduce(&self, state: State, action: DynAction) -> State {
+            let reducer = self
+                .table
+                .read()
+                .unwrap()
+                .reducers
+                .get(&action.type_id())
+                .cloned();
+            match reducer {
+                Some(reducer) => reducer(state, &action),
+                None => {
+                    println!("{:?} Unhandled {}", &action, action.type_name());
+                    self.unhandled.fetch_add(1, Ordering::Relaxed);
+                    let callback = self.table.read().unwrap().unhandled.clone();
+                    if let Some(callback) = callback {
+                        callback(&action);
+                    }
+                    state
+                }
+            }
+        }
+    }
+
+    // a store for open sets of actions, reduced by a Registry
+    pub type DynStore<State> = Store<DynAction, State, Registry<State>>;
+
+    impl<State> Store<DynAction, State, Registry<State>>
+    where
+        State: Debug + Clone + 'static,
+    {
+        pub fn dynamic(state: State) -> Self {
+            Store::new(Registry::new(), state)
+        }
+        pub fn dynamic_with_queue(state: State, config: QueueConfig<DynAction>) -> Self {
+            Store::with_queue(Registry::new(), state, config)
+        }
+        // a handle to register reducers with, sharing the store's table
+        pub fn registry(&self) -> Registry<State> {
+            self.root_reducer.clone()
+        }
+        pub fn dispatch_any<A: Any + Send + Clone + Debug>(
+            &self,
+            action: A,
+        ) -> Result<(), DispatchError<DynAction>> {
+            // Store::dispatch wants Sync actions, a DynAction is only Send
+            let action = match self.on_dispatch(DynAction::new(action)) {
+                Some(action) => action,
+                None => return Ok(()),
+            };
+            println!("{:?} Dispatched", &action);
+            self.dispatcher.send(action)
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        thread,
+    };
+
+    use super::registry::{DynAction, DynStore};
+    use crate::simple::simple::Store;
+
+    #[derive(Clone, Debug)]
+    struct Add(i32);
+
+    #[derive(Clone, Debug)]
+    enum Plugin {
+        Double,
+        Reset,
+    }
+
+    #[test]
+    fn registered_reducers_handle_their_types() {
+        let mut store: DynStore<i32> = Store::dynamic(1);
+        let registry = store.registry();
+        assert!(!registry.register(|state: i32, Add(n): &Add| state + n));
+        // plugins register from their own threads
+        let plugins = registry.clone();
+        thread::spawn(move || {
+            plugins.register(|state: i32, action: &Plugin| match action {
+                Plugin::Double => state * 2,
+                Plugin::Reset => 0,
+            })
+        })
+        .join()
+        .unwrap();
+
+        store.dispatch_any(Add(2)).unwrap();
+        store.dispatch_any(Plugin::Double).unwrap();
+        store.tick();
+        store.update();
+        assert_eq!(store.state, 6);
+
+        assert!(registry.unregister::<Plugin>());
+        assert!(!registry.is_registered::<Plugin>());
+        store.dispatch_any(Plugin::Reset).unwrap();
+        store.dispatch_any(Add(1)).unwrap();
+        store.tick();
+        store.update();
+        assert_eq!(store.state, 7);
+        assert_eq!(registry.unhandled(), 1);
+    }
+
+    #[test]
+    fn unhandled_actions_are_reported() {
+        let mut store: DynStore<i32> = Store::dynamic(0);
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let log = seen.clone();
+        store.registry().on_unhandled(move |action: &DynAction| {
+            log.lock()
+                .unwrap()
+                .push(format!("{} {:?}", action.type_name(), action));
+        });
+        store.dispatch_any("hello".to_string()).unwrap();
+        store.tick();
+        store.update();
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 1);
+        // type_name's output is not specified, only that it names the type
+        assert!(seen[0].contains("String"));
+        assert!(seen[0].ends_with(r#" "hello""#));
+
+        let action = DynAction::new(Add(5)).clone();
+        let action = action.downcast::<String>().unwrap_err();
+        assert_eq!(action.downcast::<Add>().unwrap().0, 5);
+    }
+
+    #[test]
+    fn reducers_register_reducers() {
+        let mut store: DynStore<i32> = Store::dynamic(0);
+        let registry = store.registry();
+        let plugins = registry.clone();
+        registry.register(move |state: i32, action: &Plugin| {
+            if let Plugin::Double = action {
+                plugins.register(|state: i32, Add(n): &Add| state + n);
+            }
+            state
+        });
+        let fallback = registry.clone();
+        registry.on_unhandled(move |_: &DynAction| {
+            fallback.unregister::<Add>();
+        });
+        store.dispatch_any(Plugin::Double).unwrap();
+        store.dispatch_any(Add(3)).unwrap();
+        store.dispatch_any("unknown").unwrap();
+        store.tick();
+        store.update();
+        assert_eq!(store.state, 3);
+        assert!(!registry.is_registered::<Add>());
+    }
+}
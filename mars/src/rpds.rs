@@ -3,45 +3,134 @@
 // dead_code warnings in the ide won't show if the code is used in tests.
 #![cfg_attr(not(test), allow(dead_code))]
 pub mod rpds {
-    use std::{fmt::Debug, sync::mpsc};
+    use std::{
+        fmt::Debug,
+        sync::{mpsc, Arc, RwLock},
+    };
 
-    pub trait Reducer<State, Action> {
-        fn reduce(&self, state: State, action: Action) -> State;
+    use ::rpds::RedBlackTreeMapSync;
+
+    // the store used to have a Reducer trait of its own, reducers written
+    // against it implement the shared one the same way
+    pub use crate::simple::simple::Reducer;
+
+    // a committed version of the state. the state is expected to be built from the
+    // rpds *Sync collections, so cloning it only bumps reference counts and the
+    // snapshot can be sent to and kept on any thread
+    #[derive(Clone, Debug)]
+    pub struct Snapshot<State> {
+        pub version: usize,
+        pub state: State,
+    }
+
+    // the versions a store keeps, by version number. the map is persistent too,
+    // so the lock is only held to clone its root
+    struct Versions<State> {
+        latest: usize,
+        states: RedBlackTreeMapSync<usize, State>,
+    }
+
+    impl<State> Clone for Versions<State> {
+        fn clone(&self) -> Self {
+            Versions {
+                latest: self.latest,
+                states: self.states.clone(),
+            }
+        }
+    }
+
+    // read handle to the versions kept by a Store. cloning the handle or
+    // reading a version never copies a state, the versions share structure with
+    // each other and with the store's current state
+    pub struct Snapshots<State> {
+        versions: Arc<RwLock<Versions<State>>>,
+    }
+
+    impl<State> Clone for Snapshots<State> {
+        fn clone(&self) -> Self {
+            Snapshots {
+                versions: self.versions.clone(),
+            }
+        }
     }
 
-    impl<F, State, Action> Reducer<State, Action> for F
+    impl<State> Snapshots<State>
     where
-        F: Fn(State, Action) -> State,
+        State: Clone,
     {
-        fn reduce(&self, state: State, action: Action) -> State {
-            self(state, action)
+        fn versions(&self) -> Versions<State> {
+            self.versions.read().unwrap().clone()
+        }
+        // None for versions not committed yet or already dropped by with_history
+        pub fn get(&self, version: usize) -> Option<Snapshot<State>> {
+            self.versions()
+                .states
+                .get(&version)
+                .cloned()
+                .map(|state| Snapshot { version, state })
+        }
+        pub fn latest(&self) -> Snapshot<State> {
+            let versions = self.versions();
+            let state = versions.states[&versions.latest].clone();
+            Snapshot {
+                version: versions.latest,
+                state,
+            }
+        }
+        // the oldest version still kept, 0 until with_history drops one
+        pub fn oldest(&self) -> usize {
+            let versions = self.versions();
+            *versions.states.first().unwrap().0
+        }
+        // number of versions kept, including the initial state while it is kept
+        pub fn count(&self) -> usize {
+            self.versions.read().unwrap().states.size()
         }
     }
 
+    // a store whose state is made of persistent collections. reducers get the
+    // state by value, but cloning it is O(1) and the new state shares everything
+    // the reducer did not touch with the previous version. every update() that
+    // applied actions commits a new version readable through Snapshots
     pub struct Store<Action, State, RootReducer> {
         actions: mpsc::Receiver<Action>,
         pub dispatcher: mpsc::Sender<Action>,
         pub state: State,
         pub pending_actions: Vec<Action>,
         root_reducer: RootReducer,
+        versions: Arc<RwLock<Versions<State>>>,
+        history: Option<usize>,
     }
 
     impl<Action, State, RootReducer> Store<Action, State, RootReducer>
     where
         Action: Debug + Clone,
-        State: Debug + Clone,
+        State: Debug + Clone + Send + Sync + 'static,
         RootReducer: Reducer<State, Action> + Send + Sync + 'static,
     {
         pub fn new(root_reducer: RootReducer, state: State) -> Self {
             let (tx, rx) = mpsc::channel::<Action>();
+            let versions = Versions {
+                latest: 0,
+                states: RedBlackTreeMapSync::new_sync().insert(0, state.clone()),
+            };
             Store {
                 actions: rx,
                 dispatcher: tx,
                 pending_actions: Vec::<Action>::new(),
                 state,
                 root_reducer,
+                versions: Arc::new(RwLock::new(versions)),
+                history: None,
             }
         }
+        // keeps only the latest `versions` versions, at least the current one.
+        // without it every committed state is kept for as long as the store lives
+        pub fn with_history(mut self, versions: usize) -> Self {
+            self.history = Some(versions.max(1));
+            self.trim();
+            self
+        }
         pub fn dispatch(&self, action: Action) -> Result<(), mpsc::SendError<Action>>
         where
             Action: Send + Sync,
@@ -56,14 +145,43 @@ pub mod rpds {
                 self.pending_actions.len()
             );
         }
-        // called by owning thread to collect & process updates the state with the reducer
+        // called by owning thread to reduce the pending actions in arrival order
+        // and commit the resulting state as a new version
         pub fn update(&mut self) {
-            while let Some(action) = self.pending_actions.pop() {
-                self.state = self.root_reducer.reduce(self.state.clone(), action.clone());
-                println!(
-                    "Store Update: {:?} applied to state. New state: {:?}",
-                    action, self.state
-                );
+            if self.pending_actions.is_empty() {
+                return;
+            }
+            for action in self.pending_actions.drain(..) {
+                self.state = self.root_reducer.reduce(self.state.clone(), action);
+            }
+            let mut versions = self.versions.read().unwrap().clone();
+            versions.latest += 1;
+            versions
+                .states
+                .insert_mut(versions.latest, self.state.clone());
+            *self.versions.write().unwrap() = versions;
+            self.trim();
+            println!("Store Update: committed version {}", self.version());
+        }
+        fn trim(&mut self) {
+            let history = match self.history {
+                Some(history) => history,
+                None => return,
+            };
+            let mut versions = self.versions.read().unwrap().clone();
+            while versions.states.size() > history {
+                let oldest = *versions.states.first().unwrap().0;
+                versions.states.remove_mut(&oldest);
+            }
+            *self.versions.write().unwrap() = versions;
+        }
+        // the version of the current state, the initial state is version 0
+        pub fn version(&self) -> usize {
+            self.versions.read().unwrap().latest
+        }
+        pub fn snapshots(&self) -> Snapshots<State> {
+            Snapshots {
+                versions: self.versions.clone(),
             }
         }
     }
@@ -73,49 +191,133 @@ mod tests {
 
     use std::thread;
 
+    use ::rpds::{HashTrieMapSync, RedBlackTreeMapSync, VectorSync};
+
     use super::rpds::Store;
 
+    #[derive(Clone, Debug, PartialEq)]
+    struct Project {
+        params: HashTrieMapSync<String, f32>,
+        tracks: VectorSync<String>,
+        markers: RedBlackTreeMapSync<u32, String>,
+    }
+
+    impl Project {
+        fn new() -> Self {
+            Project {
+                params: HashTrieMapSync::new_sync(),
+                tracks: VectorSync::new_sync(),
+                markers: RedBlackTreeMapSync::new_sync(),
+            }
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    enum Action {
+        SetParam(String, f32),
+        AddTrack(String),
+        Mark(u32, String),
+    }
+
+    type R = fn(Project, Action) -> Project;
+    const REDUCER: R = |state: Project, action: Action| match action {
+        Action::SetParam(key, value) => Project {
+            params: state.params.insert(key, value),
+            ..state
+        },
+        Action::AddTrack(name) => Project {
+            tracks: state.tracks.push_back(name),
+            ..state
+        },
+        Action::Mark(time, name) => Project {
+            markers: state.markers.insert(time, name),
+            ..state
+        },
+    };
+
     #[test]
     fn can_create_store() {
-        #[derive(Clone, Debug)]
-        enum Action {}
-        type R = fn(i32, Action) -> i32;
-        let r: R = |state: i32, _: Action| state;
-        let store = Some(Store::<Action, i32, R>::new(r, 0));
-        assert!(store.is_some());
+        let store = Store::<Action, Project, R>::new(REDUCER, Project::new());
+        assert_eq!(store.version(), 0);
+        assert_eq!(store.snapshots().latest().state, Project::new());
     }
 
     #[test]
     fn can_dispatch_different_thread() {
-        #[derive(Clone, Debug)]
-        enum Action {
-            Add(i32),
-            Remove(i32),
-        }
-        type R = fn(i32, Action) -> i32;
-        let r: R = |state: i32, action: Action| match action {
-            Action::Add(n) => state + n,
-            Action::Remove(n) => state - n,
-        };
-        let mut store = Store::<Action, i32, R>::new(r, 0);
+        let mut store = Store::<Action, Project, R>::new(REDUCER, Project::new());
         let dispatcher_1 = store.dispatcher.clone();
         let dispatcher_2 = store.dispatcher.clone();
         let thread_1 = thread::spawn(move || {
-            let _ = dispatcher_1.send(Action::Remove(1));
-            let _ = dispatcher_1.send(Action::Add(1));
-            let _ = dispatcher_1.send(Action::Add(1));
+            let _ = dispatcher_1.send(Action::AddTrack("drums".to_string()));
+            let _ = dispatcher_1.send(Action::SetParam("gain".to_string(), 0.5));
         });
         let thread_2 = thread::spawn(move || {
-            let _ = dispatcher_2.send(Action::Remove(1));
-            let _ = dispatcher_2.send(Action::Add(1));
-            let _ = dispatcher_2.send(Action::Add(1));
+            let _ = dispatcher_2.send(Action::AddTrack("bass".to_string()));
+            let _ = dispatcher_2.send(Action::Mark(4, "verse".to_string()));
         });
         thread_1.join().unwrap();
         thread_2.join().unwrap();
         store.tick();
-        assert_eq!(store.pending_actions.len(), 6);
+        assert_eq!(store.pending_actions.len(), 4);
         store.update();
-        assert_eq!(store.state, 2);
+        assert_eq!(store.state.tracks.len(), 2);
+        assert_eq!(store.state.params.get("gain"), Some(&0.5));
+        assert_eq!(store.state.markers.get(&4), Some(&"verse".to_string()));
         assert_eq!(store.pending_actions.len(), 0);
+        assert_eq!(store.version(), 1);
+    }
+
+    #[test]
+    fn snapshots_keep_committed_versions() {
+        let mut store = Store::<Action, Project, R>::new(REDUCER, Project::new());
+        let snapshots = store.snapshots();
+        store
+            .dispatch(Action::AddTrack("drums".to_string()))
+            .unwrap();
+        store.tick();
+        store.update();
+        // a snapshot taken now is unaffected by later updates
+        let first = snapshots.latest();
+        store
+            .dispatch(Action::AddTrack("bass".to_string()))
+            .unwrap();
+        store
+            .dispatch(Action::SetParam("gain".to_string(), 0.5))
+            .unwrap();
+        store.tick();
+        store.update();
+        // updates without actions do not commit
+        store.tick();
+        store.update();
+        assert_eq!(snapshots.count(), 3);
+        let reader = snapshots.clone();
+        let (first, latest, initial) =
+            thread::spawn(move || (first, reader.latest(), reader.get(0).unwrap()))
+                .join()
+                .unwrap();
+        assert_eq!(first.version, 1);
+        assert_eq!(first.state.tracks.len(), 1);
+        assert!(first.state.params.is_empty());
+        assert_eq!(latest.version, 2);
+        assert_eq!(latest.state, store.state);
+        assert_eq!(initial.state, Project::new());
+        assert!(snapshots.get(3).is_none());
+    }
+
+    #[test]
+    fn history_keeps_the_latest_versions() {
+        let mut store = Store::<Action, Project, R>::new(REDUCER, Project::new()).with_history(2);
+        let snapshots = store.snapshots();
+        for name in ["drums", "bass", "keys"] {
+            store.dispatch(Action::AddTrack(name.to_string())).unwrap();
+            store.tick();
+            store.update();
+        }
+        assert_eq!(snapshots.count(), 2);
+        assert_eq!(snapshots.oldest(), 2);
+        assert!(snapshots.get(1).is_none());
+        assert_eq!(snapshots.get(2).unwrap().state.tracks.len(), 2);
+        assert_eq!(snapshots.latest().version, 3);
+        assert_eq!(store.version(), 3);
     }
 }
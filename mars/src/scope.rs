@@ -0,0 +1,237 @@
+// allow dead_code when checking the lib without tests
+// cargo-analyzer runs `cargo check` for lib the lib with and without tests.
+// dead_code warnings in the ide won't show if the code is used in tests.
+#![cfg_attr(not(test), allow(dead_code))]
+pub mod scope {
+    use std::{fmt::Debug, sync::Arc};
+
+    use crate::{
+        queue::queue::{DispatchError, Dispatcher},
+        reader::reader::{Guard, Publisher, Reader},
+        simple::simple::{Reducer, Store, Subscription},
+    };
+
+    // sends a child's actions to the parent store, wrapped into the parent's
+    // Action. like Dispatcher, clones can be moved to other threads
+    pub struct ScopedDispatcher<Action, ChildAction> {
+        dispatcher: Dispatcher<Action>,
+        wrap: Arc<dyn Fn(ChildAction) -> Action + Send + Sync>,
+    }
+
+    impl<Action, ChildAction> Clone for ScopedDispatcher<Action, ChildAction> {
+        fn clone(&self) -> Self {
+            ScopedDispatcher {
+                dispatcher: self.dispatcher.clone(),
+                wrap: self.wrap.clone(),
+            }
+        }
+    }
+
+    impl<Action, ChildAction> ScopedDispatcher<Action, ChildAction> {
+        // the error holds the wrapped action, the child's is gone by then
+        pub fn send(&self, action: ChildAction) -> Result<(), DispatchError<Action>> {
+            self.dispatcher.send((self.wrap)(action))
+        }
+        // a dispatcher for a scope inside this one, wrapping twice
+        pub fn map<Inner, Wrap>(&self, wrap: Wrap) -> ScopedDispatcher<Action, Inner>
+        where
+            ChildAction: 'static,
+            Action: 'static,
+            Wrap: Fn(Inner) -> ChildAction + Send + Sync + 'static,
+        {
+            let outer = self.wrap.clone();
+            ScopedDispatcher {
+                dispatcher: self.dispatcher.clone(),
+                wrap: Arc::new(move |action| outer(wrap(action))),
+            }
+        }
+    }
+
+    /*
+     the child half of Store::scope, handed to code that should only see a
+     slice of the state and only speak its own action type. the slice is
+     projected from the parent's state by the lens each time update() commits
+     and published like Store::reader, so the child reads it without locks from
+     any thread. dropping the scope stops the projection
+    */
+    pub struct Scope<Action, ChildAction, ChildState> {
+        dispatcher: ScopedDispatcher<Action, ChildAction>,
+        reader: Reader<ChildState>,
+        subscription: Subscription,
+    }
+
+    impl<Action, ChildAction, ChildState> Scope<Action, ChildAction, ChildState> {
+        pub fn dispatch(&self, action: ChildAction) -> Result<(), DispatchError<Action>> {
+            self.dispatcher.send(action)
+        }
+        pub fn dispatcher(&self) -> ScopedDispatcher<Action, ChildAction> {
+            self.dispatcher.clone()
+        }
+        // the slice as of the last update() that changed the parent's state
+        pub fn state(&mut self) -> Guard<'_, ChildState> {
+            self.reader.read()
+        }
+        // another handle to the slice, for other threads
+        pub fn reader(&self) -> Reader<ChildState> {
+            self.reader.clone()
+        }
+    }
+
+    impl<Action, ChildAction, ChildState> Drop for Scope<Action, ChildAction, ChildState> {
+        fn drop(&mut self) {
+            self.subscription.unsubscribe();
+        }
+    }
+
+    impl<Action, State, RootReducer> Store<Action, State, RootReducer>
+    where
+        Action: Debug + Clone + 'static,
+        State: Debug + Clone,
+        RootReducer: Reducer<State, Action> + Send + Sync + 'static,
+    {
+        // a child store over the slice of the state lens selects, whose actions
+        // reach this store through wrap
+        pub fn scope<ChildAction, ChildState, Lens, Wrap>(
+            &mut self,
+            lens: Lens,
+            wrap: Wrap,
+        ) -> Scope<Action, ChildAction, ChildState>
+        where
+            ChildState: Clone + Send + Sync + 'static,
+            Lens: Fn(&State) -> ChildState + Send + 'static,
+            Wrap: Fn(ChildAction) -> Action + Send + Sync + 'static,
+        {
+            let mut publisher = Publisher::new(lens(&self.state));
+            let reader = publisher.reader();
+            // the callback runs on the thread calling update(), which owns the
+            // publisher from here on
+            let subscription = self.subscribe_with(
+                lens,
+                |_: &ChildState, _: &ChildState| false,
+                move |slice: &ChildState| publisher.publish(slice.clone()),
+            );
+            Scope {
+                dispatcher: ScopedDispatcher {
+                    dispatcher: self.dispatcher.clone(),
+                    wrap: Arc::new(wrap),
+                },
+                reader,
+                subscription,
+            }
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use crate::simple::simple::Store;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Track {
+        gain: i32,
+        muted: bool,
+    }
+
+    #[derive(Clone, Debug)]
+    enum TrackAction {
+        Gain(i32),
+        Mute,
+    }
+
+    #[derive(Clone, Debug)]
+    enum Action {
+        Track(usize, TrackAction),
+        Tempo(u32),
+    }
+
+    #[derive(Clone, Debug)]
+    struct State {
+        tempo: u32,
+        tracks: Vec<Track>,
+    }
+
+    fn reducer(mut state: State, action: Action) -> State {
+        match action {
+            Action::Track(index, TrackAction::Gain(gain)) => state.tracks[index].gain += gain,
+            Action::Track(index, TrackAction::Mute) => state.tracks[index].muted = true,
+            Action::Tempo(tempo) => state.tempo = tempo,
+        }
+        state
+    }
+
+    fn state() -> State {
+        State {
+            tempo: 120,
+            tracks: vec![
+                Track {
+                    gain: 0,
+                    muted: false,
+                };
+                2
+            ],
+        }
+    }
+
+    #[test]
+    fn scope_wraps_actions_and_projects_state() {
+        let mut store = Store::new(reducer, state());
+        let mut first = store.scope(|s: &State| s.tracks[0].clone(), |a| Action::Track(0, a));
+        let mut second = store.scope(|s: &State| s.tracks[1].clone(), |a| Action::Track(1, a));
+
+        first.dispatch(TrackAction::Gain(3)).unwrap();
+        let dispatcher = second.dispatcher();
+        thread::spawn(move || dispatcher.send(TrackAction::Mute).unwrap())
+            .join()
+            .unwrap();
+        // the slice only moves on update()
+        store.tick();
+        assert_eq!(first.state().gain, 0);
+        store.update();
+
+        assert_eq!(
+            *first.state(),
+            Track {
+                gain: 3,
+                muted: false
+            }
+        );
+        assert_eq!(
+            *second.state(),
+            Track {
+                gain: 0,
+                muted: true
+            }
+        );
+        store.dispatch(Action::Tempo(90)).unwrap();
+        store.tick();
+        store.update();
+        assert_eq!(first.state().gain, 3);
+        assert_eq!(store.state.tempo, 90);
+    }
+
+    #[test]
+    fn nested_dispatchers_and_drop() {
+        #[derive(Clone, Debug)]
+        enum Edit {
+            Louder,
+        }
+        let mut store = Store::new(reducer, state());
+        let track = store.scope(|s: &State| s.tracks[1].gain, |a| Action::Track(1, a));
+        let knob = track.dispatcher().map(|Edit::Louder| TrackAction::Gain(1));
+        knob.send(Edit::Louder).unwrap();
+        knob.send(Edit::Louder).unwrap();
+        store.tick();
+        store.update();
+        assert_eq!(*track.reader().read(), 2);
+
+        let reader = track.reader();
+        drop(track);
+        knob.send(Edit::Louder).unwrap();
+        store.tick();
+        store.update();
+        assert_eq!(store.state.tracks[1].gain, 3);
+        let mut reader = reader;
+        assert_eq!(*reader.read(), 2);
+    }
+}
@@ -3,10 +3,53 @@
 // dead_code warnings in the ide won't show if the code is used in tests.
 #![cfg_attr(not(test), allow(dead_code))]
 pub mod simple {
-    use std::{fmt::Debug, sync::mpsc};
+    use std::{
+        collections::HashSet,
+        fmt::Debug,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        time::Instant,
+    };
+
+    use crate::{
+        ack::ack::{Closing, Outcome, Stamp},
+        coalesce::coalesce::Coalescer,
+        optimistic::optimistic::Optimistic,
+        queue::queue::{
+            channel, channel_sharing_ids, DispatchError, Dispatcher, Envelope, QueueConfig,
+            Receiver,
+        },
+        reader::reader::{Publisher, Reader},
+        stats::stats::Stats,
+        timetravel::timetravel::Timeline,
+    };
 
     pub trait Reducer<State, Action> {
         fn reduce(&self, state: State, action: Action) -> State;
+        // how the store applies an action. by value reducers need their own copy of
+        // the state and action, reducers wrapped in InPlace override this to skip the clones
+        fn reduce_in_place(&self, state: &mut State, action: &Action)
+        where
+            State: Clone,
+            Action: Clone,
+        {
+            *state = self.reduce(state.clone(), action.clone());
+        }
+        // how time travel, replay and rebases reduce an action that was reduced
+        // before. the same as reduce_in_place, except reducers with side
+        // effects leave them out
+        fn replay_in_place(&self, state: &mut State, action: &Action)
+        where
+            State: Clone,
+            Action: Clone,
+        {
+            self.reduce_in_place(state, action)
+        }
+        // called by update() after each action it reduced for a dispatch.
+        // reducers that collect side effects while reducing start them here
+        fn run_effects(&self) {}
     }
 
     impl<F, State, Action> Reducer<State, Action> for F
@@ -18,12 +61,139 @@ pub mod simple {
         }
     }
 
+    // a reducer that edits the state where it is instead of returning a new one
+    pub trait MutReducer<State, Action> {
+        fn reduce(&self, state: &mut State, action: &Action);
+    }
+
+    impl<F, State, Action> MutReducer<State, Action> for F
+    where
+        F: Fn(&mut State, &Action),
+    {
+        fn reduce(&self, state: &mut State, action: &Action) {
+            self(state, action)
+        }
+    }
+
+    // runs a MutReducer as the root reducer of a store, so update() neither clones
+    // the state nor the action. the state is only cloned when middleware needs
+    // the state from before the action
+    pub struct InPlace<R>(pub R);
+
+    impl<R, State, Action> Reducer<State, Action> for InPlace<R>
+    where
+        R: MutReducer<State, Action>,
+    {
+        fn reduce(&self, mut state: State, action: Action) -> State {
+            self.0.reduce(&mut state, &action);
+            state
+        }
+        fn reduce_in_place(&self, state: &mut State, action: &Action) {
+            self.0.reduce(state, action)
+        }
+    }
+
+    // intercepts actions as they flow through a store. every hook passes the
+    // action through unchanged by default, so a middleware only implements the
+    // stages it cares about. returning None from a hook drops the action, returning
+    // a different action transforms it, and sending on the dispatcher re-dispatches
+    // an action for the next tick.
+    //
+    // ordering: on_dispatch, on_tick and before_reduce run in the order the
+    // middleware was added, after_reduce runs in reverse order so the first
+    // middleware added wraps all the ones added after it.
+    pub trait Middleware<State, Action> {
+        // called by Store::dispatch before the action is sent to the queue
+        fn on_dispatch(&self, action: Action) -> Option<Action> {
+            Some(action)
+        }
+        // called by Store::tick for every action collected from the queue
+        fn on_tick(&self, action: Action) -> Option<Action> {
+            Some(action)
+        }
+        // called by Store::update right before the action is reduced
+        fn before_reduce(
+            &self,
+            _state: &State,
+            action: Action,
+            _dispatcher: &Dispatcher<Action>,
+        ) -> Option<Action> {
+            Some(action)
+        }
+        // called by Store::update with the states before and after the action was reduced
+        fn after_reduce(
+            &self,
+            _before: &State,
+            _after: &State,
+            _action: &Action,
+            _dispatcher: &Dispatcher<Action>,
+        ) {
+        }
+    }
+
+    // handle returned by Store::subscribe. dropping it keeps the subscription alive,
+    // call unsubscribe to stop the callback from firing. the subscription is
+    // removed from the store on the next update()
+    #[derive(Clone, Debug)]
+    pub struct Subscription {
+        active: Arc<AtomicBool>,
+    }
+
+    impl Subscription {
+        pub fn unsubscribe(&self) {
+            self.active.store(false, Ordering::Release);
+        }
+        pub fn is_active(&self) -> bool {
+            self.active.load(Ordering::Acquire)
+        }
+    }
+
+    // a subscription erased to a closure called with every committed state.
+    // subscribe_with's closure re-runs its selector and fires its callback when
+    // the memoized slice changed
+    type Notify<State> = Box<dyn FnMut(&State) + Send>;
+
+    pub const DEFAULT_LANE: &str = "default";
+
+    // one queue of the store, see Store::with_lane
+    pub(crate) struct Lane<Action> {
+        pub(crate) name: String,
+        priority: u8,
+        pub(crate) actions: Receiver<Action>,
+        // None for the default lane, which sends through Store::dispatcher
+        dispatcher: Option<Dispatcher<Action>>,
+    }
+
+    // ordering: update() reduces actions in the order they entered the queue.
+    // the actions one thread sends through one dispatcher are reduced in the
+    // order they were sent (their envelope sequence numbers count up), and an
+    // action whose send happened before another send (same thread, or
+    // synchronized through a lock, channel or join) is reduced first. actions
+    // sent concurrently, from different dispatchers or from threads sharing a
+    // &Dispatcher, have no order other than the one the queue picked, which is
+    // the order tick() collects them in. clone the dispatcher for each thread
+    // when the sequence numbers should follow reduce order.
+    // all of this holds per lane: tick() takes the actions of a lane with a
+    // higher priority before any action of a lower priority lane
     pub struct Store<Action, State, RootReducer> {
-        actions: mpsc::Receiver<Action>,
-        pub dispatcher: mpsc::Sender<Action>,
+        pub(crate) lanes: Vec<Lane<Action>>,
+        tick_budget: Option<usize>,
+        pub dispatcher: Dispatcher<Action>,
         pub state: State,
-        pub pending_actions: Vec<Action>,
-        root_reducer: RootReducer,
+        pub pending_actions: Vec<Envelope<Action>>,
+        pub(crate) root_reducer: RootReducer,
+        middleware: Vec<Box<dyn Middleware<State, Action> + Send>>,
+        subscriptions: Vec<(Subscription, Notify<State>)>,
+        publisher: Option<Publisher<State>>,
+        pub(crate) timeline: Option<Timeline<Action, State>>,
+        pub(crate) coalescer: Option<Coalescer<Action>>,
+        pub(crate) version: u64,
+        // closed when the store is dropped
+        pub(crate) progress: Option<Closing<State>>,
+        pub(crate) stats: Option<Stats<Action>>,
+        pub(crate) optimistic: Option<Optimistic<Action, State>>,
+        // envelopes processed since the last settle and what became of them
+        processed: Vec<(Stamp, Outcome)>,
     }
 
     impl<Action, State, RootReducer> Store<Action, State, RootReducer>
@@ -33,37 +203,375 @@ pub mod simple {
         RootReducer: Reducer<State, Action> + Send + Sync + 'static,
     {
         pub fn new(root_reducer: RootReducer, state: State) -> Self {
-            let (tx, rx) = mpsc::channel::<Action>();
+            Self::with_queue(root_reducer, state, QueueConfig::Unbounded)
+        }
+        // like new but with a choice of queue. a bounded queue is preallocated and
+        // dispatching to it never allocates, so it is safe to dispatch from a
+        // realtime thread. see QueueConfig::Bounded for the lock Coalesce takes
+        // and the cost of waking a parked store thread
+        pub fn with_queue(
+            root_reducer: RootReducer,
+            state: State,
+            queue: QueueConfig<Action>,
+        ) -> Self {
+            let (tx, rx) = channel(queue);
             Store {
-                actions: rx,
+                lanes: vec![Lane {
+                    name: DEFAULT_LANE.to_string(),
+                    priority: 0,
+                    actions: rx,
+                    dispatcher: None,
+                }],
+                tick_budget: None,
                 dispatcher: tx,
-                pending_actions: Vec::<Action>::new(),
+                pending_actions: Vec::<Envelope<Action>>::new(),
                 state,
                 root_reducer,
+                middleware: Vec::new(),
+                subscriptions: Vec::new(),
+                publisher: None,
+                timeline: None,
+                coalescer: None,
+                version: 0,
+                progress: None,
+                stats: None,
+                optimistic: None,
+                processed: Vec::new(),
             }
         }
-        pub fn dispatch(&self, action: Action) -> Result<(), mpsc::SendError<Action>>
+        // a lock free handle to the latest state committed by update(), for
+        // realtime threads. the first call starts publishing the state, every
+        // update() that applied actions then publishes a clone of the new state
+        // and frees the superseded states no reader is holding anymore
+        pub fn reader(&mut self) -> Reader<State>
+        where
+            State: Send + Sync,
+        {
+            let state = &self.state;
+            self.publisher
+                .get_or_insert_with(|| Publisher::new(state.clone()))
+                .reader()
+        }
+        // adds a lane with its own queue. tick() drains lanes from the highest
+        // priority down, lanes with the same priority in the order they were added.
+        // the queue from new/with_queue is the lane "default" with priority 0
+        pub fn with_lane<N: Into<String>>(
+            mut self,
+            name: N,
+            priority: u8,
+            queue: QueueConfig<Action>,
+        ) -> Self {
+            let (tx, rx) = channel_sharing_ids(queue, self.dispatcher.ids());
+            let at = self
+                .lanes
+                .iter()
+                .position(|lane| lane.priority < priority)
+                .unwrap_or(self.lanes.len());
+            self.lanes.insert(
+                at,
+                Lane {
+                    name: name.into(),
+                    priority,
+                    actions: rx,
+                    dispatcher: Some(tx),
+                },
+            );
+            self
+        }
+        // a dispatcher for the named lane
+        pub fn lane(&self, name: &str) -> Option<Dispatcher<Action>> {
+            self.lanes
+                .iter()
+                .find(|lane| lane.name == name)
+                .map(|lane| lane.dispatcher.as_ref().unwrap_or(&self.dispatcher).clone())
+        }
+        // limits how many actions one tick() takes out of the lanes, which bounds
+        // the work of the following update(). whatever is left waits for the next tick
+        pub fn with_tick_budget(mut self, budget: usize) -> Self {
+            self.tick_budget = Some(budget);
+            self
+        }
+        // appends a middleware to the end of the chain
+        pub fn with_middleware<M>(mut self, middleware: M) -> Self
+        where
+            M: Middleware<State, Action> + Send + 'static,
+        {
+            self.middleware.push(Box::new(middleware));
+            self
+        }
+        // calls back with the selected slice of the state after every update() that
+        // changed it. slices are compared with PartialEq against the last slice
+        // the selector returned, the callback does not fire for the initial state
+        pub fn subscribe<Slice, Selector, Callback>(
+            &mut self,
+            selector: Selector,
+            callback: Callback,
+        ) -> Subscription
+        where
+            Slice: PartialEq + Send + 'static,
+            Selector: Fn(&State) -> Slice + Send + 'static,
+            Callback: FnMut(&Slice) + Send + 'static,
+        {
+            self.subscribe_with(selector, |a: &Slice, b: &Slice| a == b, callback)
+        }
+        // like subscribe but only compares the Arc pointers of the selected slice.
+        // persistent collections kept behind an Arc in the state only get a new
+        // pointer when a reducer replaces them, so this skips the deep comparison
+        pub fn subscribe_ptr<Slice, Selector, Callback>(
+            &mut self,
+            selector: Selector,
+            callback: Callback,
+        ) -> Subscription
+        where
+            Slice: Send + Sync + 'static,
+            Selector: Fn(&State) -> Arc<Slice> + Send + 'static,
+            Callback: FnMut(&Arc<Slice>) + Send + 'static,
+        {
+            self.subscribe_with(selector, Arc::ptr_eq, callback)
+        }
+        // subscribe with a custom equality used to memoize the selected slice
+        pub fn subscribe_with<Slice, Selector, Eq, Callback>(
+            &mut self,
+            selector: Selector,
+            eq: Eq,
+            mut callback: Callback,
+        ) -> Subscription
+        where
+            Slice: Send + 'static,
+            Selector: Fn(&State) -> Slice + Send + 'static,
+            Eq: Fn(&Slice, &Slice) -> bool + Send + 'static,
+            Callback: FnMut(&Slice) + Send + 'static,
+        {
+            let mut last = selector(&self.state);
+            self.subscribe_all(move |state: &State| {
+                let next = selector(state);
+                if !eq(&last, &next) {
+                    callback(&next);
+                    last = next;
+                }
+            })
+        }
+        // calls back with every committed state, for subscribers that keep
+        // their own memo
+        pub(crate) fn subscribe_all<Callback>(&mut self, callback: Callback) -> Subscription
+        where
+            Callback: FnMut(&State) + Send + 'static,
+        {
+            let subscription = Subscription {
+                active: Arc::new(AtomicBool::new(true)),
+            };
+            self.subscriptions
+                .push((subscription.clone(), Box::new(callback)));
+            subscription
+        }
+        pub fn dispatch(&self, action: Action) -> Result<(), DispatchError<Action>>
         where
             Action: Send + Sync,
         {
+            let action = match self.on_dispatch(action) {
+                Some(action) => action,
+                None => return Ok(()),
+            };
             println!("{:?} Dispatched", &action);
             self.dispatcher.send(action)
         }
+        // dispatch to the named lane. there is nothing to receive actions sent to a
+        // lane that does not exist, so they come back as Disconnected
+        pub fn dispatch_to(&self, lane: &str, action: Action) -> Result<(), DispatchError<Action>>
+        where
+            Action: Send + Sync,
+        {
+            let lane = match self.lanes.iter().find(|l| l.name == lane) {
+                Some(lane) => lane,
+                None => return Err(DispatchError::Disconnected(action)),
+            };
+            let action = match self.on_dispatch(action) {
+                Some(action) => action,
+                None => return Ok(()),
+            };
+            println!("{:?} Dispatched to {}", &action, lane.name);
+            lane.dispatcher
+                .as_ref()
+                .unwrap_or(&self.dispatcher)
+                .send(action)
+        }
+        // runs the on_dispatch hooks, None if one dropped the action
+        pub(crate) fn on_dispatch(&self, action: Action) -> Option<Action> {
+            let action = self
+                .middleware
+                .iter()
+                .try_fold(action, |action, m| m.on_dispatch(action));
+            if let (None, Some(stats)) = (&action, self.stats.as_ref()) {
+                stats.rejected();
+            }
+            action
+        }
         pub fn tick(&mut self) {
-            self.pending_actions = self.actions.try_iter().collect::<Vec<Action>>();
+            self.take_actions();
+        }
+        // tick, returning how many actions it took out of the lanes
+        pub(crate) fn take_actions(&mut self) -> usize {
+            // the budget counts the actions the middleware drops too, they were dequeued
+            let budget = self.tick_budget.unwrap_or(usize::MAX);
+            let mut dequeued = 0;
+            self.pending_actions = Vec::new();
+            // the acks waited on before draining, and the stamps this tick received.
+            // once every lane is drained, the waited on actions that never came
+            // out were overwritten or merged by a full bounded queue
+            let waiting = match self.progress.as_ref() {
+                Some(progress) => progress.waiting(),
+                None => Vec::new(),
+            };
+            let mut received = HashSet::new();
+            let mut drained = true;
+            for lane in self.lanes.iter() {
+                let from_lane = dequeued;
+                let queued = lane.actions.queued();
+                while dequeued < budget {
+                    let envelope = match lane.actions.try_recv() {
+                        Some(envelope) => envelope,
+                        None => break,
+                    };
+                    dequeued += 1;
+                    let stamp = (envelope.dispatcher, envelope.sequence);
+                    if !waiting.is_empty() {
+                        received.insert(stamp);
+                    }
+                    match self
+                        .middleware
+                        .iter()
+                        .try_fold(envelope.action, |action, m| m.on_tick(action))
+                    {
+                        Some(action) => self.pending_actions.push(Envelope { action, ..envelope }),
+                        None => {
+                            if self.progress.is_some() {
+                                self.processed.push((stamp, Outcome::Dropped));
+                            }
+                            if let Some(stats) = self.stats.as_mut() {
+                                stats.dropped();
+                            }
+                        }
+                    }
+                }
+                drained = drained && dequeued < budget && lane.actions.settled();
+                if let Some(stats) = self.stats.as_mut() {
+                    stats.dequeued(&lane.name, dequeued - from_lane, queued);
+                }
+            }
+            if drained {
+                for stamp in waiting.into_iter().filter(|s| !received.contains(s)) {
+                    self.processed.push((stamp, Outcome::Dropped));
+                }
+            }
+            let collected = self.pending_actions.len();
+            if let Some(coalescer) = self.coalescer.as_ref() {
+                let stamps = |pending: &[Envelope<Action>]| {
+                    pending
+                        .iter()
+                        .map(|envelope| (envelope.dispatcher, envelope.sequence))
+                        .collect::<HashSet<_>>()
+                };
+                let before = match self.progress.is_some() {
+                    true => stamps(&self.pending_actions),
+                    false => HashSet::new(),
+                };
+                self.pending_actions =
+                    coalescer.coalesce(std::mem::take(&mut self.pending_actions));
+                if !before.is_empty() {
+                    // merged actions give up their stamp to the one they merged into
+                    let after = stamps(&self.pending_actions);
+                    for stamp in before.difference(&after) {
+                        self.processed.push((*stamp, Outcome::Coalesced));
+                    }
+                }
+            }
+            if let Some(stats) = self.stats.as_mut() {
+                stats.ticked(dequeued, collected - self.pending_actions.len());
+            }
             println!(
                 "Store Update: {} actions in the queue",
                 self.pending_actions.len()
             );
+            dequeued
         }
         // called by owning thread to collect & process updates the state with the reducer
         pub fn update(&mut self) {
-            while let Some(action) = self.pending_actions.pop() {
-                self.state = self.root_reducer.reduce(self.state.clone(), action.clone());
+            let mut applied = self.reconcile_reports();
+            for envelope in std::mem::take(&mut self.pending_actions) {
+                let stamp = (envelope.dispatcher, envelope.sequence);
+                if self.progress.is_some() {
+                    self.processed.push((stamp, Outcome::Dropped));
+                }
+                let action = match self
+                    .middleware
+                    .iter()
+                    .try_fold(envelope.action, |action, m| {
+                        m.before_reduce(&self.state, action, &self.dispatcher)
+                    }) {
+                    Some(action) => action,
+                    None => {
+                        if let Some(stats) = self.stats.as_mut() {
+                            stats.dropped();
+                        }
+                        continue;
+                    }
+                };
+                let before = match self.middleware.is_empty() {
+                    true => None,
+                    false => Some(self.state.clone()),
+                };
+                let started = self.stats.is_some().then(Instant::now);
+                self.root_reducer.reduce_in_place(&mut self.state, &action);
+                if let (Some(started), Some(stats)) = (started, self.stats.as_mut()) {
+                    stats.reduced(&action, started.elapsed());
+                }
+                self.root_reducer.run_effects();
+                if let Some(before) = before {
+                    for m in self.middleware.iter().rev() {
+                        m.after_reduce(&before, &self.state, &action, &self.dispatcher);
+                    }
+                }
                 println!(
                     "Store Update: {:?} applied to state. New state: {:?}",
                     action, self.state
                 );
+                if let Some(optimistic) = self.optimistic.as_mut() {
+                    optimistic.propose(&action);
+                }
+                if let Some(timeline) = self.timeline.as_mut() {
+                    timeline.record(action, self.state.clone());
+                }
+                if let Some((_, outcome)) = self.processed.last_mut() {
+                    *outcome = Outcome::Applied;
+                }
+                applied = true;
+            }
+            match applied {
+                true => self.commit(),
+                false => self.settle(),
+            }
+        }
+        // hands a new state to readers, waiting threads and subscribers
+        pub(crate) fn commit(&mut self) {
+            self.version += 1;
+            if let Some(publisher) = self.publisher.as_mut() {
+                publisher.publish(self.state.clone());
+            }
+            self.settle();
+            self.notify_subscribers();
+        }
+        // resolves the acks of the actions processed since the last settle
+        fn settle(&mut self) {
+            if let Some(progress) = self.progress.as_ref() {
+                let processed = std::mem::take(&mut self.processed);
+                progress.settle(self.version, &self.state, processed);
+            }
+        }
+        fn notify_subscribers(&mut self) {
+            self.subscriptions
+                .retain(|(subscription, _)| subscription.is_active());
+            for (_, notify) in self.subscriptions.iter_mut() {
+                notify(&self.state);
             }
         }
     }
@@ -71,9 +579,16 @@ pub mod simple {
 #[cfg(test)]
 mod tests {
 
-    use std::thread;
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+        thread,
+    };
 
-    use super::simple::Store;
+    use super::simple::{InPlace, Middleware, Store};
+    use crate::queue::queue::{Backpressure, DispatchError, Dispatcher, QueueConfig};
 
     #[test]
     fn can_create_store() {
@@ -118,4 +633,484 @@ mod tests {
         assert_eq!(store.state, 2);
         assert_eq!(store.pending_actions.len(), 0);
     }
+
+    #[test]
+    fn middleware_runs_in_order() {
+        #[derive(Clone, Debug)]
+        enum Action {
+            Add(i32),
+        }
+        struct Tag(&'static str, Arc<Mutex<Vec<String>>>);
+        impl Middleware<i32, Action> for Tag {
+            fn on_dispatch(&self, action: Action) -> Option<Action> {
+                self.1.lock().unwrap().push(format!("{} dispatch", self.0));
+                Some(action)
+            }
+            fn on_tick(&self, action: Action) -> Option<Action> {
+                self.1.lock().unwrap().push(format!("{} tick", self.0));
+                Some(action)
+            }
+            fn before_reduce(
+                &self,
+                _state: &i32,
+                action: Action,
+                _dispatcher: &Dispatcher<Action>,
+            ) -> Option<Action> {
+                self.1.lock().unwrap().push(format!("{} before", self.0));
+                Some(action)
+            }
+            fn after_reduce(
+                &self,
+                before: &i32,
+                after: &i32,
+                _action: &Action,
+                _dispatcher: &Dispatcher<Action>,
+            ) {
+                self.1
+                    .lock()
+                    .unwrap()
+                    .push(format!("{} after {} -> {}", self.0, before, after));
+            }
+        }
+        type R = fn(i32, Action) -> i32;
+        let r: R = |state: i32, action: Action| match action {
+            Action::Add(n) => state + n,
+        };
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut store = Store::<Action, i32, R>::new(r, 0)
+            .with_middleware(Tag("a", log.clone()))
+            .with_middleware(Tag("b", log.clone()));
+        store.dispatch(Action::Add(2)).unwrap();
+        store.tick();
+        store.update();
+        assert_eq!(store.state, 2);
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![
+                "a dispatch",
+                "b dispatch",
+                "a tick",
+                "b tick",
+                "a before",
+                "b before",
+                "b after 0 -> 2",
+                "a after 0 -> 2",
+            ]
+        );
+    }
+
+    #[test]
+    fn middleware_can_transform_drop_and_redispatch() {
+        #[derive(Clone, Debug, PartialEq)]
+        enum Action {
+            Add(i32),
+            Double,
+            Ignored,
+        }
+        // drops Ignored at dispatch, turns Add into Double when the state is
+        // already past 10, and re-dispatches Add(1) after every Double
+        struct Rules;
+        impl Middleware<i32, Action> for Rules {
+            fn on_dispatch(&self, action: Action) -> Option<Action> {
+                match action {
+                    Action::Ignored => None,
+                    action => Some(action),
+                }
+            }
+            fn before_reduce(
+                &self,
+                state: &i32,
+                action: Action,
+                _dispatcher: &Dispatcher<Action>,
+            ) -> Option<Action> {
+                match action {
+                    Action::Add(_) if *state > 10 => Some(Action::Double),
+                    action => Some(action),
+                }
+            }
+            fn after_reduce(
+                &self,
+                _before: &i32,
+                _after: &i32,
+                action: &Action,
+                dispatcher: &Dispatcher<Action>,
+            ) {
+                if *action == Action::Double {
+                    dispatcher.send(Action::Add(1)).unwrap();
+                }
+            }
+        }
+        type R = fn(i32, Action) -> i32;
+        let r: R = |state: i32, action: Action| match action {
+            Action::Add(n) => state + n,
+            Action::Double => state * 2,
+            Action::Ignored => panic!("ignored actions never reach the reducer"),
+        };
+        let mut store = Store::<Action, i32, R>::new(r, 0).with_middleware(Rules);
+        store.dispatch(Action::Ignored).unwrap();
+        store.dispatch(Action::Add(11)).unwrap();
+        store.tick();
+        assert_eq!(store.pending_actions.len(), 1);
+        store.update();
+        assert_eq!(store.state, 11);
+        store.dispatch(Action::Add(1)).unwrap();
+        store.tick();
+        store.update();
+        assert_eq!(store.state, 22);
+        // the re-dispatched Add(1) is picked up on the next tick and doubled again
+        store.tick();
+        assert_eq!(store.pending_actions[0].action, Action::Add(1));
+        store.update();
+        assert_eq!(store.state, 44);
+    }
+
+    #[test]
+    fn subscribers_fire_only_when_slice_changes() {
+        #[derive(Clone, Debug)]
+        struct State {
+            volume: i32,
+            pan: i32,
+        }
+        #[derive(Clone, Debug)]
+        enum Action {
+            Volume(i32),
+            Pan(i32),
+        }
+        type R = fn(State, Action) -> State;
+        let r: R = |state: State, action: Action| match action {
+            Action::Volume(volume) => State { volume, ..state },
+            Action::Pan(pan) => State { pan, ..state },
+        };
+        let mut store = Store::<Action, State, R>::new(r, State { volume: 0, pan: 0 });
+        let volumes = Arc::new(Mutex::new(Vec::new()));
+        let volumes_clone = volumes.clone();
+        let volume = store.subscribe(
+            |state: &State| state.volume,
+            move |volume: &i32| volumes_clone.lock().unwrap().push(*volume),
+        );
+        let pans = Arc::new(AtomicUsize::new(0));
+        let pans_clone = pans.clone();
+        store.subscribe(
+            |state: &State| state.pan,
+            move |_: &i32| {
+                pans_clone.fetch_add(1, Ordering::SeqCst);
+            },
+        );
+        store.dispatch(Action::Pan(3)).unwrap();
+        store.tick();
+        store.update();
+        assert!(volumes.lock().unwrap().is_empty());
+        assert_eq!(pans.load(Ordering::SeqCst), 1);
+        store.dispatch(Action::Volume(5)).unwrap();
+        store.tick();
+        store.update();
+        // setting the same value again does not change the slice
+        store.dispatch(Action::Volume(5)).unwrap();
+        store.tick();
+        store.update();
+        assert_eq!(*volumes.lock().unwrap(), vec![5]);
+        volume.unsubscribe();
+        assert!(!volume.is_active());
+        store.dispatch(Action::Volume(7)).unwrap();
+        store.dispatch(Action::Pan(4)).unwrap();
+        store.tick();
+        store.update();
+        assert_eq!(*volumes.lock().unwrap(), vec![5]);
+        assert_eq!(pans.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn ptr_subscribers_skip_unchanged_collections() {
+        use rpds::VectorSync;
+        #[derive(Clone, Debug)]
+        struct State {
+            tracks: Arc<VectorSync<String>>,
+            tempo: i32,
+        }
+        #[derive(Clone, Debug)]
+        enum Action {
+            AddTrack(String),
+            Tempo(i32),
+        }
+        type R = fn(State, Action) -> State;
+        let r: R = |state: State, action: Action| match action {
+            Action::AddTrack(name) => State {
+                tracks: Arc::new(state.tracks.push_back(name)),
+                ..state
+            },
+            Action::Tempo(tempo) => State { tempo, ..state },
+        };
+        let initial = State {
+            tracks: Arc::new(VectorSync::new_sync()),
+            tempo: 120,
+        };
+        let mut store = Store::<Action, State, R>::new(r, initial);
+        let renders = Arc::new(Mutex::new(Vec::new()));
+        let renders_clone = renders.clone();
+        store.subscribe_ptr(
+            |state: &State| state.tracks.clone(),
+            move |tracks: &Arc<VectorSync<String>>| {
+                renders_clone.lock().unwrap().push(tracks.len());
+            },
+        );
+        store.dispatch(Action::Tempo(90)).unwrap();
+        store.tick();
+        store.update();
+        assert_eq!(store.state.tempo, 90);
+        assert!(renders.lock().unwrap().is_empty());
+        store
+            .dispatch(Action::AddTrack("drums".to_string()))
+            .unwrap();
+        store.tick();
+        store.update();
+        assert_eq!(*renders.lock().unwrap(), vec![1]);
+    }
+
+    #[test]
+    fn readers_follow_committed_states() {
+        #[derive(Clone, Debug)]
+        struct State {
+            count: i32,
+            double: i32,
+        }
+        #[derive(Clone, Debug)]
+        enum Action {
+            Increment,
+        }
+        type R = fn(State, Action) -> State;
+        let r: R = |state: State, _: Action| State {
+            count: state.count + 1,
+            double: (state.count + 1) * 2,
+        };
+        let mut store = Store::<Action, State, R>::new(
+            r,
+            State {
+                count: 0,
+                double: 0,
+            },
+        );
+        let readers = (0..4)
+            .map(|_| {
+                let mut reader = store.reader();
+                thread::spawn(move || {
+                    let mut last = 0;
+                    while last < 2_000 {
+                        let state = reader.read();
+                        // a reader never sees a half applied or older state
+                        assert_eq!(state.double, state.count * 2);
+                        assert!(state.count >= last);
+                        last = state.count;
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for _ in 0..2_000 {
+            store.dispatch(Action::Increment).unwrap();
+            store.tick();
+            store.update();
+        }
+        for reader in readers {
+            reader.join().unwrap();
+        }
+        assert_eq!(store.reader().read().count, 2_000);
+    }
+
+    #[test]
+    fn can_dispatch_to_bounded_queue() {
+        #[derive(Clone, Debug, PartialEq)]
+        enum Action {
+            Add(i32),
+        }
+        type R = fn(i32, Action) -> i32;
+        let r: R = |state: i32, action: Action| match action {
+            Action::Add(n) => state + n,
+        };
+        let queue = QueueConfig::Bounded {
+            capacity: 2,
+            backpressure: Backpressure::Reject,
+        };
+        let mut store = Store::<Action, i32, R>::with_queue(r, 0, queue);
+        let dispatcher = store.dispatcher.clone();
+        thread::spawn(move || {
+            dispatcher.send(Action::Add(1)).unwrap();
+            dispatcher.send(Action::Add(2)).unwrap();
+            assert_eq!(
+                dispatcher.send(Action::Add(3)),
+                Err(DispatchError::Full(Action::Add(3)))
+            );
+        })
+        .join()
+        .unwrap();
+        store.tick();
+        store.update();
+        assert_eq!(store.state, 3);
+        store.dispatch(Action::Add(3)).unwrap();
+        store.tick();
+        store.update();
+        assert_eq!(store.state, 6);
+    }
+
+    #[test]
+    fn update_reduces_in_fifo_and_causal_order() {
+        #[derive(Clone, Debug)]
+        enum Action {
+            Push(&'static str),
+        }
+        // the reducer is order sensitive, the state records the order actions were applied in
+        type R = fn(String, Action) -> String;
+        let r: R = |state: String, action: Action| match action {
+            Action::Push(s) => state + s,
+        };
+        let mut store = Store::<Action, String, R>::new(r, String::new());
+        store.dispatch(Action::Push("a")).unwrap();
+        store.dispatch(Action::Push("b")).unwrap();
+        store.dispatch(Action::Push("c")).unwrap();
+        store.tick();
+        store.update();
+        assert_eq!(store.state, "abc");
+
+        // thread 2 only sends after it heard from thread 1, so thread 1's actions come first
+        let dispatcher_1 = store.dispatcher.clone();
+        let dispatcher_2 = store.dispatcher.clone();
+        let (tx, rx) = std::sync::mpsc::channel();
+        let thread_2 = thread::spawn(move || {
+            rx.recv().unwrap();
+            dispatcher_2.send(Action::Push("f")).unwrap();
+            dispatcher_2.send(Action::Push("g")).unwrap();
+        });
+        let thread_1 = thread::spawn(move || {
+            dispatcher_1.send(Action::Push("d")).unwrap();
+            dispatcher_1.send(Action::Push("e")).unwrap();
+            tx.send(()).unwrap();
+        });
+        thread_1.join().unwrap();
+        thread_2.join().unwrap();
+        store.tick();
+        let stamps = store
+            .pending_actions
+            .iter()
+            .map(|envelope| (envelope.dispatcher, envelope.sequence))
+            .collect::<Vec<_>>();
+        assert_eq!(stamps, vec![(1, 0), (1, 1), (2, 0), (2, 1)]);
+        store.update();
+        assert_eq!(store.state, "abcdefg");
+    }
+
+    #[test]
+    fn each_dispatcher_is_fifo_under_contention() {
+        #[derive(Clone, Debug)]
+        enum Action {
+            Record(u64, u64),
+        }
+        // the state is the last value seen per dispatcher, the reducer checks they count up
+        type R = fn(Vec<u64>, Action) -> Vec<u64>;
+        let r: R = |mut state: Vec<u64>, action: Action| match action {
+            Action::Record(thread, value) => {
+                let last = &mut state[thread as usize];
+                assert_eq!(*last + 1, value);
+                *last = value;
+                state
+            }
+        };
+        let mut store = Store::<Action, Vec<u64>, R>::new(r, vec![0; 4]);
+        let threads = (0..4)
+            .map(|thread| {
+                let dispatcher = store.dispatcher.clone();
+                thread::spawn(move || {
+                    for value in 1..=500 {
+                        dispatcher.send(Action::Record(thread, value)).unwrap();
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        while store.state.iter().any(|last| *last < 500) {
+            store.tick();
+            store.update();
+        }
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(store.state, vec![500; 4]);
+    }
+
+    #[test]
+    fn can_reduce_in_place() {
+        #[derive(Clone, Debug)]
+        enum Action {
+            Push(i32),
+            Clear,
+        }
+        let r = |state: &mut Vec<i32>, action: &Action| match action {
+            Action::Push(n) => state.push(*n),
+            Action::Clear => state.clear(),
+        };
+        let mut store = Store::new(InPlace(r), Vec::new());
+        store.dispatch(Action::Push(1)).unwrap();
+        store.dispatch(Action::Push(2)).unwrap();
+        store.tick();
+        store.update();
+        assert_eq!(store.state, vec![1, 2]);
+        store.dispatch(Action::Clear).unwrap();
+        store.dispatch(Action::Push(3)).unwrap();
+        store.tick();
+        store.update();
+        assert_eq!(store.state, vec![3]);
+    }
+
+    #[test]
+    fn lanes_drain_by_priority() {
+        type R = fn(Vec<&'static str>, &'static str) -> Vec<&'static str>;
+        let r: R = |mut state, action| {
+            state.push(action);
+            state
+        };
+        let mut store = Store::<&'static str, Vec<&'static str>, R>::new(r, vec![])
+            .with_lane("background", 0, QueueConfig::Unbounded)
+            .with_lane("realtime", 2, QueueConfig::Unbounded)
+            .with_lane("interactive", 1, QueueConfig::Unbounded);
+        let realtime = store.lane("realtime").unwrap();
+        store.dispatch_to("background", "paste").unwrap();
+        store.dispatch("default").unwrap();
+        store.dispatch_to("interactive", "click").unwrap();
+        realtime.send("gain 1").unwrap();
+        realtime.send("gain 2").unwrap();
+        assert_eq!(
+            store.dispatch_to("missing", "lost"),
+            Err(DispatchError::Disconnected("lost"))
+        );
+        store.tick();
+        store.update();
+        assert_eq!(
+            store.state,
+            vec!["gain 1", "gain 2", "click", "default", "paste"]
+        );
+    }
+
+    #[test]
+    fn tick_budget_bounds_update() {
+        type R = fn(Vec<i32>, i32) -> Vec<i32>;
+        let r: R = |mut state, action| {
+            state.push(action);
+            state
+        };
+        let mut store = Store::<i32, Vec<i32>, R>::new(r, vec![])
+            .with_lane("realtime", 1, QueueConfig::Unbounded)
+            .with_tick_budget(3);
+        let realtime = store.lane("realtime").unwrap();
+        for i in 0..5 {
+            store.dispatch(i).unwrap();
+        }
+        realtime.send(100).unwrap();
+        store.tick();
+        assert_eq!(store.pending_actions.len(), 3);
+        store.update();
+        assert_eq!(store.state, vec![100, 0, 1]);
+        // urgent actions sent since overtake the backlog
+        realtime.send(101).unwrap();
+        store.tick();
+        store.update();
+        store.tick();
+        store.update();
+        assert_eq!(store.state, vec![100, 0, 1, 101, 2, 3, 4]);
+    }
 }
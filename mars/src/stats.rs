@@ -0,0 +1,767 @@
+// allow dead_code when checking the lib without tests
+// cargo-analyzer runs `cargo check` for lib the lib with and without tests.
+// dead_code warnings in the ide won't show if the code is used in tests.
+#![cfg_attr(not(test), allow(dead_code))]
+pub mod stats {
+    use std::{
+        collections::BTreeMap,
+        fmt::{Debug, Write as _},
+        fs,
+        io::{self, BufRead, BufReader, Read, Write},
+        net::TcpStream,
+        path::Path,
+        sync::{
+            atomic::{AtomicBool, AtomicU64, Ordering},
+            Arc, Mutex,
+        },
+        thread,
+        time::Duration,
+    };
+
+    use crate::{
+        action::action::Action as Tagged,
+        devtools::devtools::{check_loopback, listen, wake, Endpoint, Listener},
+        simple::simple::{Reducer, Store},
+    };
+
+    // upper bounds of the reducer duration buckets, in nanoseconds
+    pub const DURATION_BUCKETS: &[u64] = &[
+        1_000,
+        5_000,
+        10_000,
+        50_000,
+        100_000,
+        500_000,
+        1_000_000,
+        5_000_000,
+        10_000_000,
+        50_000_000,
+        100_000_000,
+        1_000_000_000,
+    ];
+    // upper bounds of the actions per tick buckets
+    pub const COUNT_BUCKETS: &[u64] = &[0, 1, 2, 4, 8, 16, 32, 64, 128, 256, 512, 1024];
+
+    // counts values into fixed buckets, each holding the values up to its bound
+    // and above the previous one. the last bucket takes everything larger
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct Histogram {
+        bounds: &'static [u64],
+        buckets: Vec<u64>,
+        count: u64,
+        sum: u64,
+        max: u64,
+    }
+
+    impl Histogram {
+        pub fn new(bounds: &'static [u64]) -> Self {
+            Histogram {
+                bounds,
+                buckets: vec![0; bounds.len() + 1],
+                count: 0,
+                sum: 0,
+                max: 0,
+            }
+        }
+        pub fn record(&mut self, value: u64) {
+            let bucket = self.bounds.partition_point(|&bound| bound < value);
+            self.buckets[bucket] += 1;
+            self.count += 1;
+            self.sum = self.sum.saturating_add(value);
+            self.max = self.max.max(value);
+        }
+        pub fn count(&self) -> u64 {
+            self.count
+        }
+        pub fn sum(&self) -> u64 {
+            self.sum
+        }
+        pub fn max(&self) -> u64 {
+            self.max
+        }
+        pub fn mean(&self) -> f64 {
+            match self.count {
+                0 => 0.0,
+                count => self.sum as f64 / count as f64,
+            }
+        }
+        // an upper bound of the q quantile: the bound of the bucket it falls in,
+        // or the max for the last bucket
+        pub fn quantile(&self, q: f64) -> u64 {
+            let rank = (q.clamp(0.0, 1.0) * self.count as f64).ceil().max(1.0) as u64;
+            let mut seen = 0;
+            for (bucket, count) in self.buckets.iter().enumerate() {
+                seen += count;
+                if seen >= rank {
+                    return self
+                        .bounds
+                        .get(bucket)
+                        .map_or(self.max, |&b| b.min(self.max));
+                }
+            }
+            self.max
+        }
+        // (bound, values up to bound) for every bucket, None for the last one
+        pub fn cumulative(&self) -> impl Iterator<Item = (Option<u64>, u64)> + '_ {
+            let bounds = self.bounds.iter().map(|&bound| Some(bound));
+            let mut seen = 0;
+            bounds
+                .chain(std::iter::once(None))
+                .zip(self.buckets.iter())
+                .map(move |(bound, count)| {
+                    seen += count;
+                    (bound, seen)
+                })
+        }
+    }
+
+    #[derive(Clone, Debug, Default, PartialEq)]
+    pub struct LaneStats {
+        pub name: String,
+        // the most actions a tick took out of the lane at once
+        pub dequeued_per_tick_max: usize,
+        // the most actions waiting in the lane when a tick started. only bounded
+        // queues know, None for unbounded lanes
+        pub depth_high_water: Option<usize>,
+        pub dequeued: u64,
+        // from the lane's bounded queue, see QueueCounters
+        pub rejected: u64,
+        pub overwritten: u64,
+        pub merged: u64,
+    }
+
+    // a snapshot of what a store did since with_stats or reset_stats
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct StoreStats {
+        // time spent in the root reducer by action kind, in nanoseconds
+        pub reducers: BTreeMap<&'static str, Histogram>,
+        pub actions_per_tick: Histogram,
+        pub ticks: u64,
+        pub applied: u64,
+        pub lanes: Vec<LaneStats>,
+        // dropped by on_dispatch middleware
+        pub rejected: u64,
+        // dropped by on_tick and before_reduce middleware
+        pub dropped: u64,
+        // merged into other actions by the coalescer
+        pub coalesced: u64,
+        // connections the PrometheusExporter failed to accept, filled in by
+        // PrometheusExporter::publish
+        pub scrape_errors: u64,
+    }
+
+    impl Default for StoreStats {
+        fn default() -> Self {
+            StoreStats {
+                reducers: BTreeMap::new(),
+                actions_per_tick: Histogram::new(COUNT_BUCKETS),
+                ticks: 0,
+                applied: 0,
+                lanes: Vec::new(),
+                rejected: 0,
+                dropped: 0,
+                coalesced: 0,
+                scrape_errors: 0,
+            }
+        }
+    }
+
+    // escapes a prometheus label value
+    fn label(value: &str) -> String {
+        value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    }
+
+    impl StoreStats {
+        // the stats in the prometheus text exposition format, metric names start
+        // with prefix (e.g. "mars")
+        pub fn to_prometheus(&self, prefix: &str) -> String {
+            let mut out = String::new();
+            let header = |out: &mut String, name: &str, kind: &str, help: &str| {
+                let _ = writeln!(out, "# HELP {}_{} {}", prefix, name, help);
+                let _ = writeln!(out, "# TYPE {}_{} {}", prefix, name, kind);
+            };
+            let histogram =
+                |out: &mut String, name: &str, labels: &str, histogram: &Histogram, scale: f64| {
+                    let separator = if labels.is_empty() { "" } else { "," };
+                    for (bound, count) in histogram.cumulative() {
+                        let le =
+                            bound.map_or("+Inf".to_string(), |b| (b as f64 / scale).to_string());
+                        let _ = writeln!(
+                            out,
+                            "{}_{}_bucket{{{}{}le=\"{}\"}} {}",
+                            prefix, name, labels, separator, le, count
+                        );
+                    }
+                    let labels = match labels.is_empty() {
+                        true => String::new(),
+                        false => format!("{{{}}}", labels),
+                    };
+                    let sum = histogram.sum() as f64 / scale;
+                    let _ = writeln!(out, "{}_{}_sum{} {}", prefix, name, labels, sum);
+                    let _ = writeln!(
+                        out,
+                        "{}_{}_count{} {}",
+                        prefix,
+                        name,
+                        labels,
+                        histogram.count()
+                    );
+                };
+
+            header(
+                &mut out,
+                "reducer_duration_seconds",
+                "histogram",
+                "Time spent in the root reducer by action kind.",
+            );
+            for (kind, reducer) in &self.reducers {
+                let labels = format!("kind=\"{}\"", label(kind));
+                histogram(&mut out, "reducer_duration_seconds", &labels, reducer, 1e9);
+            }
+            header(
+                &mut out,
+                "actions_per_tick",
+                "histogram",
+                "Actions a tick took out of the lanes.",
+            );
+            histogram(
+                &mut out,
+                "actions_per_tick",
+                "",
+                &self.actions_per_tick,
+                1.0,
+            );
+
+            let counters = [
+                ("ticks_total", "Ticks.", self.ticks),
+                ("actions_applied_total", "Actions reduced.", self.applied),
+                (
+                    "actions_rejected_total",
+                    "Actions dropped by on_dispatch middleware.",
+                    self.rejected,
+                ),
+                (
+                    "actions_dropped_total",
+                    "Actions dropped by on_tick and before_reduce middleware.",
+                    self.dropped,
+                ),
+                (
+                    "actions_coalesced_total",
+                    "Actions merged into others by the coalescer.",
+                    self.coalesced,
+                ),
+                (
+                    "scrape_errors_total",
+                    "Scrape connections the exporter failed to accept.",
+                    self.scrape_errors,
+                ),
+            ];
+            for (name, help, value) in counters {
+                header(&mut out, name, "counter", help);
+                let _ = writeln!(out, "{}_{} {}", prefix, name, value);
+            }
+
+            // lanes without a value, unbounded lanes for the depth, are left out
+            type LaneValue = fn(&LaneStats) -> Option<u64>;
+            let lanes: [(&str, &str, &str, LaneValue); 6] = [
+                (
+                    "queue_depth_high_water",
+                    "gauge",
+                    "The most actions waiting in the bounded lane when a tick started.",
+                    |lane| lane.depth_high_water.map(|depth| depth as u64),
+                ),
+                (
+                    "queue_dequeued_per_tick_max",
+                    "gauge",
+                    "The most actions a tick took out of the lane.",
+                    |lane| Some(lane.dequeued_per_tick_max as u64),
+                ),
+                (
+                    "queue_dequeued_total",
+                    "counter",
+                    "Actions taken out of the lane.",
+                    |lane| Some(lane.dequeued),
+                ),
+                (
+                    "queue_rejected_total",
+                    "counter",
+                    "Actions the full lane turned away.",
+                    |lane| Some(lane.rejected),
+                ),
+                (
+                    "queue_overwritten_total",
+                    "counter",
+                    "Queued actions the full lane dropped for newer ones.",
+                    |lane| Some(lane.overwritten),
+                ),
+                (
+                    "queue_merged_total",
+                    "counter",
+                    "Actions the full lane merged into its overflow.",
+                    |lane| Some(lane.merged),
+                ),
+            ];
+            for (name, kind, help, value) in lanes {
+                let values = self
+                    .lanes
+                    .iter()
+                    .filter_map(|lane| Some((lane, value(lane)?)))
+                    .collect::<Vec<_>>();
+                if values.is_empty() {
+                    continue;
+                }
+                header(&mut out, name, kind, help);
+                for (lane, value) in values {
+                    let _ = writeln!(
+                        out,
+                        "{}_{}{{lane=\"{}\"}} {}",
+                        prefix,
+                        name,
+                        label(&lane.name),
+                        value
+                    );
+                }
+            }
+            out
+        }
+        // writes the prometheus text to path through a temporary file, so a
+        // collector reading the file never sees half of it
+        pub fn write_prometheus<P: AsRef<Path>>(&self, path: P, prefix: &str) -> io::Result<()> {
+            let path = path.as_ref();
+            let mut temporary = path.as_os_str().to_owned();
+            temporary.push(".tmp");
+            fs::write(&temporary, self.to_prometheus(prefix))?;
+            fs::rename(&temporary, path)
+        }
+    }
+
+    // what the store keeps while stats are enabled
+    pub(crate) struct Stats<Action> {
+        kind: Box<dyn Fn(&Action) -> &'static str + Send + Sync>,
+        // on_dispatch only gets &Store
+        rejected: AtomicU64,
+        stats: StoreStats,
+    }
+
+    impl<Action> Stats<Action> {
+        pub(crate) fn rejected(&self) {
+            self.rejected.fetch_add(1, Ordering::Relaxed);
+        }
+        pub(crate) fn dropped(&mut self) {
+            self.stats.dropped += 1;
+        }
+        // count actions were taken out of lane, queued were waiting in it before
+        pub(crate) fn dequeued(&mut self, lane: &str, count: usize, queued: Option<usize>) {
+            let index = match self.stats.lanes.iter().position(|l| l.name == lane) {
+                Some(index) => index,
+                None => {
+                    self.stats.lanes.push(LaneStats {
+                        name: lane.to_string(),
+                        ..LaneStats::default()
+                    });
+                    self.stats.lanes.len() - 1
+                }
+            };
+            let lane = &mut self.stats.lanes[index];
+            lane.dequeued_per_tick_max = lane.dequeued_per_tick_max.max(count);
+            if let Some(queued) = queued {
+                lane.depth_high_water = Some(lane.depth_high_water.unwrap_or(0).max(queued));
+            }
+            lane.dequeued += count as u64;
+        }
+        pub(crate) fn ticked(&mut self, dequeued: usize, coalesced: usize) {
+            self.stats.ticks += 1;
+            self.stats.actions_per_tick.record(dequeued as u64);
+            self.stats.coalesced += coalesced as u64;
+        }
+        pub(crate) fn reduced(&mut self, action: &Action, duration: Duration) {
+            let kind = (self.kind)(action);
+            self.stats
+                .reducers
+                .entry(kind)
+                .or_insert_with(|| Histogram::new(DURATION_BUCKETS))
+                .record(duration.as_nanos() as u64);
+            self.stats.applied += 1;
+        }
+    }
+
+    impl<Action, State, RootReducer> Store<Action, State, RootReducer>
+    where
+        Action: Debug + Clone,
+        State: Debug + Clone,
+        RootReducer: Reducer<State, Action> + Send + Sync + 'static,
+    {
+        // starts collecting stats, timing the reducer by the action kind kind
+        // names. kinds are static so recording never allocates once a kind was seen
+        pub fn with_stats<Kind>(mut self, kind: Kind) -> Self
+        where
+            Kind: Fn(&Action) -> &'static str + Send + Sync + 'static,
+        {
+            self.stats = Some(Stats {
+                kind: Box::new(kind),
+                rejected: AtomicU64::new(0),
+                stats: StoreStats::default(),
+            });
+            self
+        }
+        // a snapshot of the stats, None unless with_stats was called
+        pub fn stats(&self) -> Option<StoreStats> {
+            let stats = self.stats.as_ref()?;
+            let mut snapshot = stats.stats.clone();
+            snapshot.rejected = stats.rejected.load(Ordering::Relaxed);
+            for lane in self.lanes.iter() {
+                let counters = lane.actions.counters();
+                let index = match snapshot.lanes.iter().position(|l| l.name == lane.name) {
+                    Some(index) => index,
+                    None => {
+                        snapshot.lanes.push(LaneStats {
+                            name: lane.name.clone(),
+                            ..LaneStats::default()
+                        });
+                        snapshot.lanes.len() - 1
+                    }
+                };
+                let stats = &mut snapshot.lanes[index];
+                stats.rejected = counters.rejected;
+                stats.overwritten = counters.overwritten;
+                stats.merged = counters.merged;
+            }
+            Some(snapshot)
+        }
+        // starts over from zero. the queue counters of the lanes keep counting
+        pub fn reset_stats(&mut self) {
+            if let Some(stats) = self.stats.as_mut() {
+                stats.rejected.store(0, Ordering::Relaxed);
+                stats.stats = StoreStats::default();
+            }
+        }
+    }
+
+    impl<Action, State, RootReducer> Store<Action, State, RootReducer>
+    where
+        Action: Tagged + Debug + Clone,
+        State: Debug + Clone,
+        RootReducer: Reducer<State, Action> + Send + Sync + 'static,
+    {
+        // with_stats by the tags of #[derive(Action)]
+        pub fn with_action_stats(self) -> Self {
+            self.with_stats(|action: &Action| action.tag())
+        }
+    }
+
+    struct Shared {
+        text: Mutex<String>,
+        closed: AtomicBool,
+        // connections serve failed to accept
+        errors: AtomicU64,
+    }
+
+    // how long a scrape may take to send its request or read the answer. the
+    // exporter serves one connection at a time, a stalled client holds up the
+    // next scrape until it times out
+    pub const SCRAPE_TIMEOUT: Duration = Duration::from_secs(2);
+
+    trait Timeouts {
+        fn set_timeouts(&self, timeout: Duration) -> io::Result<()>;
+    }
+
+    impl Timeouts for TcpStream {
+        fn set_timeouts(&self, timeout: Duration) -> io::Result<()> {
+            self.set_read_timeout(Some(timeout))?;
+            self.set_write_timeout(Some(timeout))
+        }
+    }
+
+    #[cfg(unix)]
+    impl Timeouts for std::os::unix::net::UnixStream {
+        fn set_timeouts(&self, timeout: Duration) -> io::Result<()> {
+            self.set_read_timeout(Some(timeout))?;
+            self.set_write_timeout(Some(timeout))
+        }
+    }
+
+    /*
+     serves the latest published stats to prometheus over http, on a tcp port
+     or a unix socket. every connection gets the text published last and is
+     closed, whatever it asked for. dropping the exporter stops serving
+    */
+    pub struct PrometheusExporter {
+        shared: Arc<Shared>,
+        endpoint: Endpoint,
+        prefix: String,
+    }
+
+    impl PrometheusExporter {
+        // an exporter that only listens on loopback addresses
+        pub fn bind<P: Into<String>>(endpoint: Endpoint, prefix: P) -> io::Result<Self> {
+            PrometheusExporter::builder().bind(endpoint, prefix)
+        }
+        pub fn builder() -> PrometheusExporterBuilder {
+            PrometheusExporterBuilder {
+                allow_remote: false,
+            }
+        }
+        pub fn endpoint(&self) -> &Endpoint {
+            &self.endpoint
+        }
+        // replaces what scrapes get
+        pub fn publish(&self, stats: &StoreStats) {
+            let stats = StoreStats {
+                scrape_errors: self.shared.errors.load(Ordering::Relaxed),
+                ..stats.clone()
+            };
+            *self.shared.text.lock().unwrap() = stats.to_prometheus(&self.prefix);
+        }
+    }
+
+    /*
+     options for a PrometheusExporter. the stats tell anyone who connects what
+     the application is doing, so tcp endpoints are refused unless they are
+     loopback addresses. allow_remote(true) lifts that, e.g. for a collector on
+     another host
+    */
+    pub struct PrometheusExporterBuilder {
+        allow_remote: bool,
+    }
+
+    impl PrometheusExporterBuilder {
+        pub fn allow_remote(mut self, allow: bool) -> Self {
+            self.allow_remote = allow;
+            self
+        }
+        pub fn bind<P: Into<String>>(
+            self,
+            endpoint: Endpoint,
+            prefix: P,
+        ) -> io::Result<PrometheusExporter> {
+            check_loopback(&endpoint, self.allow_remote, "PrometheusExporterBuilder")?;
+            let (listener, endpoint) = listen(endpoint)?;
+            let shared = Arc::new(Shared {
+                text: Mutex::new(String::new()),
+                closed: AtomicBool::new(false),
+                errors: AtomicU64::new(0),
+            });
+            let accept_shared = shared.clone();
+            thread::spawn(move || match listener {
+                Listener::Tcp(listener) => serve(listener.incoming(), &accept_shared),
+                #[cfg(unix)]
+                Listener::Unix(listener) => serve(listener.incoming(), &accept_shared),
+            });
+            Ok(PrometheusExporter {
+                shared,
+                endpoint,
+                prefix: prefix.into(),
+            })
+        }
+    }
+
+    impl Drop for PrometheusExporter {
+        fn drop(&mut self) {
+            self.shared.closed.store(true, Ordering::SeqCst);
+            wake(&self.endpoint);
+        }
+    }
+
+    fn serve<C, I>(incoming: I, shared: &Shared)
+    where
+        C: Read + Write + Timeouts,
+        I: Iterator<Item = io::Result<C>>,
+    {
+        for connection in incoming {
+            if shared.closed.load(Ordering::SeqCst) {
+                break;
+            }
+            let mut connection = match connection.and_then(|connection| {
+                connection.set_timeouts(SCRAPE_TIMEOUT)?;
+                Ok(connection)
+            }) {
+                Ok(connection) => connection,
+                Err(_) => {
+                    shared.errors.fetch_add(1, Ordering::Relaxed);
+                    continue;
+                }
+            };
+            // skip the request head, everything is answered the same
+            let mut line = String::new();
+            let mut reader = BufReader::new(&mut connection);
+            while reader.read_line(&mut line).is_ok_and(|read| read > 0) {
+                if line.trim().is_empty() {
+                    break;
+                }
+                line.clear();
+            }
+            drop(reader);
+            let text = shared.text.lock().unwrap().clone();
+            let _ = write!(
+                connection,
+                "HTTP/1.0 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\n\r\n{}",
+                text.len(),
+                text
+            );
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::TcpStream,
+        time::Duration,
+    };
+
+    use super::stats::{Histogram, PrometheusExporter, DURATION_BUCKETS, SCRAPE_TIMEOUT};
+    use crate::{
+        devtools::devtools::Endpoint,
+        queue::queue::{Backpressure, QueueConfig},
+        simple::simple::{Middleware, Store},
+    };
+
+    #[test]
+    fn histogram_buckets_and_quantiles() {
+        let mut histogram = Histogram::new(DURATION_BUCKETS);
+        for value in [500, 2_000, 3_000, 2_000_000_000] {
+            histogram.record(value);
+        }
+        assert_eq!(histogram.count(), 4);
+        assert_eq!(histogram.max(), 2_000_000_000);
+        assert_eq!(histogram.quantile(0.5), 5_000);
+        assert_eq!(histogram.quantile(1.0), 2_000_000_000);
+        let cumulative = histogram.cumulative().collect::<Vec<_>>();
+        assert_eq!(cumulative[0], (Some(1_000), 1));
+        assert_eq!(cumulative[1], (Some(5_000), 3));
+        assert_eq!(*cumulative.last().unwrap(), (None, 4));
+    }
+
+    #[derive(Clone, Debug)]
+    enum Action {
+        Add(i32),
+        Secret,
+    }
+
+    struct NoSecrets;
+
+    impl Middleware<i32, Action> for NoSecrets {
+        fn on_dispatch(&self, action: Action) -> Option<Action> {
+            match action {
+                Action::Secret => None,
+                action => Some(action),
+            }
+        }
+    }
+
+    fn reducer(state: i32, action: Action) -> i32 {
+        match action {
+            Action::Add(n) => state + n,
+            Action::Secret => state,
+        }
+    }
+
+    #[test]
+    fn store_collects_stats() {
+        let rejecting = QueueConfig::Bounded {
+            capacity: 2,
+            backpressure: Backpressure::Reject,
+        };
+        let mut store = Store::with_queue(reducer, 0, rejecting)
+            .with_middleware(NoSecrets)
+            .with_stats(|action: &Action| match action {
+                Action::Add(_) => "add",
+                Action::Secret => "secret",
+            });
+        assert!(Store::new(reducer, 0).stats().is_none());
+
+        store.dispatch(Action::Secret).unwrap();
+        store.dispatch(Action::Add(1)).unwrap();
+        store.dispatch(Action::Add(2)).unwrap();
+        assert!(store.dispatch(Action::Add(3)).is_err());
+        store.tick();
+        store.update();
+        store.tick();
+        store.update();
+
+        let stats = store.stats().unwrap();
+        assert_eq!(store.state, 3);
+        assert_eq!(stats.ticks, 2);
+        assert_eq!(stats.applied, 2);
+        assert_eq!(stats.rejected, 1);
+        assert_eq!(stats.reducers["add"].count(), 2);
+        assert_eq!(stats.actions_per_tick.count(), 2);
+        assert_eq!(stats.lanes[0].dequeued_per_tick_max, 2);
+        assert_eq!(stats.lanes[0].depth_high_water, Some(2));
+        assert_eq!(stats.lanes[0].rejected, 1);
+
+        let text = stats.to_prometheus("mars");
+        assert!(text.contains("# TYPE mars_reducer_duration_seconds histogram"));
+        assert!(text.contains("mars_reducer_duration_seconds_count{kind=\"add\"} 2"));
+        assert!(text.contains("mars_actions_per_tick_bucket{le=\"2\"} 2"));
+        assert!(text.contains("mars_queue_rejected_total{lane=\"default\"} 1"));
+        assert!(text.contains("mars_queue_depth_high_water{lane=\"default\"} 2"));
+        assert!(text.contains("mars_queue_dequeued_per_tick_max{lane=\"default\"} 2"));
+
+        store.reset_stats();
+        assert_eq!(store.stats().unwrap().ticks, 0);
+    }
+
+    #[test]
+    fn exporter_serves_latest_stats() {
+        let mut store = Store::new(reducer, 0).with_stats(|_: &Action| "any");
+        store.dispatch(Action::Add(1)).unwrap();
+        store.tick();
+        store.update();
+
+        let exporter =
+            PrometheusExporter::bind(Endpoint::Tcp("127.0.0.1:0".parse().unwrap()), "app").unwrap();
+        exporter.publish(&store.stats().unwrap());
+        let addr = match exporter.endpoint() {
+            Endpoint::Tcp(addr) => *addr,
+            #[cfg(unix)]
+            Endpoint::Unix(_) => unreachable!(),
+        };
+        let mut scrape = TcpStream::connect(addr).unwrap();
+        scrape
+            .set_read_timeout(Some(Duration::from_secs(10)))
+            .unwrap();
+        write!(scrape, "GET /metrics HTTP/1.0\r\n\r\n").unwrap();
+        let mut response = String::new();
+        scrape.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.0 200 OK"));
+        assert!(response.contains("app_actions_applied_total 1"));
+
+        // a client that never sends its request only holds the exporter up
+        // until the scrape timeout
+        let stalled = TcpStream::connect(addr).unwrap();
+        let mut scrape = TcpStream::connect(addr).unwrap();
+        scrape.set_read_timeout(Some(SCRAPE_TIMEOUT * 5)).unwrap();
+        write!(scrape, "GET /metrics HTTP/1.0\r\n\r\n").unwrap();
+        let mut response = String::new();
+        scrape.read_to_string(&mut response).unwrap();
+        assert!(response.contains("app_actions_applied_total 1"));
+        drop(stalled);
+
+        let path = std::env::temp_dir().join(format!("mars-stats-{}.prom", std::process::id()));
+        store
+            .stats()
+            .unwrap()
+            .write_prometheus(&path, "app")
+            .unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(text.contains("app_ticks_total 1"));
+    }
+
+    #[test]
+    fn remote_exporters_need_allow_remote() {
+        let refused = PrometheusExporter::bind(Endpoint::Tcp("0.0.0.0:0".parse().unwrap()), "app");
+        assert_eq!(
+            refused.err().unwrap().kind(),
+            std::io::ErrorKind::PermissionDenied
+        );
+        assert!(PrometheusExporter::builder()
+            .allow_remote(true)
+            .bind(Endpoint::Tcp("0.0.0.0:0".parse().unwrap()), "app")
+            .is_ok());
+    }
+}
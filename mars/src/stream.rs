@@ -0,0 +1,286 @@
+// allow dead_code when checking the lib without tests
+// cargo-analyzer runs `cargo check` for lib the lib with and without tests.
+// dead_code warnings in the ide won't show if the code is used in tests.
+#![cfg_attr(not(test), allow(dead_code))]
+pub mod stream {
+    use std::{
+        fmt::Debug,
+        future::Future,
+        pin::Pin,
+        sync::Arc,
+        task::{Context, Poll},
+    };
+
+    pub use futures_core::Stream;
+
+    use crate::{
+        ack::ack::{Ack, AckDispatcher, Acked, Progress},
+        queue::queue::DispatchError,
+        simple::simple::{Reducer, Store},
+    };
+
+    /*
+     the async side of acks and watchers. nothing here needs a runtime: the
+     futures and streams register the polling task's waker with the store, and
+     the next update() that settles wakes it. someone still has to call tick()
+     and update(), on the store's own thread as usual
+    */
+
+    // an ack resolves as a future as well as by waiting on it
+    impl<State: Clone> Future for Ack<State> {
+        type Output = Acked<State>;
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Acked<State>> {
+            match &self.stamp {
+                Some(stamp) => self.progress.poll(cx, |inner| inner.resolved.remove(stamp)),
+                None => Poll::Ready(Acked::Dropped),
+            }
+        }
+    }
+
+    // completes when update() processed the action, or right away with the
+    // error if it could not be sent
+    pub struct Dispatched<Action, State: Clone> {
+        sent: Result<Ack<State>, Option<DispatchError<Action>>>,
+    }
+
+    // the fields are never pinned
+    impl<Action, State: Clone> Unpin for Dispatched<Action, State> {}
+
+    impl<Action, State: Clone> Future for Dispatched<Action, State> {
+        type Output = Result<Acked<State>, DispatchError<Action>>;
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+            match &mut self.get_mut().sent {
+                Ok(ack) => Pin::new(ack).poll(cx).map(Ok),
+                Err(error) => Poll::Ready(Err(error.take().expect("polled after completion"))),
+            }
+        }
+    }
+
+    impl<Action, State: Clone> AckDispatcher<Action, State> {
+        // send_with_ack for async tasks on other threads
+        pub fn send_async(&self, action: Action) -> Dispatched<Action, State> {
+            Dispatched {
+                sent: self
+                    .progress
+                    .send_with_ack(&self.dispatcher, action, false)
+                    .map_err(Some),
+            }
+        }
+    }
+
+    // the states the store commits. a stream that falls behind skips to the
+    // latest state, it never yields a version twice or an older one. it ends
+    // when the store is dropped
+    pub struct StateStream<State> {
+        progress: Arc<Progress<State>>,
+        seen: u64,
+    }
+
+    impl<State> Drop for StateStream<State> {
+        fn drop(&mut self) {
+            self.progress.unwatch();
+        }
+    }
+
+    impl<State: Clone> Stream for StateStream<State> {
+        type Item = (u64, State);
+        fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<(u64, State)>> {
+            let this = self.get_mut();
+            let seen = this.seen;
+            let next = this.progress.poll(cx, |inner| match &inner.state {
+                Some(state) if inner.version > seen => Some(Some((inner.version, state.clone()))),
+                _ if inner.closed => Some(None),
+                _ => None,
+            });
+            next.map(|next| {
+                if let Some((version, _)) = &next {
+                    this.seen = *version;
+                }
+                next
+            })
+        }
+    }
+
+    // like StateStream without cloning states
+    pub struct VersionStream<State> {
+        progress: Arc<Progress<State>>,
+        seen: u64,
+    }
+
+    impl<State: Clone> Stream for VersionStream<State> {
+        type Item = u64;
+        fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<u64>> {
+            let this = self.get_mut();
+            let seen = this.seen;
+            let next = this.progress.poll(cx, |inner| match inner.version > seen {
+                true => Some(Some(inner.version)),
+                false => inner.closed.then_some(None),
+            });
+            next.map(|next| {
+                if let Some(version) = next {
+                    this.seen = version;
+                }
+                next
+            })
+        }
+    }
+
+    impl<Action, State, RootReducer> Store<Action, State, RootReducer>
+    where
+        Action: Debug + Clone,
+        State: Debug + Clone,
+        RootReducer: Reducer<State, Action> + Send + Sync + 'static,
+    {
+        // dispatch, returning a future that completes once update() processed
+        // the action
+        pub fn dispatch_async(&mut self, action: Action) -> Dispatched<Action, State>
+        where
+            Action: Send + Sync,
+        {
+            Dispatched {
+                sent: self.dispatch_acked(action, false).map_err(Some),
+            }
+        }
+        // the states committed from now on, starting after the current one
+        pub fn state_stream(&mut self) -> StateStream<State> {
+            let seen = self.version;
+            let progress = self.progress().clone();
+            progress.watch();
+            StateStream { progress, seen }
+        }
+        // the versions committed from now on
+        pub fn version_stream(&mut self) -> VersionStream<State> {
+            VersionStream {
+                seen: self.version,
+                progress: self.progress().clone(),
+            }
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::{
+        future::{poll_fn, Future},
+        pin::{pin, Pin},
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        task::{Context, Poll, Wake, Waker},
+        thread::{self, Thread},
+    };
+
+    use super::stream::Stream;
+    use crate::{ack::ack::Acked, simple::simple::Store};
+
+    // the least an executor has to do: poll, park until woken, poll again
+    struct Unpark(Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        let waker = Waker::from(Arc::new(Unpark(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+            thread::park();
+        }
+    }
+
+    async fn next<S: Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+        poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)).await
+    }
+
+    type R = fn(i32, i32) -> i32;
+    const REDUCER: R = |state, n| state + n;
+
+    #[test]
+    fn dispatch_async_completes_on_update() {
+        let mut store = Store::<i32, i32, R>::new(REDUCER, 0);
+        let ready = store.dispatch_async(2);
+        let dispatcher = store.ack_dispatcher();
+        let running = Arc::new(AtomicBool::new(true));
+        let store_running = running.clone();
+        let store_thread = thread::spawn(move || {
+            while store_running.load(Ordering::Relaxed) {
+                store.tick();
+                store.update();
+                thread::yield_now();
+            }
+            store.state
+        });
+
+        assert_eq!(
+            block_on(ready),
+            Ok(Acked::Applied {
+                version: 1,
+                state: None
+            })
+        );
+        let acked = block_on(async {
+            let first = dispatcher.send_async(3).await.unwrap();
+            let second = dispatcher.send_async(4).await.unwrap();
+            (first, second)
+        });
+        assert!(matches!(
+            acked,
+            (Acked::Applied { .. }, Acked::Applied { .. })
+        ));
+        running.store(false, Ordering::Relaxed);
+        assert_eq!(store_thread.join().unwrap(), 9);
+    }
+
+    #[test]
+    fn streams_follow_commits() {
+        let mut store = Store::<i32, i32, R>::new(REDUCER, 0);
+        let mut states = store.state_stream();
+        let mut versions = store.version_stream();
+        {
+            let mut pending = pin!(next(&mut states));
+            let waker = Waker::from(Arc::new(Unpark(thread::current())));
+            assert!(pending
+                .as_mut()
+                .poll(&mut Context::from_waker(&waker))
+                .is_pending());
+
+            store.dispatch(5).unwrap();
+            store.tick();
+            store.update();
+            assert_eq!(block_on(pending), Some((1, 5)));
+        }
+
+        // a slow consumer skips to the latest state
+        for n in [1, 2] {
+            store.dispatch(n).unwrap();
+            store.tick();
+            store.update();
+        }
+        assert_eq!(block_on(next(&mut states)), Some((3, 8)));
+        assert_eq!(block_on(next(&mut versions)), Some(3));
+    }
+
+    #[test]
+    fn dropping_the_store_ends_streams_and_acks() {
+        let mut store = Store::<i32, i32, R>::new(REDUCER, 0);
+        let mut states = store.state_stream();
+        let mut versions = store.version_stream();
+        let ack = store.dispatch_async(1);
+        let waiting = thread::spawn(move || {
+            let acked = block_on(ack);
+            (
+                acked,
+                block_on(next(&mut states)),
+                block_on(next(&mut versions)),
+            )
+        });
+        drop(store);
+        assert_eq!(waiting.join().unwrap(), (Ok(Acked::Dropped), None, None));
+    }
+}
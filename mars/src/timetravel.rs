@@ -0,0 +1,338 @@
+// allow dead_code when checking the lib without tests
+// cargo-analyzer runs `cargo check` for lib the lib with and without tests.
+// dead_code warnings in the ide won't show if the code is used in tests.
+#![cfg_attr(not(test), allow(dead_code))]
+pub mod timetravel {
+    use std::fmt::Debug;
+
+    use crate::simple::simple::{Reducer, Store};
+
+    #[derive(Clone, Debug)]
+    pub struct Entry<Action, State> {
+        // None for a state reconcile put in place of the store's state. such
+        // an entry can't be skipped, the entries after it replay on its state
+        pub action: Option<Action>,
+        // the state after the action, or the state before it if it is skipped
+        pub state: State,
+        pub skipped: bool,
+    }
+
+    /*
+     the sequence of (action, resulting state) pairs a store produced. the cursor
+     counts the entries the current state includes, 0 is the initial state. like
+     an undo history, recording while the cursor is in the past drops the entries
+     after it, so dispatching resumes from the chosen point. every entry holds a
+     clone of the state, so without a capacity the timeline grows for as long as
+     the store records. with one, the oldest entry is folded into the initial
+     state once there are more entries than that
+    */
+    pub struct Timeline<Action, State> {
+        initial: State,
+        entries: Vec<Entry<Action, State>>,
+        cursor: usize,
+        capacity: Option<usize>,
+    }
+
+    impl<Action, State> Timeline<Action, State>
+    where
+        Action: Clone,
+        State: Clone,
+    {
+        pub fn new(initial: State) -> Self {
+            Timeline {
+                initial,
+                entries: Vec::new(),
+                cursor: 0,
+                capacity: None,
+            }
+        }
+        // a timeline that keeps the last capacity entries
+        pub fn with_capacity(initial: State, capacity: usize) -> Self {
+            Timeline {
+                capacity: Some(capacity),
+                ..Timeline::new(initial)
+            }
+        }
+        pub fn record(&mut self, action: Action, state: State) {
+            self.push(Some(action), state);
+        }
+        // records a state that replaced the store's state without an action
+        pub fn record_state(&mut self, state: State) {
+            self.push(None, state);
+        }
+        fn push(&mut self, action: Option<Action>, state: State) {
+            self.entries.truncate(self.cursor);
+            self.entries.push(Entry {
+                action,
+                state,
+                skipped: false,
+            });
+            self.cursor = self.entries.len();
+            if let Some(capacity) = self.capacity {
+                while self.entries.len() > capacity {
+                    // the entry can't be skipped or jumped over anymore
+                    self.initial = self.entries.remove(0).state;
+                    self.cursor -= 1;
+                }
+            }
+        }
+        pub fn entries(&self) -> &[Entry<Action, State>] {
+            &self.entries
+        }
+        pub fn len(&self) -> usize {
+            self.entries.len()
+        }
+        pub fn is_empty(&self) -> bool {
+            self.entries.is_empty()
+        }
+        pub fn cursor(&self) -> usize {
+            self.cursor
+        }
+        // the state after the first `cursor` entries
+        pub fn state(&self) -> &State {
+            self.state_at(self.cursor)
+        }
+        fn state_at(&self, cursor: usize) -> &State {
+            match cursor {
+                0 => &self.initial,
+                cursor => &self.entries[cursor - 1].state,
+            }
+        }
+        pub fn step_back(&mut self) -> &State {
+            self.cursor = self.cursor.saturating_sub(1);
+            self.state()
+        }
+        pub fn step_forward(&mut self) -> &State {
+            self.cursor = (self.cursor + 1).min(self.entries.len());
+            self.state()
+        }
+        // moves the cursor to index, indexes past the last entry go to the last entry
+        pub fn jump(&mut self, index: usize) -> &State {
+            self.cursor = index.min(self.entries.len());
+            self.state()
+        }
+        // skips the entry at index, or applies it again if it was skipped, and
+        // recomputes the states of every entry from there on
+        pub fn toggle<R>(&mut self, index: usize, reducer: &R) -> &State
+        where
+            R: Reducer<State, Action>,
+        {
+            if let Some(entry) = self.entries.get_mut(index) {
+                if entry.action.is_none() {
+                    return self.state();
+                }
+                entry.skipped = !entry.skipped;
+                let mut state = self.state_at(index).clone();
+                for entry in self.entries[index..].iter_mut() {
+                    match (&entry.action, entry.skipped) {
+                        (None, _) => state = entry.state.clone(),
+                        (Some(action), false) => reducer.replay_in_place(&mut state, action),
+                        (Some(_), true) => {}
+                    }
+                    entry.state = state.clone();
+                }
+            }
+            self.state()
+        }
+    }
+
+    impl<Action, State, RootReducer> Store<Action, State, RootReducer>
+    where
+        Action: Debug + Clone,
+        State: Debug + Clone,
+        RootReducer: Reducer<State, Action> + Send + Sync + 'static,
+    {
+        // records every action update() applies from now on, with the state it
+        // produced. the timeline is never trimmed, use with_time_travel_capacity
+        // for stores that run longer than a debugging session
+        pub fn with_time_travel(mut self) -> Self {
+            self.timeline = Some(Timeline::new(self.state.clone()));
+            self
+        }
+        // like with_time_travel, keeping the last capacity actions
+        pub fn with_time_travel_capacity(mut self, capacity: usize) -> Self {
+            self.timeline = Some(Timeline::with_capacity(self.state.clone(), capacity));
+            self
+        }
+        pub fn timeline(&self) -> Option<&Timeline<Action, State>> {
+            self.timeline.as_ref()
+        }
+        pub fn step_back(&mut self) {
+            self.travel(|timeline, _| {
+                timeline.step_back();
+            });
+        }
+        pub fn step_forward(&mut self) {
+            self.travel(|timeline, _| {
+                timeline.step_forward();
+            });
+        }
+        pub fn jump_to(&mut self, index: usize) {
+            self.travel(|timeline, _| {
+                timeline.jump(index);
+            });
+        }
+        pub fn toggle_action(&mut self, index: usize) {
+            self.travel(|timeline, reducer| {
+                timeline.toggle(index, reducer);
+            });
+        }
+        // moves the timeline, then makes its state the store's state. readers and
+        // subscribers see it like a state committed by update()
+        fn travel<F>(&mut self, f: F)
+        where
+            F: FnOnce(&mut Timeline<Action, State>, &RootReducer),
+        {
+            if let Some(timeline) = self.timeline.as_mut() {
+                f(timeline, &self.root_reducer);
+                self.state = timeline.state().clone();
+                self.commit();
+            }
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::{optimistic::optimistic::Authoritative, simple::simple::Store};
+
+    #[derive(Clone, Debug)]
+    enum Action {
+        Add(i32),
+        Double,
+    }
+
+    type R = fn(i32, Action) -> i32;
+    const REDUCER: R = |state: i32, action: Action| match action {
+        Action::Add(n) => state + n,
+        Action::Double => state * 2,
+    };
+
+    fn apply(store: &mut Store<Action, i32, R>, actions: Vec<Action>) {
+        for action in actions {
+            store.dispatch(action).unwrap();
+        }
+        store.tick();
+        store.update();
+    }
+
+    #[test]
+    fn steps_and_jumps_through_history() {
+        let mut store = Store::<Action, i32, R>::new(REDUCER, 1).with_time_travel();
+        apply(
+            &mut store,
+            vec![Action::Add(2), Action::Double, Action::Add(1)],
+        );
+        assert_eq!(store.state, 7);
+        store.step_back();
+        assert_eq!(store.state, 6);
+        store.step_back();
+        store.step_back();
+        store.step_back();
+        assert_eq!(store.state, 1);
+        store.step_forward();
+        assert_eq!(store.state, 3);
+        store.jump_to(3);
+        assert_eq!(store.state, 7);
+        store.jump_to(10);
+        assert_eq!(store.timeline().unwrap().cursor(), 3);
+    }
+
+    #[test]
+    fn capacity_folds_oldest_entries_into_initial_state() {
+        let mut store = Store::<Action, i32, R>::new(REDUCER, 1).with_time_travel_capacity(2);
+        apply(
+            &mut store,
+            vec![Action::Add(2), Action::Double, Action::Add(1)],
+        );
+        let timeline = store.timeline().unwrap();
+        assert_eq!(timeline.len(), 2);
+        assert_eq!(timeline.cursor(), 2);
+        store.jump_to(0);
+        assert_eq!(store.state, 3);
+        // without Double: 3 + 1
+        store.jump_to(2);
+        store.toggle_action(0);
+        assert_eq!(store.state, 4);
+    }
+
+    #[test]
+    fn skipping_an_action_recomputes_later_states() {
+        let mut store = Store::<Action, i32, R>::new(REDUCER, 1).with_time_travel();
+        apply(
+            &mut store,
+            vec![Action::Add(2), Action::Double, Action::Add(1)],
+        );
+        let states = Arc::new(Mutex::new(Vec::new()));
+        let states_clone = states.clone();
+        store.subscribe(
+            |state: &i32| *state,
+            move |state: &i32| states_clone.lock().unwrap().push(*state),
+        );
+        // without Add(2): 1 * 2 + 1
+        store.toggle_action(0);
+        assert_eq!(store.state, 3);
+        let timeline = store.timeline().unwrap();
+        assert!(timeline.entries()[0].skipped);
+        assert_eq!(
+            timeline
+                .entries()
+                .iter()
+                .map(|e| e.state)
+                .collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+        store.toggle_action(0);
+        assert_eq!(store.state, 7);
+        assert_eq!(*states.lock().unwrap(), vec![3, 7]);
+    }
+
+    #[test]
+    fn dispatch_resumes_from_chosen_point() {
+        let mut store = Store::<Action, i32, R>::new(REDUCER, 1).with_time_travel();
+        apply(
+            &mut store,
+            vec![Action::Add(2), Action::Double, Action::Add(1)],
+        );
+        store.jump_to(1);
+        apply(&mut store, vec![Action::Add(10)]);
+        assert_eq!(store.state, 13);
+        let timeline = store.timeline().unwrap();
+        assert_eq!(timeline.len(), 2);
+        assert_eq!(timeline.cursor(), 2);
+        store.step_back();
+        assert_eq!(store.state, 3);
+    }
+
+    #[test]
+    fn reconciled_states_are_kept_when_skipping_earlier_actions() {
+        let mut store = Store::<Action, i32, R>::new(REDUCER, 1)
+            .with_optimistic(|_| {}, |_| {})
+            .with_time_travel();
+        apply(&mut store, vec![Action::Add(2), Action::Double]);
+        // the authority confirmed Add(2) on a state of its own, Double is pending
+        store.reconcile(Authoritative {
+            version: 1,
+            state: 10,
+            confirmed: vec![1],
+            rejected: vec![],
+        });
+        apply(&mut store, vec![Action::Add(1)]);
+        assert_eq!(store.state, 21);
+        let timeline = store.timeline().unwrap();
+        assert_eq!(timeline.len(), 4);
+        assert!(timeline.entries()[2].action.is_none());
+        // later entries replay on the reconciled state, not on Double
+        store.toggle_action(1);
+        assert_eq!(store.state, 21);
+        // and the reconciled entry itself can't be skipped
+        store.toggle_action(2);
+        assert!(!store.timeline().unwrap().entries()[2].skipped);
+        assert_eq!(store.state, 21);
+        store.jump_to(2);
+        assert_eq!(store.state, 3);
+        store.jump_to(3);
+        assert_eq!(store.state, 20);
+    }
+}
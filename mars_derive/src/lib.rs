@@ -0,0 +1,512 @@
+// derive macros for mars, re-exported next to the traits they implement
+use std::collections::HashSet;
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, punctuated::Punctuated, Data, DeriveInput, Error, Fields, Ident, Index, Lit,
+    LitStr, Member, Token, Variant,
+};
+
+/*
+ #[derive(Diff)] on a struct implements mars::diff::diff::Diff field by field.
+ it also generates <Name>Patch, with the struct's visibility and one
+ Option<field patch> per field, None for fields that did not change. json
+ patch paths use the rust field names, or the index for tuple structs
+*/
+#[proc_macro_derive(Diff)]
+pub fn derive_diff(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    diff(input).unwrap_or_else(Error::into_compile_error).into()
+}
+
+fn diff(input: DeriveInput) -> Result<TokenStream2, Error> {
+    let name = &input.ident;
+    let vis = &input.vis;
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "Diff can not be derived for generic structs",
+        ));
+    }
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => {
+            return Err(Error::new(
+                Span::call_site(),
+                "Diff can only be derived for structs",
+            ))
+        }
+    };
+    let patch = format_ident!("{}Patch", name);
+    let diff_trait = quote!(::mars::diff::diff::Diff);
+    let types = fields.iter().map(|field| &field.ty).collect::<Vec<_>>();
+    // how a field is accessed on the struct and on the patch, and its json path segment
+    let (members, segments): (Vec<TokenStream2>, Vec<String>) = match fields {
+        Fields::Named(_) => fields
+            .iter()
+            .map(|field| {
+                let ident = field.ident.as_ref().unwrap();
+                (quote!(#ident), ident.to_string())
+            })
+            .unzip(),
+        _ => (0..fields.len())
+            .map(|i| {
+                let index = Index::from(i);
+                (quote!(#index), i.to_string())
+            })
+            .unzip(),
+    };
+    let patch_struct = match fields {
+        Fields::Named(_) => {
+            let idents = fields.iter().map(|field| &field.ident);
+            quote! {
+                #vis struct #patch {
+                    #(pub #idents: ::std::option::Option<<#types as #diff_trait>::Patch>,)*
+                }
+            }
+        }
+        Fields::Unnamed(_) => quote! {
+            #vis struct #patch(
+                #(pub ::std::option::Option<<#types as #diff_trait>::Patch>,)*
+            );
+        },
+        Fields::Unit => quote!(#vis struct #patch;),
+    };
+    // the locals holding each field's patch while diffing
+    let locals = (0..fields.len())
+        .map(|i| format_ident!("field_{}", i))
+        .collect::<Vec<_>>();
+    let construct = match fields {
+        Fields::Named(_) => {
+            let idents = fields.iter().map(|field| &field.ident);
+            quote!(#patch { #(#idents: #locals,)* })
+        }
+        Fields::Unnamed(_) => quote!(#patch(#(#locals,)*)),
+        Fields::Unit => quote!(#patch),
+    };
+    Ok(quote! {
+        #[derive(Clone, Debug, PartialEq)]
+        #patch_struct
+
+        // unit structs and fields without patches leave the arguments unused
+        #[allow(unused_variables)]
+        impl #diff_trait for #name {
+            type Patch = #patch;
+            fn diff(&self, other: &Self) -> ::std::option::Option<#patch> {
+                #(let #locals = #diff_trait::diff(&self.#members, &other.#members);)*
+                let unchanged = true #(&& #locals.is_none())*;
+                match unchanged {
+                    true => ::std::option::Option::None,
+                    false => ::std::option::Option::Some(#construct),
+                }
+            }
+            fn apply(&mut self, patch: #patch) {
+                #(
+                    if let ::std::option::Option::Some(field) = patch.#members {
+                        #diff_trait::apply(&mut self.#members, field);
+                    }
+                )*
+            }
+            fn json_patch(
+                patch: &#patch,
+                path: &str,
+                ops: &mut ::std::vec::Vec<::mars::diff::diff::Value>,
+            ) {
+                #(
+                    if let ::std::option::Option::Some(field) = &patch.#members {
+                        <#types as #diff_trait>::json_patch(
+                            field,
+                            &::std::format!("{}/{}", path, #segments),
+                            ops,
+                        );
+                    }
+                )*
+            }
+        }
+    })
+}
+
+/*
+ #[derive(Action)] on an enum implements mars::action::action::Action. every
+ variant gets a tag, its name unless #[action(tag = "...")] pins it, and an
+ action creator named after it in snake case. #[action(lane = "...")] and
+ #[action(coalesce = "last" | "first")] on a variant are metadata the store
+ reads in dispatch_routed and Coalescer::action_meta. #[action(key = 0)] or
+ #[action(key = "track")] next to coalesce names a field that tells actions of
+ the variant apart, only actions with equal keys are coalesced. key can be
+ given more than once, the fields need Hash and Eq. #[action(serde)] on the
+ enum implements Serialize and Deserialize as {"type": tag, "payload": ...},
+ so renaming a variant whose tag is pinned keeps old logs readable, and
+ #[action(alias = "...")] accepts tags the variant had before
+*/
+#[proc_macro_derive(Action, attributes(action))]
+pub fn derive_action(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    action(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+struct VariantMeta {
+    tag: String,
+    aliases: Vec<String>,
+    lane: Option<String>,
+    coalesce: Option<TokenStream2>,
+    // the fields named by key
+    keys: Vec<Member>,
+}
+
+fn variant_meta(variant: &Variant) -> Result<VariantMeta, Error> {
+    let mut meta = VariantMeta {
+        tag: variant.ident.to_string(),
+        aliases: Vec::new(),
+        lane: None,
+        coalesce: None,
+        keys: Vec::new(),
+    };
+    for attr in variant.attrs.iter().filter(|a| a.path().is_ident("action")) {
+        attr.parse_nested_meta(|nested| {
+            let value = || nested.value()?.parse::<LitStr>();
+            if nested.path.is_ident("tag") {
+                meta.tag = value()?.value();
+            } else if nested.path.is_ident("alias") {
+                meta.aliases.push(value()?.value());
+            } else if nested.path.is_ident("lane") {
+                meta.lane = Some(value()?.value());
+            } else if nested.path.is_ident("coalesce") {
+                let policy = value()?;
+                meta.coalesce = Some(match policy.value().as_str() {
+                    "last" => quote!(::mars::action::action::Coalesce::Last),
+                    "first" => quote!(::mars::action::action::Coalesce::First),
+                    _ => {
+                        return Err(Error::new_spanned(
+                            policy,
+                            "coalesce is \"last\" or \"first\"",
+                        ))
+                    }
+                });
+            } else if nested.path.is_ident("key") {
+                meta.keys
+                    .push(key_field(variant, nested.value()?.parse::<Lit>()?)?);
+            } else {
+                return Err(nested.error("expected tag, alias, lane, coalesce or key"));
+            }
+            Ok(())
+        })?;
+    }
+    if meta.coalesce.is_none() && !meta.keys.is_empty() {
+        return Err(Error::new_spanned(
+            &variant.ident,
+            "key only makes sense next to coalesce",
+        ));
+    }
+    Ok(meta)
+}
+
+// the field of variant a key attribute names, by index or by name
+fn key_field(variant: &Variant, key: Lit) -> Result<Member, Error> {
+    match (&variant.fields, &key) {
+        (Fields::Unnamed(fields), Lit::Int(index)) => {
+            let index = index.base10_parse::<usize>()?;
+            match index < fields.unnamed.len() {
+                true => Ok(Member::Unnamed(Index::from(index))),
+                false => Err(Error::new_spanned(key, "no field with this index")),
+            }
+        }
+        (Fields::Named(fields), Lit::Str(name)) => fields
+            .named
+            .iter()
+            .filter_map(|field| field.ident.clone())
+            .find(|ident| *ident == name.value())
+            .map(Member::Named)
+            .ok_or_else(|| Error::new_spanned(&key, "no field with this name")),
+        _ => Err(Error::new_spanned(
+            key,
+            "key is a field index for tuple variants and a field name for struct variants",
+        )),
+    }
+}
+
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    let mut previous_lower = false;
+    for c in name.chars() {
+        if c.is_uppercase() && previous_lower {
+            snake.push('_');
+        }
+        previous_lower = c.is_lowercase() || c.is_ascii_digit();
+        snake.extend(c.to_lowercase());
+    }
+    snake
+}
+
+fn action(input: DeriveInput) -> Result<TokenStream2, Error> {
+    let name = &input.ident;
+    let vis = &input.vis;
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "Action can not be derived for generic enums",
+        ));
+    }
+    let variants = match &input.data {
+        Data::Enum(data) => &data.variants,
+        _ => {
+            return Err(Error::new(
+                Span::call_site(),
+                "Action can only be derived for enums",
+            ))
+        }
+    };
+    let mut serde = false;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("action")) {
+        attr.parse_nested_meta(|nested| {
+            if !nested.path.is_ident("serde") {
+                return Err(nested.error("expected serde"));
+            }
+            serde = true;
+            Ok(())
+        })?;
+    }
+    let metas = variants
+        .iter()
+        .map(variant_meta)
+        .collect::<Result<Vec<_>, _>>()?;
+    // tags name variants in logs and serialized actions, they have to be unique
+    let mut seen = HashSet::new();
+    for (variant, meta) in variants.iter().zip(&metas) {
+        for tag in std::iter::once(&meta.tag).chain(&meta.aliases) {
+            if !seen.insert(tag.clone()) {
+                return Err(Error::new_spanned(
+                    variant,
+                    format!("the tag {:?} is used twice", tag),
+                ));
+            }
+        }
+    }
+
+    let action_trait = quote!(::mars::action::action::Action);
+    let meta_struct = quote!(::mars::action::action::ActionMeta);
+    let entries = metas.iter().map(|meta| {
+        let tag = &meta.tag;
+        let lane = match &meta.lane {
+            Some(lane) => quote!(::std::option::Option::Some(#lane)),
+            None => quote!(::std::option::Option::None),
+        };
+        let coalesce = match &meta.coalesce {
+            Some(policy) => quote!(::std::option::Option::Some(#policy)),
+            None => quote!(::std::option::Option::None),
+        };
+        quote!(#meta_struct { tag: #tag, lane: #lane, coalesce: #coalesce })
+    });
+    let indexes = (0..variants.len()).collect::<Vec<_>>();
+    let patterns = variants
+        .iter()
+        .map(|variant| {
+            let ident = &variant.ident;
+            match &variant.fields {
+                Fields::Named(_) => quote!(#name::#ident { .. }),
+                Fields::Unnamed(_) => quote!(#name::#ident(..)),
+                Fields::Unit => quote!(#name::#ident),
+            }
+        })
+        .collect::<Vec<_>>();
+    // actions of a variant with keys are only coalesced if their key fields are equal
+    let mut same_key = Vec::new();
+    let mut hash_key = Vec::new();
+    for (variant, meta) in variants.iter().zip(&metas) {
+        if meta.keys.is_empty() {
+            continue;
+        }
+        let ident = &variant.ident;
+        let members = &meta.keys;
+        let ours = (0..members.len())
+            .map(|i| format_ident!("ours_{}", i))
+            .collect::<Vec<_>>();
+        let theirs = (0..members.len())
+            .map(|i| format_ident!("theirs_{}", i))
+            .collect::<Vec<_>>();
+        same_key.push(quote! {
+            (
+                #name::#ident { #(#members: #ours,)* .. },
+                #name::#ident { #(#members: #theirs,)* .. },
+            ) => #(#ours == #theirs)&&*
+        });
+        hash_key.push(quote! {
+            #name::#ident { #(#members: #ours,)* .. } => {
+                #(::std::hash::Hash::hash(#ours, &mut state);)*
+            }
+        });
+    }
+    let creators = variants.iter().map(|variant| {
+        let ident = &variant.ident;
+        let snake = snake_case(&ident.to_string());
+        let creator = match syn::parse_str::<Ident>(&snake) {
+            Ok(_) => format_ident!("{}", snake),
+            Err(_) => format_ident!("r#{}", snake),
+        };
+        let doc = format!("creates {}::{}", name, ident);
+        match &variant.fields {
+            Fields::Named(fields) => {
+                let idents = fields.named.iter().map(|f| &f.ident).collect::<Vec<_>>();
+                let types = fields.named.iter().map(|f| &f.ty);
+                quote! {
+                    #[doc = #doc]
+                    #vis fn #creator(#(#idents: #types),*) -> Self {
+                        #name::#ident { #(#idents),* }
+                    }
+                }
+            }
+            Fields::Unnamed(fields) => {
+                let args = (0..fields.unnamed.len())
+                    .map(|i| format_ident!("field_{}", i))
+                    .collect::<Vec<_>>();
+                let types = fields.unnamed.iter().map(|f| &f.ty);
+                quote! {
+                    #[doc = #doc]
+                    #vis fn #creator(#(#args: #types),*) -> Self {
+                        #name::#ident(#(#args),*)
+                    }
+                }
+            }
+            Fields::Unit => quote! {
+                #[doc = #doc]
+                #vis fn #creator() -> Self {
+                    #name::#ident
+                }
+            },
+        }
+    });
+    let serde_impls = match serde {
+        true => serde_impls(name, variants, &metas),
+        false => quote!(),
+    };
+    Ok(quote! {
+        impl #name {
+            #(#creators)*
+        }
+
+        impl #action_trait for #name {
+            fn meta(&self) -> &'static #meta_struct {
+                let index = match self {
+                    #(#patterns => #indexes,)*
+                };
+                &<Self as #action_trait>::all()[index]
+            }
+            fn all() -> &'static [#meta_struct] {
+                const ALL: &[#meta_struct] = &[#(#entries),*];
+                ALL
+            }
+            fn same_key(&self, other: &Self) -> bool {
+                match (self, other) {
+                    #(#same_key,)*
+                    _ => #action_trait::tag(self) == #action_trait::tag(other),
+                }
+            }
+            #[allow(unused_mut)]
+            fn hash_key(&self, mut state: &mut dyn ::std::hash::Hasher) {
+                match self {
+                    #(#hash_key)*
+                    _ => {}
+                }
+            }
+        }
+
+        #serde_impls
+    })
+}
+
+// serializes through an adjacently tagged copy of the enum that borrows the
+// fields and deserializes through one that owns them, both named by tag
+fn serde_impls(
+    name: &Ident,
+    variants: &Punctuated<Variant, Token![,]>,
+    metas: &[VariantMeta],
+) -> TokenStream2 {
+    let serde = quote!(::mars::action::action::serde);
+    let serde_path = "::mars::action::action::serde";
+    let mut borrowed = Vec::new();
+    let mut owned = Vec::new();
+    let mut to_borrowed = Vec::new();
+    let mut from_owned = Vec::new();
+    for (variant, meta) in variants.iter().zip(metas) {
+        let ident = &variant.ident;
+        let tag = &meta.tag;
+        let aliases = &meta.aliases;
+        let attrs = quote!(#[serde(rename = #tag #(, alias = #aliases)*)]);
+        match &variant.fields {
+            Fields::Named(fields) => {
+                let idents = fields.named.iter().map(|f| &f.ident).collect::<Vec<_>>();
+                let types = fields.named.iter().map(|f| &f.ty).collect::<Vec<_>>();
+                borrowed.push(quote!(#attrs #ident { #(#idents: &'a #types),* }));
+                owned.push(quote!(#attrs #ident { #(#idents: #types),* }));
+                to_borrowed.push(
+                    quote!(#name::#ident { #(#idents),* } => Borrowed::#ident { #(#idents),* }),
+                );
+                from_owned
+                    .push(quote!(Owned::#ident { #(#idents),* } => #name::#ident { #(#idents),* }));
+            }
+            Fields::Unnamed(fields) => {
+                let args = (0..fields.unnamed.len())
+                    .map(|i| format_ident!("field_{}", i))
+                    .collect::<Vec<_>>();
+                let types = fields.unnamed.iter().map(|f| &f.ty).collect::<Vec<_>>();
+                borrowed.push(quote!(#attrs #ident(#(&'a #types),*)));
+                owned.push(quote!(#attrs #ident(#(#types),*)));
+                to_borrowed.push(quote!(#name::#ident(#(#args),*) => Borrowed::#ident(#(#args),*)));
+                from_owned.push(quote!(Owned::#ident(#(#args),*) => #name::#ident(#(#args),*)));
+            }
+            Fields::Unit => {
+                borrowed.push(quote!(#attrs #ident));
+                owned.push(quote!(#attrs #ident));
+                to_borrowed.push(quote!(#name::#ident => Borrowed::#ident));
+                from_owned.push(quote!(Owned::#ident => #name::#ident));
+            }
+        }
+    }
+    quote! {
+        const _: () = {
+            #[derive(#serde::Serialize)]
+            #[serde(crate = #serde_path, tag = "type", content = "payload")]
+            enum Borrowed<'a> {
+                #(#borrowed,)*
+                // keeps 'a used when no variant has fields
+                #[serde(skip)]
+                #[allow(dead_code)]
+                Unused(::std::marker::PhantomData<&'a ()>),
+            }
+
+            #[derive(#serde::Deserialize)]
+            #[serde(crate = #serde_path, tag = "type", content = "payload")]
+            enum Owned {
+                #(#owned,)*
+            }
+
+            impl #serde::Serialize for #name {
+                fn serialize<S: #serde::Serializer>(
+                    &self,
+                    serializer: S,
+                ) -> ::std::result::Result<S::Ok, S::Error> {
+                    let borrowed = match self {
+                        #(#to_borrowed,)*
+                    };
+                    #serde::Serialize::serialize(&borrowed, serializer)
+                }
+            }
+
+            impl<'de> #serde::Deserialize<'de> for #name {
+                fn deserialize<D: #serde::Deserializer<'de>>(
+                    deserializer: D,
+                ) -> ::std::result::Result<Self, D::Error> {
+                    ::std::result::Result::Ok(
+                        match <Owned as #serde::Deserialize>::deserialize(deserializer)? {
+                            #(#from_owned,)*
+                        },
+                    )
+                }
+            }
+        };
+    }
+}
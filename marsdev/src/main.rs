@@ -0,0 +1,91 @@
+// tails a mars DevServer and pretty prints what the store does.
+//
+//   marsdev 127.0.0.1:7878        connect over tcp
+//   marsdev /tmp/mars.sock        connect to a unix socket
+//   marsdev <endpoint> --state    print the current state and exit
+use std::{
+    env,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpStream},
+    process,
+};
+
+use serde_json::{json, Value};
+
+fn connect(endpoint: &str) -> io::Result<(Box<dyn Read>, Box<dyn Write>)> {
+    if let Ok(addr) = endpoint.parse::<SocketAddr>() {
+        let stream = TcpStream::connect(addr)?;
+        return Ok((Box::new(stream.try_clone()?), Box::new(stream)));
+    }
+    #[cfg(unix)]
+    {
+        let stream = std::os::unix::net::UnixStream::connect(endpoint)?;
+        Ok((Box::new(stream.try_clone()?), Box::new(stream)))
+    }
+    #[cfg(not(unix))]
+    Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("not a socket address: {}", endpoint),
+    ))
+}
+
+fn compact(value: &Value) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+fn print_event(event: &Value) {
+    match event["event"].as_str() {
+        Some("action") => {
+            println!("#{} {}", event["seq"], compact(&event["action"]));
+            for op in event["diff"].as_array().into_iter().flatten() {
+                let path = op["path"].as_str().unwrap_or("");
+                let path = if path.is_empty() { "/" } else { path };
+                match op["op"].as_str() {
+                    Some("remove") => println!("    - {}", path),
+                    Some("add") => println!("    + {} = {}", path, compact(&op["value"])),
+                    _ => println!("    ~ {} = {}", path, compact(&op["value"])),
+                }
+            }
+        }
+        Some("state") => println!(
+            "state\n{}",
+            serde_json::to_string_pretty(&event["state"]).unwrap_or_default()
+        ),
+        Some("error") => eprintln!("error: {}", event["message"]),
+        _ => println!("{}", compact(event)),
+    }
+}
+
+fn run(endpoint: &str, state_only: bool) -> io::Result<()> {
+    let (read, mut write) = connect(endpoint)?;
+    writeln!(write, "{}", json!({ "request": "get_state" }))?;
+    for line in BufReader::new(read).lines() {
+        let line = line?;
+        match serde_json::from_str::<Value>(&line) {
+            Ok(event) => {
+                print_event(&event);
+                if state_only && event["event"] == "state" {
+                    break;
+                }
+            }
+            Err(_) => println!("{}", line),
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<String>>();
+    let endpoint = match args.iter().find(|arg| !arg.starts_with("--")) {
+        Some(endpoint) => endpoint,
+        None => {
+            eprintln!("usage: marsdev <host:port | socket path> [--state]");
+            process::exit(2);
+        }
+    };
+    let state_only = args.iter().any(|arg| arg == "--state");
+    if let Err(error) = run(endpoint, state_only) {
+        eprintln!("marsdev: {}: {}", endpoint, error);
+        process::exit(1);
+    }
+}
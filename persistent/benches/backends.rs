@@ -0,0 +1,152 @@
+// compares what a store pays for its state on each backend: building a map,
+// looking keys up, taking a snapshot (a clone plus one update) and diffing two
+// snapshots of a map, an ordered map and a vector. only the backends whose
+// features are enabled are measured.
+// run with `cargo bench -p persistent --bench backends`
+#![cfg_attr(
+    not(any(feature = "im", feature = "rpds", feature = "std")),
+    allow(dead_code)
+)]
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use persistent::backend::backend::{Backend, PersistentMap, PersistentVector};
+
+// builds a collection of LEN entries and a copy of it with EDITS entries
+// changed, returns the time one diff between them takes
+fn time_diff<C: Clone>(
+    build: impl Fn() -> C,
+    edit: impl Fn(&mut C, u64),
+    diff: impl Fn(&C, &C),
+) -> Duration {
+    let before = build();
+    let mut after = before.clone();
+    for key in 0..EDITS {
+        edit(&mut after, key * LEN / EDITS);
+    }
+    time(|| {
+        for _ in 0..ROUNDS {
+            diff(&before, &after);
+        }
+    }) / ROUNDS
+}
+
+const LEN: u64 = 100_000;
+const ROUNDS: u32 = 100;
+// entries changed between the two diffed snapshots
+const EDITS: u64 = 10;
+
+struct Costs {
+    insert: Duration,
+    lookup: Duration,
+    clone: Duration,
+    diff: Duration,
+    ord_diff: Duration,
+    push: Duration,
+    vector_diff: Duration,
+}
+
+fn time(mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    f();
+    start.elapsed()
+}
+
+fn measure<B: Backend>() -> Costs {
+    let mut map = B::Map::<u64, u64>::new();
+    let insert = time(|| {
+        for key in 0..LEN {
+            map.insert(key, key);
+        }
+    });
+    let lookup = time(|| {
+        for key in 0..LEN {
+            black_box(map.get(&(key * 7 % LEN)));
+        }
+    });
+    let clone = time(|| {
+        for round in 0..ROUNDS as u64 {
+            let mut snapshot = map.clone();
+            snapshot.insert(round, 0);
+            black_box(snapshot);
+        }
+    }) / ROUNDS;
+    let diff = time_diff(
+        || map.clone(),
+        |map, key| map.insert(key, 0),
+        |before, after| {
+            black_box(before.diff(after));
+        },
+    );
+    let ord_diff = time_diff(
+        || {
+            (0..LEN)
+                .map(|key| (key, key))
+                .collect::<B::OrdMap<u64, u64>>()
+        },
+        |map, key| map.insert(key, 0),
+        |before, after| {
+            black_box(before.diff(after));
+        },
+    );
+    let mut vector = B::Vector::<u64>::new();
+    let push = time(|| {
+        for value in 0..LEN {
+            vector.push_back(value);
+        }
+    });
+    let vector_diff = time_diff(
+        || vector.clone(),
+        |vector, index| {
+            vector.set(index as usize, 0);
+        },
+        |before, after| {
+            black_box(before.diff(after));
+        },
+    );
+    Costs {
+        insert,
+        lookup,
+        clone,
+        diff,
+        ord_diff,
+        push,
+        vector_diff,
+    }
+}
+
+fn report<B: Backend>() {
+    // warm up allocator and caches before measuring
+    measure::<B>();
+    let costs = measure::<B>();
+    println!(
+        "{:>6} {:>12?} {:>12?} {:>12?} {:>12?} {:>12?} {:>12?} {:>12?}",
+        B::NAME,
+        costs.insert,
+        costs.lookup,
+        costs.clone,
+        costs.diff,
+        costs.ord_diff,
+        costs.push,
+        costs.vector_diff
+    );
+}
+
+fn main() {
+    println!(
+        "{} entries, snapshot and diff averaged over {} rounds, {} keys edited before diffing",
+        LEN, ROUNDS, EDITS
+    );
+    println!(
+        "{:>6} {:>12} {:>12} {:>12} {:>12} {:>12} {:>12} {:>12}",
+        "", "insert", "lookup", "snapshot", "map diff", "ordmap diff", "vector push", "vector diff"
+    );
+    #[cfg(feature = "im")]
+    report::<persistent::im::im::Im>();
+    #[cfg(feature = "rpds")]
+    report::<persistent::rpds::rpds::Rpds>();
+    #[cfg(feature = "std")]
+    report::<persistent::stdlib::stdlib::Std>();
+}
@@ -0,0 +1,158 @@
+// allow dead_code when checking the lib without tests
+// cargo-analyzer runs `cargo check` for lib the lib with and without tests.
+// dead_code warnings in the ide won't show if the code is used in tests.
+#![cfg_attr(not(test), allow(dead_code))]
+pub mod backend {
+    use std::hash::Hash;
+
+    /*
+     the collections a state is built from, behind traits so the library
+     providing them can be swapped. updates take &mut self like im does: on a
+     persistent backend they copy only the path to the change and leave every
+     clone of the collection as it was, on the std backend they are plain
+     in place updates and clone() copies everything
+    */
+
+    // one entry that differs between two collections, from the first to the
+    // second. maps report their keys, vectors their indexes
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum Change<K, V> {
+        Insert(K, V),
+        Remove(K, V),
+        Update(K, V, V),
+    }
+
+    pub trait PersistentVector<T: Clone>: Clone + Default + FromIterator<T> {
+        type Iter<'a>: Iterator<Item = &'a T>
+        where
+            Self: 'a,
+            T: 'a;
+        fn new() -> Self;
+        fn len(&self) -> usize;
+        fn is_empty(&self) -> bool {
+            self.len() == 0
+        }
+        fn get(&self, index: usize) -> Option<&T>;
+        // returns false, leaving the vector as it is, when index is out of bounds
+        fn set(&mut self, index: usize, value: T) -> bool;
+        fn push_back(&mut self, value: T);
+        fn pop_back(&mut self) -> Option<T>;
+        fn iter(&self) -> Self::Iter<'_>;
+        // true only if both share their whole structure, e.g. one is an
+        // untouched clone of the other. false says nothing
+        fn ptr_eq(&self, _other: &Self) -> bool {
+            false
+        }
+        // index by index, then what other pushed or self had past other's end
+        fn diff<'a>(&'a self, other: &'a Self) -> Vec<Change<usize, &'a T>>
+        where
+            T: PartialEq,
+        {
+            if self.ptr_eq(other) {
+                return Vec::new();
+            }
+            let mut changes = Vec::new();
+            let mut before = self.iter();
+            let mut after = other.iter();
+            for index in 0.. {
+                match (before.next(), after.next()) {
+                    (Some(old), Some(new)) if old != new => {
+                        changes.push(Change::Update(index, old, new))
+                    }
+                    (Some(_), Some(_)) => {}
+                    (Some(old), None) => changes.push(Change::Remove(index, old)),
+                    (None, Some(new)) => changes.push(Change::Insert(index, new)),
+                    (None, None) => break,
+                }
+            }
+            changes
+        }
+    }
+
+    pub trait PersistentMap<K: Clone, V: Clone>: Clone + Default + FromIterator<(K, V)> {
+        type Iter<'a>: Iterator<Item = (&'a K, &'a V)>
+        where
+            Self: 'a,
+            K: 'a,
+            V: 'a;
+        fn new() -> Self;
+        fn len(&self) -> usize;
+        fn is_empty(&self) -> bool {
+            self.len() == 0
+        }
+        fn get(&self, key: &K) -> Option<&V>;
+        fn contains_key(&self, key: &K) -> bool {
+            self.get(key).is_some()
+        }
+        fn insert(&mut self, key: K, value: V);
+        // returns whether the key was there
+        fn remove(&mut self, key: &K) -> bool;
+        fn iter(&self) -> Self::Iter<'_>;
+        // see PersistentVector::ptr_eq
+        fn ptr_eq(&self, _other: &Self) -> bool {
+            false
+        }
+        // looks every key of both maps up in the other one. backends that can
+        // skip the structure both maps share override it
+        fn diff<'a>(&'a self, other: &'a Self) -> Vec<Change<&'a K, &'a V>>
+        where
+            V: PartialEq,
+        {
+            if self.ptr_eq(other) {
+                return Vec::new();
+            }
+            let mut changes = Vec::new();
+            for (key, old) in self.iter() {
+                match other.get(key) {
+                    Some(new) if new != old => changes.push(Change::Update(key, old, new)),
+                    Some(_) => {}
+                    None => changes.push(Change::Remove(key, old)),
+                }
+            }
+            for (key, new) in other.iter() {
+                if !self.contains_key(key) {
+                    changes.push(Change::Insert(key, new));
+                }
+            }
+            changes
+        }
+    }
+
+    // a map whose iter() goes through the keys in order
+    pub trait PersistentOrdMap<K: Ord + Clone, V: Clone>: PersistentMap<K, V> {
+        fn first(&self) -> Option<(&K, &V)>;
+        fn last(&self) -> Option<(&K, &V)>;
+    }
+
+    /*
+     a set of collection types. code generic over B: Backend names its
+     collections B::Vector<T>, B::Map<K, V> and B::OrdMap<K, V>. every
+     backend has to pass the tests conformance! generates for it
+    */
+    pub trait Backend: 'static {
+        const NAME: &'static str;
+        type Vector<T: Clone>: PersistentVector<T>;
+        type Map<K: Hash + Eq + Clone, V: Clone>: PersistentMap<K, V>;
+        type OrdMap<K: Ord + Clone, V: Clone>: PersistentOrdMap<K, V>;
+    }
+
+    /*
+     selected_backend!() defines `pub type Selected` as the backend the calling
+     crate's features pick: im, else rpds, else std. crates built on a backend
+     offer im, rpds and std features that enable the same feature here, and
+     name their collections through Selected
+    */
+    #[macro_export]
+    macro_rules! selected_backend {
+        () => {
+            #[cfg(feature = "im")]
+            pub type Selected = $crate::im::im::Im;
+            #[cfg(all(feature = "rpds", not(feature = "im")))]
+            pub type Selected = $crate::rpds::rpds::Rpds;
+            #[cfg(all(feature = "std", not(any(feature = "im", feature = "rpds"))))]
+            pub type Selected = $crate::stdlib::stdlib::Std;
+            #[cfg(not(any(feature = "im", feature = "rpds", feature = "std")))]
+            compile_error!("enable one of the im, rpds or std features");
+        };
+    }
+}
@@ -0,0 +1,195 @@
+// allow dead_code when checking the lib without tests
+// cargo-analyzer runs `cargo check` for lib the lib with and without tests.
+// dead_code warnings in the ide won't show if the code is used in tests.
+#![cfg_attr(not(test), allow(dead_code))]
+pub mod conformance {
+    use std::fmt::Debug;
+
+    use crate::backend::backend::{
+        Backend, Change, PersistentMap, PersistentOrdMap, PersistentVector,
+    };
+
+    /*
+     the behaviour every backend has to share, written against the traits
+     only. conformance!(name, Backend) turns each check into a #[test] in a
+     module called name, so a backend defined in another crate runs the same
+     suite from its own tests
+    */
+
+    // conformance!(im, persistent::im::im::Im);
+    #[macro_export]
+    macro_rules! conformance {
+        ($name:ident, $backend:ty) => {
+            #[cfg(test)]
+            mod $name {
+                #[test]
+                fn vector() {
+                    $crate::conformance::conformance::vector::<$backend>();
+                }
+                #[test]
+                fn map() {
+                    $crate::conformance::conformance::map::<$backend>();
+                }
+                #[test]
+                fn ord_map() {
+                    $crate::conformance::conformance::ord_map::<$backend>();
+                }
+                #[test]
+                fn clones_are_independent() {
+                    $crate::conformance::conformance::clones_are_independent::<$backend>();
+                }
+                #[test]
+                fn diff() {
+                    $crate::conformance::conformance::diff::<$backend>();
+                }
+            }
+        };
+    }
+
+    fn contents<'a, T: Clone + 'a, V: PersistentVector<T>>(vector: &'a V) -> Vec<T> {
+        vector.iter().cloned().collect()
+    }
+
+    // map iteration order is up to the backend
+    fn sorted<K: Ord + Clone, V: Clone + Ord, M: PersistentMap<K, V>>(map: &M) -> Vec<(K, V)> {
+        let mut entries = map
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect::<Vec<_>>();
+        entries.sort();
+        entries
+    }
+
+    fn sorted_changes<K: Ord + Debug, V: Ord + Debug>(
+        mut changes: Vec<Change<K, V>>,
+    ) -> Vec<String> {
+        changes.sort_by(|a, b| key_of(a).cmp(key_of(b)));
+        changes
+            .iter()
+            .map(|change| format!("{:?}", change))
+            .collect()
+    }
+
+    fn key_of<K, V>(change: &Change<K, V>) -> &K {
+        match change {
+            Change::Insert(key, _) | Change::Remove(key, _) | Change::Update(key, _, _) => key,
+        }
+    }
+
+    pub fn vector<B: Backend>() {
+        let mut vector = B::Vector::<i32>::new();
+        assert!(vector.is_empty());
+        assert_eq!(vector.pop_back(), None);
+        for n in 0..100 {
+            vector.push_back(n);
+        }
+        assert_eq!(vector.len(), 100);
+        assert_eq!(vector.get(42), Some(&42));
+        assert_eq!(vector.get(100), None);
+        assert!(vector.set(42, -42));
+        assert!(!vector.set(100, 0));
+        assert_eq!(vector.get(42), Some(&-42));
+        assert_eq!(vector.pop_back(), Some(99));
+        assert_eq!(vector.len(), 99);
+        assert_eq!(contents(&vector)[40..44], [40, 41, -42, 43]);
+        let collected = (0..3).collect::<B::Vector<i32>>();
+        assert_eq!(contents(&collected), [0, 1, 2]);
+    }
+
+    pub fn map<B: Backend>() {
+        let mut map = B::Map::<String, i32>::new();
+        assert!(map.is_empty());
+        for n in 0..100 {
+            map.insert(n.to_string(), n);
+        }
+        assert_eq!(map.len(), 100);
+        assert_eq!(map.get(&"7".to_string()), Some(&7));
+        map.insert("7".to_string(), -7);
+        assert_eq!(map.get(&"7".to_string()), Some(&-7));
+        assert_eq!(map.len(), 100);
+        assert!(map.remove(&"7".to_string()));
+        assert!(!map.remove(&"7".to_string()));
+        assert!(!map.contains_key(&"7".to_string()));
+        assert_eq!(map.iter().count(), 99);
+        let collected = [(1, 1), (2, 2)].into_iter().collect::<B::Map<i32, i32>>();
+        assert_eq!(sorted(&collected), [(1, 1), (2, 2)]);
+    }
+
+    pub fn ord_map<B: Backend>() {
+        let mut map = B::OrdMap::<i32, &str>::new();
+        assert_eq!(map.first(), None);
+        for (key, value) in [(3, "c"), (1, "a"), (2, "b")] {
+            map.insert(key, value);
+        }
+        let keys = map.iter().map(|(key, _)| *key).collect::<Vec<_>>();
+        assert_eq!(keys, [1, 2, 3]);
+        assert_eq!(map.first(), Some((&1, &"a")));
+        assert_eq!(map.last(), Some((&3, &"c")));
+        assert!(map.remove(&3));
+        assert_eq!(map.last(), Some((&2, &"b")));
+    }
+
+    // updating a clone never shows through the original, however the backend
+    // shares structure
+    pub fn clones_are_independent<B: Backend>() {
+        let vector = (0..50).collect::<B::Vector<i32>>();
+        let mut changed = vector.clone();
+        assert!(changed.set(0, 100));
+        changed.push_back(50);
+        assert_eq!(contents(&vector), (0..50).collect::<Vec<_>>());
+        assert_eq!(changed.len(), 51);
+
+        let map = (0..50).map(|n| (n, n)).collect::<B::Map<i32, i32>>();
+        let mut changed = map.clone();
+        changed.insert(0, 100);
+        changed.remove(&1);
+        assert_eq!(sorted(&map), (0..50).map(|n| (n, n)).collect::<Vec<_>>());
+        assert_eq!(changed.get(&0), Some(&100));
+
+        let map = (0..50).map(|n| (n, n)).collect::<B::OrdMap<i32, i32>>();
+        let mut changed = map.clone();
+        changed.remove(&0);
+        assert_eq!(map.first(), Some((&0, &0)));
+        assert_eq!(changed.first(), Some((&1, &1)));
+    }
+
+    pub fn diff<B: Backend>() {
+        let before = (0..5).collect::<B::Vector<i32>>();
+        assert!(before.diff(&before.clone()).is_empty());
+        let mut after = before.clone();
+        after.set(1, 10);
+        after.push_back(5);
+        assert_eq!(
+            before.diff(&after),
+            [Change::Update(1, &1, &10), Change::Insert(5, &5)]
+        );
+        assert_eq!(after.diff(&before)[1], Change::Remove(5, &5));
+
+        let before = (0..5).map(|n| (n, n)).collect::<B::Map<i32, i32>>();
+        assert!(before.diff(&before.clone()).is_empty());
+        let mut after = before.clone();
+        after.insert(1, 10);
+        after.remove(&2);
+        after.insert(7, 7);
+        let expected = ["Update(1, 1, 10)", "Remove(2, 2)", "Insert(7, 7)"];
+        assert_eq!(sorted_changes(before.diff(&after)), expected);
+
+        let before = (0..100).map(|n| (n, n)).collect::<B::OrdMap<i32, i32>>();
+        assert!(before.diff(&before.clone()).is_empty());
+        let mut after = before.clone();
+        after.insert(1, 10);
+        after.remove(&2);
+        after.insert(700, 7);
+        let expected = ["Update(1, 1, 10)", "Remove(2, 2)", "Insert(700, 7)"];
+        assert_eq!(sorted_changes(before.diff(&after)), expected);
+    }
+}
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "im")]
+    crate::conformance!(im, crate::im::im::Im);
+    #[cfg(feature = "rpds")]
+    crate::conformance!(rpds, crate::rpds::rpds::Rpds);
+    #[cfg(feature = "std")]
+    crate::conformance!(std, crate::stdlib::stdlib::Std);
+}
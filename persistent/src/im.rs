@@ -0,0 +1,144 @@
+// allow dead_code when checking the lib without tests
+// cargo-analyzer runs `cargo check` for lib the lib with and without tests.
+// dead_code warnings in the ide won't show if the code is used in tests.
+#![cfg_attr(not(test), allow(dead_code))]
+pub mod im {
+    use std::hash::Hash;
+
+    use ::im::{
+        hashmap,
+        ordmap::{self, DiffItem},
+        vector, HashMap, OrdMap, Vector,
+    };
+
+    use crate::backend::backend::{
+        Backend, Change, PersistentMap, PersistentOrdMap, PersistentVector,
+    };
+
+    // im's rrb vector, hash array mapped trie and b-tree
+    pub struct Im;
+
+    impl Backend for Im {
+        const NAME: &'static str = "im";
+        type Vector<T: Clone> = Vector<T>;
+        type Map<K: Hash + Eq + Clone, V: Clone> = HashMap<K, V>;
+        type OrdMap<K: Ord + Clone, V: Clone> = OrdMap<K, V>;
+    }
+
+    impl<T: Clone> PersistentVector<T> for Vector<T> {
+        type Iter<'a>
+            = vector::Iter<'a, T>
+        where
+            T: 'a;
+        fn new() -> Self {
+            Vector::new()
+        }
+        fn len(&self) -> usize {
+            self.len()
+        }
+        fn get(&self, index: usize) -> Option<&T> {
+            self.get(index)
+        }
+        fn set(&mut self, index: usize, value: T) -> bool {
+            match self.get_mut(index) {
+                Some(slot) => {
+                    *slot = value;
+                    true
+                }
+                None => false,
+            }
+        }
+        fn push_back(&mut self, value: T) {
+            self.push_back(value)
+        }
+        fn pop_back(&mut self) -> Option<T> {
+            self.pop_back()
+        }
+        fn iter(&self) -> vector::Iter<'_, T> {
+            self.iter()
+        }
+        fn ptr_eq(&self, other: &Self) -> bool {
+            self.ptr_eq(other)
+        }
+    }
+
+    impl<K: Hash + Eq + Clone, V: Clone> PersistentMap<K, V> for HashMap<K, V> {
+        type Iter<'a>
+            = hashmap::Iter<'a, K, V>
+        where
+            K: 'a,
+            V: 'a;
+        fn new() -> Self {
+            HashMap::new()
+        }
+        fn len(&self) -> usize {
+            self.len()
+        }
+        fn get(&self, key: &K) -> Option<&V> {
+            self.get(key)
+        }
+        fn insert(&mut self, key: K, value: V) {
+            self.insert(key, value);
+        }
+        fn remove(&mut self, key: &K) -> bool {
+            self.remove(key).is_some()
+        }
+        fn iter(&self) -> hashmap::Iter<'_, K, V> {
+            self.iter()
+        }
+        fn ptr_eq(&self, other: &Self) -> bool {
+            self.ptr_eq(other)
+        }
+    }
+
+    impl<K: Ord + Clone, V: Clone> PersistentMap<K, V> for OrdMap<K, V> {
+        type Iter<'a>
+            = ordmap::Iter<'a, K, V>
+        where
+            K: 'a,
+            V: 'a;
+        fn new() -> Self {
+            OrdMap::new()
+        }
+        fn len(&self) -> usize {
+            self.len()
+        }
+        fn get(&self, key: &K) -> Option<&V> {
+            self.get(key)
+        }
+        fn insert(&mut self, key: K, value: V) {
+            self.insert(key, value);
+        }
+        fn remove(&mut self, key: &K) -> bool {
+            self.remove(key).is_some()
+        }
+        fn iter(&self) -> ordmap::Iter<'_, K, V> {
+            self.iter()
+        }
+        // walks both b-trees together, skipping the nodes they share
+        fn diff<'a>(&'a self, other: &'a Self) -> Vec<Change<&'a K, &'a V>>
+        where
+            V: PartialEq,
+        {
+            self.diff(other)
+                .map(|item| match item {
+                    DiffItem::Add(key, new) => Change::Insert(key, new),
+                    DiffItem::Update {
+                        old: (key, old),
+                        new: (_, new),
+                    } => Change::Update(key, old, new),
+                    DiffItem::Remove(key, old) => Change::Remove(key, old),
+                })
+                .collect()
+        }
+    }
+
+    impl<K: Ord + Clone, V: Clone> PersistentOrdMap<K, V> for OrdMap<K, V> {
+        fn first(&self) -> Option<(&K, &V)> {
+            self.get_min().map(|(key, value)| (key, value))
+        }
+        fn last(&self) -> Option<(&K, &V)> {
+            self.get_max().map(|(key, value)| (key, value))
+        }
+    }
+}
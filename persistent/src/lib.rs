@@ -0,0 +1,11 @@
+// every module wraps its items in a module of the same name
+#![allow(clippy::module_inception)]
+
+pub mod backend;
+pub mod conformance;
+#[cfg(feature = "im")]
+pub mod im;
+#[cfg(feature = "rpds")]
+pub mod rpds;
+#[cfg(feature = "std")]
+pub mod stdlib;
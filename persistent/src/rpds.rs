@@ -0,0 +1,121 @@
+// allow dead_code when checking the lib without tests
+// cargo-analyzer runs `cargo check` for lib the lib with and without tests.
+// dead_code warnings in the ide won't show if the code is used in tests.
+#![cfg_attr(not(test), allow(dead_code))]
+pub mod rpds {
+    use std::hash::Hash;
+
+    use ::rpds::{
+        map::{hash_trie_map, red_black_tree_map},
+        vector, HashTrieMapSync, RedBlackTreeMapSync, VectorSync,
+    };
+    use archery::ArcK;
+
+    use crate::backend::backend::{Backend, PersistentMap, PersistentOrdMap, PersistentVector};
+
+    /*
+     rpds' vector, hash trie map and red-black tree, the *Sync flavours so
+     states can be sent between threads. rpds keeps no way to tell whether two
+     collections share their root, so ptr_eq is always false and diff looks
+     every key up
+    */
+    pub struct Rpds;
+
+    impl Backend for Rpds {
+        const NAME: &'static str = "rpds";
+        type Vector<T: Clone> = VectorSync<T>;
+        type Map<K: Hash + Eq + Clone, V: Clone> = HashTrieMapSync<K, V>;
+        type OrdMap<K: Ord + Clone, V: Clone> = RedBlackTreeMapSync<K, V>;
+    }
+
+    impl<T: Clone> PersistentVector<T> for VectorSync<T> {
+        type Iter<'a>
+            = vector::Iter<'a, T, ArcK>
+        where
+            T: 'a;
+        fn new() -> Self {
+            VectorSync::new_sync()
+        }
+        fn len(&self) -> usize {
+            self.len()
+        }
+        fn get(&self, index: usize) -> Option<&T> {
+            self.get(index)
+        }
+        fn set(&mut self, index: usize, value: T) -> bool {
+            self.set_mut(index, value)
+        }
+        fn push_back(&mut self, value: T) {
+            self.push_back_mut(value)
+        }
+        fn pop_back(&mut self) -> Option<T> {
+            let last = self.last().cloned();
+            self.drop_last_mut();
+            last
+        }
+        fn iter(&self) -> vector::Iter<'_, T, ArcK> {
+            self.iter()
+        }
+    }
+
+    impl<K: Hash + Eq + Clone, V: Clone> PersistentMap<K, V> for HashTrieMapSync<K, V> {
+        type Iter<'a>
+            = hash_trie_map::Iter<'a, K, V, ArcK>
+        where
+            K: 'a,
+            V: 'a;
+        fn new() -> Self {
+            HashTrieMapSync::new_sync()
+        }
+        fn len(&self) -> usize {
+            self.size()
+        }
+        fn get(&self, key: &K) -> Option<&V> {
+            self.get(key)
+        }
+        fn insert(&mut self, key: K, value: V) {
+            self.insert_mut(key, value)
+        }
+        fn remove(&mut self, key: &K) -> bool {
+            self.remove_mut(key)
+        }
+        fn iter(&self) -> hash_trie_map::Iter<'_, K, V, ArcK> {
+            self.iter()
+        }
+    }
+
+    impl<K: Ord + Clone, V: Clone> PersistentMap<K, V> for RedBlackTreeMapSync<K, V> {
+        type Iter<'a>
+            = red_black_tree_map::Iter<'a, K, V, ArcK>
+        where
+            K: 'a,
+            V: 'a;
+        fn new() -> Self {
+            RedBlackTreeMapSync::new_sync()
+        }
+        fn len(&self) -> usize {
+            self.size()
+        }
+        fn get(&self, key: &K) -> Option<&V> {
+            self.get(key)
+        }
+        fn insert(&mut self, key: K, value: V) {
+            self.insert_mut(key, value)
+        }
+        fn remove(&mut self, key: &K) -> bool {
+            self.remove_mut(key)
+        }
+        fn iter(&self) -> red_black_tree_map::Iter<'_, K, V, ArcK> {
+            self.iter()
+        }
+    }
+
+    impl<K: Ord + Clone, V: Clone> PersistentOrdMap<K, V> for RedBlackTreeMapSync<K, V> {
+        fn first(&self) -> Option<(&K, &V)> {
+            self.first()
+        }
+        fn last(&self) -> Option<(&K, &V)> {
+            self.last()
+        }
+    }
+}
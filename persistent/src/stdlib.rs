@@ -0,0 +1,120 @@
+// allow dead_code when checking the lib without tests
+// cargo-analyzer runs `cargo check` for lib the lib with and without tests.
+// dead_code warnings in the ide won't show if the code is used in tests.
+#![cfg_attr(not(test), allow(dead_code))]
+pub mod stdlib {
+    use std::{
+        collections::{btree_map, hash_map, BTreeMap, HashMap},
+        hash::Hash,
+        slice,
+    };
+
+    use crate::backend::backend::{Backend, PersistentMap, PersistentOrdMap, PersistentVector};
+
+    // Vec, HashMap and BTreeMap. not persistent at all: every clone copies the
+    // whole collection. the baseline the persistent backends are measured
+    // against, and fine for small states
+    pub struct Std;
+
+    impl Backend for Std {
+        const NAME: &'static str = "std";
+        type Vector<T: Clone> = Vec<T>;
+        type Map<K: Hash + Eq + Clone, V: Clone> = HashMap<K, V>;
+        type OrdMap<K: Ord + Clone, V: Clone> = BTreeMap<K, V>;
+    }
+
+    impl<T: Clone> PersistentVector<T> for Vec<T> {
+        type Iter<'a>
+            = slice::Iter<'a, T>
+        where
+            T: 'a;
+        fn new() -> Self {
+            Vec::new()
+        }
+        fn len(&self) -> usize {
+            self.len()
+        }
+        fn get(&self, index: usize) -> Option<&T> {
+            self.as_slice().get(index)
+        }
+        fn set(&mut self, index: usize, value: T) -> bool {
+            match self.get_mut(index) {
+                Some(slot) => {
+                    *slot = value;
+                    true
+                }
+                None => false,
+            }
+        }
+        fn push_back(&mut self, value: T) {
+            self.push(value)
+        }
+        fn pop_back(&mut self) -> Option<T> {
+            self.pop()
+        }
+        fn iter(&self) -> slice::Iter<'_, T> {
+            self.as_slice().iter()
+        }
+    }
+
+    impl<K: Hash + Eq + Clone, V: Clone> PersistentMap<K, V> for HashMap<K, V> {
+        type Iter<'a>
+            = hash_map::Iter<'a, K, V>
+        where
+            K: 'a,
+            V: 'a;
+        fn new() -> Self {
+            HashMap::new()
+        }
+        fn len(&self) -> usize {
+            self.len()
+        }
+        fn get(&self, key: &K) -> Option<&V> {
+            self.get(key)
+        }
+        fn insert(&mut self, key: K, value: V) {
+            self.insert(key, value);
+        }
+        fn remove(&mut self, key: &K) -> bool {
+            self.remove(key).is_some()
+        }
+        fn iter(&self) -> hash_map::Iter<'_, K, V> {
+            self.iter()
+        }
+    }
+
+    impl<K: Ord + Clone, V: Clone> PersistentMap<K, V> for BTreeMap<K, V> {
+        type Iter<'a>
+            = btree_map::Iter<'a, K, V>
+        where
+            K: 'a,
+            V: 'a;
+        fn new() -> Self {
+            BTreeMap::new()
+        }
+        fn len(&self) -> usize {
+            self.len()
+        }
+        fn get(&self, key: &K) -> Option<&V> {
+            self.get(key)
+        }
+        fn insert(&mut self, key: K, value: V) {
+            self.insert(key, value);
+        }
+        fn remove(&mut self, key: &K) -> bool {
+            self.remove(key).is_some()
+        }
+        fn iter(&self) -> btree_map::Iter<'_, K, V> {
+            self.iter()
+        }
+    }
+
+    impl<K: Ord + Clone, V: Clone> PersistentOrdMap<K, V> for BTreeMap<K, V> {
+        fn first(&self) -> Option<(&K, &V)> {
+            self.first_key_value()
+        }
+        fn last(&self) -> Option<(&K, &V)> {
+            self.last_key_value()
+        }
+    }
+}
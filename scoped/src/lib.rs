@@ -4,7 +4,6 @@
 // #![cfg_attr(not(test), allow(dead_code))]
 #![allow(dead_code)]
 mod store {
-    extern crate im;
     use std::{
         cell::RefCell,
         marker::PhantomData,
@@ -18,7 +17,7 @@ mod store {
         phantom_closure: PhantomData<&'b C>,
     }
 
-    impl<'b, 'a, T, C: 'b + FnMut(T, T) -> bool + std::marker::Sync> Store<'b, T, C>
+    impl<'b, T, C: 'b + FnMut(T, T) -> bool + std::marker::Sync> Store<'b, T, C>
     where
         Self: 'b,
         T: 'b + Send + Sync + Clone,
@@ -68,14 +67,18 @@ mod store {
 
 // #[cfg(test)]
 mod tests {
-    use im::Vector;
+    use persistent::backend::backend::{Backend, PersistentVector};
 
     use crate::store::Store;
     use std::thread;
 
+    persistent::selected_backend!();
+
+    type Vector<T> = <Selected as Backend>::Vector<T>;
+
     // #[test]
     fn vec_thread_access() {
-        let initial_state = Vector::<i32>::new();
+        let initial_state = <Vector<i32> as PersistentVector<i32>>::new();
         let store = Store::new(initial_state);
         store.start(move |mut state, new_state| {
             println!("state len: {}", state.len());
@@ -94,8 +97,8 @@ mod tests {
                 println!("reader started");
                 let mut count = 0;
                 loop {
-                    let mut new_state = Vector::<i32>::new();
-                    new_state.push_front(count);
+                    let mut new_state = <Vector<i32> as PersistentVector<i32>>::new();
+                    PersistentVector::push_back(&mut new_state, count);
                     _ = dispatch.send(new_state);
                     count += 1;
                     if count == 10 {
@@ -4,47 +4,53 @@
 #![cfg_attr(not(test), allow(dead_code))]
 
 pub(crate) mod immutable {
-    use im::Vector;
-    pub(crate) struct UndoHistory<T> {
-        history: Vector<T>,
+    use persistent::backend::backend::{Backend, PersistentVector};
+
+    persistent::selected_backend!();
+
+    /*
+     the history is kept on a persistent backend, the one the crate's features
+     select unless another one is picked. index 0 is the newest state; the
+     backend vectors only grow at the back, so the newest state sits at the
+     back of the vector
+    */
+    pub(crate) struct UndoHistory<T: Clone, B: Backend = Selected> {
+        history: B::Vector<T>,
         current: usize,
     }
-    impl<T> UndoHistory<T>
+    impl<T, B> UndoHistory<T, B>
     where
         T: Clone,
+        B: Backend,
     {
         pub fn new(initial_state: T) -> Self {
-            let mut state = Vector::<T>::new();
-            state.push_front(initial_state);
+            let mut state = B::Vector::<T>::new();
+            state.push_back(initial_state);
             UndoHistory {
                 history: state,
                 current: 0,
             }
         }
         fn get(&self, index: usize) -> Option<T> {
-            if !self.history.is_empty() && index < self.history.len() {
-                return self.history.get(index).cloned();
-            }
-            return None;
+            let newest = self.history.len().checked_sub(1)?;
+            self.history.get(newest.checked_sub(index)?).cloned()
         }
         // save a new state to the history, saves the current state first
         pub fn save(&mut self, value: T) {
-            // if the current index is in the past, push the current value to the front first
+            // if the current index is in the past, push the current value as the newest first
             if self.current > 0 {
                 let current = self.get(self.current);
-                self.history.push_front(current.unwrap());
+                self.history.push_back(current.unwrap());
             }
-            self.history.push_front(value);
+            self.history.push_back(value);
             self.current = 0;
         }
         pub fn current(&self) -> Option<T> {
-            if self.history.is_empty() {
-                return None;
-            }
             self.get(self.current)
         }
+        // stops at the oldest state
         pub fn undo(&mut self) -> Option<T> {
-            if self.current < self.history.len() {
+            if self.current + 1 < self.history.len() {
                 self.current += 1;
             }
             self.current()
@@ -65,7 +71,9 @@ pub(crate) mod immutable {
 }
 #[cfg(test)]
 mod tests {
-    use super::immutable::UndoHistory;
+    use super::immutable::{self, Selected};
+
+    type UndoHistory<T> = immutable::UndoHistory<T, Selected>;
     #[test]
     fn undo_redo() {
         let initial_state = "initial".to_string();
@@ -89,6 +97,8 @@ mod tests {
         history.undo();
         history.undo();
         assert_eq!(history.current(), initial_test_state);
+        history.undo();
+        assert_eq!(history.current(), initial_test_state);
         history.redo();
         assert_eq!(history.current().unwrap(), "z".to_string());
     }
@@ -102,11 +112,15 @@ mod tests {
         assert_eq!(history.current().unwrap(), "z".to_string());
         history.save("y".to_string());
         assert_eq!(history.current().unwrap(), "y".to_string());
+        // index 0 is the newest state
         history.load(0);
-        assert_eq!(history.current().unwrap(), "z".to_string());
-        history.load(1);
         assert_eq!(history.current().unwrap(), "y".to_string());
+        history.load(1);
+        assert_eq!(history.current().unwrap(), "z".to_string());
         history.load(2);
         assert_eq!(history.current(), initial_test_state);
+        // an index past the oldest state changes nothing
+        history.load(3);
+        assert_eq!(history.current(), initial_test_state);
     }
 }
@@ -1,2 +1,5 @@
+// every module wraps its items in a module of the same name
+#![allow(clippy::module_inception)]
+
+pub mod imhistory;
 pub mod simple;
-pub mod imhistory;
\ No newline at end of file
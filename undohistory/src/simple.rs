@@ -38,6 +38,10 @@ pub mod simple {
         pub fn len(&self) -> usize {
             self.history.len()
         }
+        // never true, the initial state is always kept
+        pub fn is_empty(&self) -> bool {
+            self.history.is_empty()
+        }
     }
 }
 #[cfg(test)]